@@ -5,16 +5,30 @@ use std::{
 
 use egui::{Frame, Key, KeyboardShortcut, Modifiers};
 use egui_dock::{DockArea, DockState};
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 
 use crate::{
     channels::Channels,
-    components::{ComponentChannels, ComponentTab, Components, playback::PLAYBACK_BAR_HEIGHT},
-    config::core::SharedConfig,
-    context::SharedContext,
-    database::connection::{DatabaseCommand, DatabaseError, DatabaseEvent},
-    files::open::{get_folder_tracks, select_file_dialog, select_folders_dialog},
-    playback::state::PlayerCommand,
+    components::{
+        ComponentChannels, ComponentTab, Components, DOCK_STATE_STORAGE_KEY,
+        playback::PLAYBACK_BAR_HEIGHT,
+    },
+    config::{core::SharedConfig, general::SessionRestore, library::LibraryConfig},
+    context::{
+        Context, PendingRemoval, PlayDirection, SharedContext, storage::TRACK_SORTS_STORAGE_KEY,
+        ui::LayoutRequest,
+    },
+    database::{
+        connection::{DatabaseCommand, DatabaseError, DatabaseEvent},
+        models::tracks::Track,
+    },
+    files::{
+        open::{select_file_dialog, select_folders_dialog},
+        watch::LibraryWatcher,
+    },
+    playback::state::{PlayerCommand, PlayerEvent},
+    session::{SESSION_STORAGE_KEY, Session},
+    tasks::TaskKind,
 };
 
 pub struct App {
@@ -23,30 +37,68 @@ pub struct App {
     channels: Rc<Channels>,
     components: Components,
     dock_state: DockState<ComponentTab>,
+    /// When the library was last validated, or the app started if it hasn't been
+    last_validation: Instant,
+    /// Watches the library folders for changes, if any are configured
+    library_watcher: Option<LibraryWatcher>,
+    /// The library config that the watcher was started with, to notice when it changes
+    watched_library: LibraryConfig,
 }
 
 impl App {
     pub fn new(
-        _cc: &eframe::CreationContext<'_>,
+        cc: &eframe::CreationContext<'_>,
         config: SharedConfig,
         channels: Rc<Channels>,
     ) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
 
-        // Load previous app state (if any).
-        // if let Some(storage) = cc.storage {
-        //     return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-        // }
-
         let context = SharedContext::default();
+        context
+            .borrow_mut()
+            .playback
+            .autoplay
+            .set_autoplay(config.borrow().playback.autoplay.clone());
+
+        // Sort tracks the same way as last time, before any are loaded
+        if let Some(track_sorts) = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, TRACK_SORTS_STORAGE_KEY))
+        {
+            context.borrow_mut().storage.set_track_sorts(track_sorts);
+        }
 
         let component_channels = Rc::new(ComponentChannels::new(
             channels.database_command_tx.clone(),
             channels.player_command_tx.clone(),
         ));
-        let components = Components::new(config.clone(), context.clone(), component_channels);
-        let dock_state = components.component_tab_layout();
+        let mut components = Components::new(config.clone(), context.clone(), component_channels);
+
+        // Load the previous session (if any), to be resumed once the library has loaded
+        let session = if config.borrow().general.restore_session == SessionRestore::Off {
+            None
+        } else {
+            cc.storage
+                .and_then(|storage| eframe::get_value::<Session>(storage, SESSION_STORAGE_KEY))
+        };
+
+        if let Some(session) = session {
+            components.resume_session.set_session(session);
+        }
+
+        // Restore the layout of tabs the way it was arranged last time
+        let dock_state = cc
+            .storage
+            .and_then(|storage| {
+                eframe::get_value::<DockState<ComponentTab>>(storage, DOCK_STATE_STORAGE_KEY)
+            })
+            .unwrap_or_else(|| components.component_tab_layout());
+
+        // Queued behind the initial queries, so the validated tracks are updated once they're loaded
+        if config.borrow().general.validate_on_startup {
+            components.task_table.validate_tracks();
+        }
 
         Self {
             config,
@@ -54,22 +106,36 @@ impl App {
             channels,
             components,
             dock_state,
+            last_validation: Instant::now(),
+            library_watcher: None,
+            watched_library: LibraryConfig::default(),
         }
     }
 
     fn handle_database_event_error(&mut self, err: DatabaseError) {
         match err {
-            DatabaseError::DuplicateTrack(_track) => {
-                self.context.borrow_mut().processing.decrement(None);
+            DatabaseError::DuplicateTrack(track) => {
+                debug!("Track {:?} is already in the library", track);
+            }
+            DatabaseError::DuplicatePlaylistTrack(track, playlist) => {
+                debug!("Track {:?} is already in playlist {}", track, playlist.name);
             }
-            DatabaseError::DuplicatePlaylistTrack(_track, playlist) => {
+            DatabaseError::DuplicatePlaylist(name) => {
+                warn!("A playlist named {} already exists", name);
+
                 self.context
                     .borrow_mut()
-                    .processing
-                    .decrement(Some(playlist.name));
+                    .ui
+                    .playlist
+                    .set_error(Some(format!("A playlist named {name} already exists")));
             }
-            DatabaseError::DuplicatePlaylist => {
-                todo!();
+            DatabaseError::DuplicateTag(name) => {
+                warn!("A tag named {} already exists", name);
+                self.context
+                    .borrow_mut()
+                    .ui
+                    .tag
+                    .set_error(Some(format!("A tag named {name} already exists")));
             }
             DatabaseError::DatabaseUnavailable => {
                 todo!();
@@ -80,37 +146,62 @@ impl App {
         }
     }
 
+    /// Handles every event the database has sent since the last frame,
+    /// so a busy database thread doesn't leave the UI further and further behind.
     fn handle_database_events(&mut self) {
-        let Some(database_event_result) = self.channels.database_event_rx.try_recv().ok() else {
-            return;
-        };
+        while let Ok(database_event_result) = self.channels.database_event_rx.try_recv() {
+            match database_event_result {
+                Ok(database_event) => self.handle_database_event(database_event),
+                Err(err) => self.handle_database_event_error(err),
+            }
+        }
+    }
 
+    fn handle_database_event(&mut self, database_event: DatabaseEvent) {
         // debug!("UI received database event: {:?}", database_event);
 
-        let database_event = match database_event_result {
-            Ok(database_event) => database_event,
-            Err(err) => {
-                self.handle_database_event_error(err);
-                return;
-            }
-        };
-
         match database_event {
-            DatabaseEvent::InsertTrack(track, playlist) => {
+            DatabaseEvent::InsertTracks(tracks, playlist) => {
                 let mut context = self.context.borrow_mut();
 
                 context
                     .storage
-                    .add_tracks_to_playlist(playlist.as_ref(), vec![track]);
-
-                let playlist_name = playlist.map(|playlist| playlist.name);
-                context.processing.decrement(playlist_name);
+                    .add_tracks_to_playlist(playlist.as_ref(), tracks);
             }
-            DatabaseEvent::QueryTracks(tracks, playlist) => {
+            DatabaseEvent::QueryTracks(mut tracks, playlist) => {
                 let mut context = self.context.borrow_mut();
+
+                // Tracks waiting on their undo window are still in the database
+                if let Some(removal) = context.removal.pending()
+                    && removal.affects(playlist.as_ref())
+                {
+                    tracks.retain(|track| !removal.track_ids.contains(&track.id));
+                }
                 let storage_context = &mut context.storage;
                 storage_context.set_playlist_tracks(playlist, tracks);
             }
+            DatabaseEvent::DeleteTracks(track_ids) => {
+                debug!("Removed {} track(s) from the library", track_ids.len());
+
+                // Queued tracks were removed from the stored queue along with them
+                self.context
+                    .borrow_mut()
+                    .playback
+                    .queue
+                    .remove_tracks(&track_ids);
+            }
+            DatabaseEvent::DeletePlaylistTracks(playlist, track_ids) => {
+                debug!(
+                    "Removed {} track(s) from playlist {}",
+                    track_ids.len(),
+                    playlist.name
+                );
+            }
+            DatabaseEvent::SearchTracks(full_text, track_ids) => {
+                self.components
+                    .track_table
+                    .show_search_results(&full_text, &track_ids);
+            }
             DatabaseEvent::InsertPlaylist(playlist) => {
                 let mut context = self.context.borrow_mut();
                 let storage_context = &mut context.storage;
@@ -123,9 +214,236 @@ impl App {
                     storage_context.add_empty_playlist(&playlist);
                 }
             }
+            DatabaseEvent::RenamePlaylist(playlist) => {
+                let mut context = self.context.borrow_mut();
+
+                context.ui.playlist.set_error(None);
+                context.ui.playlist.rename(&playlist);
+                context.playback.rename_playlist(&playlist);
+                context.storage.rename_playlist(playlist);
+            }
+            DatabaseEvent::DuplicatePlaylist(source, duplicate, tracks) => {
+                let mut context = self.context.borrow_mut();
+
+                context.ui.playlist.set_error(None);
+                context
+                    .storage
+                    .add_duplicate_playlist(&source, duplicate, tracks);
+            }
+            DatabaseEvent::DeletePlaylist(playlist) => {
+                let mut context = self.context.borrow_mut();
+                let context = &mut *context;
+
+                context.ui.playlist.remove(playlist.id);
+                context.storage.remove_playlist(playlist.id);
+
+                let all_tracks = context.storage.get_playlist_tracks(None);
+                context
+                    .playback
+                    .remove_playlist(playlist.id, all_tracks.map_or(&[], Vec::as_slice));
+            }
+            DatabaseEvent::InsertTag(tag) => {
+                let mut context = self.context.borrow_mut();
+
+                context.ui.tag.set_error(None);
+                context.storage.add_empty_tag(&tag);
+            }
+            DatabaseEvent::QueryTags(tags) => {
+                self.context.borrow_mut().storage.set_tags(tags);
+            }
+            DatabaseEvent::RenameTag(tag) => {
+                let mut context = self.context.borrow_mut();
+
+                context.ui.tag.set_error(None);
+                if context
+                    .ui
+                    .tag
+                    .selected()
+                    .is_some_and(|selected_tag| selected_tag.id == tag.id)
+                {
+                    context.ui.tag.set_selected(Some(tag.clone()));
+                }
+
+                context.storage.rename_tag(tag);
+            }
+            DatabaseEvent::DeleteTag(tag) => {
+                let mut context = self.context.borrow_mut();
+
+                if context
+                    .ui
+                    .tag
+                    .selected()
+                    .is_some_and(|selected_tag| selected_tag.id == tag.id)
+                {
+                    context.ui.tag.set_selected(None);
+                }
+
+                context.storage.remove_tag(tag.id);
+            }
+            DatabaseEvent::InsertTagTracks(tag, track_ids) => {
+                self.context
+                    .borrow_mut()
+                    .storage
+                    .add_tag_tracks(&tag, track_ids);
+            }
+            DatabaseEvent::DeleteTagTracks(tag, track_ids) => {
+                self.context
+                    .borrow_mut()
+                    .storage
+                    .remove_tag_tracks(&tag, &track_ids);
+            }
+            DatabaseEvent::UpdateTracks(tracks) => {
+                let mut context = self.context.borrow_mut();
+
+                if let Some(selected_track) = context.playback.selected_track.as_mut()
+                    && let Some(updated_track) = tracks
+                        .iter()
+                        .find(|track| track.id == selected_track.track.id)
+                {
+                    selected_track.track = updated_track.clone();
+                }
+
+                context.storage.update_tracks(&tracks);
+            }
+            DatabaseEvent::LocateMissingTracks(relocations) => {
+                self.components.relocate_tracks.set_relocations(relocations);
+            }
+            DatabaseEvent::QueryPlayHistory(play_history) => {
+                self.context
+                    .borrow_mut()
+                    .play_history
+                    .set_recent(play_history);
+            }
+            DatabaseEvent::QueryQueue(tracks) => {
+                self.context.borrow_mut().playback.queue.set_tracks(tracks);
+            }
+            DatabaseEvent::Task(task_event) => {
+                self.context.borrow_mut().tasks.handle_event(task_event);
+            }
+        }
+    }
+
+    /// Validates the library again once the configured interval has passed since it was last validated.
+    fn schedule_validation(&mut self) {
+        let interval_hours = self.config.borrow().general.validation_interval_hours;
+        if interval_hours == 0 {
+            return;
+        }
+
+        let interval = Duration::from_secs(u64::from(interval_hours) * 60 * 60);
+        if self.last_validation.elapsed() < interval {
+            return;
+        }
+
+        info!(
+            "Validating the library, as {} hours have passed",
+            interval_hours
+        );
+        self.components.task_table.validate_tracks();
+        self.last_validation = Instant::now();
+    }
+
+    /// (Re)starts watching the library folders whenever they change in the config, including on startup.
+    /// The folders are reconciled with the library first, as they may have changed while they weren't watched.
+    fn update_library_watcher(&mut self) {
+        let library = self.config.borrow().library.clone();
+        if library == self.watched_library {
+            return;
+        }
+
+        self.library_watcher = None;
+        self.watched_library = library.clone();
+
+        if library.watched_folders.is_empty() {
+            return;
+        }
+
+        let task = self.context.borrow_mut().tasks.register(
+            TaskKind::Sync,
+            "Reconcile watched folders".to_string(),
+            0,
+        );
+
+        if let Err(err) =
+            self.channels
+                .database_command_tx
+                .send(DatabaseCommand::ReconcileLibraryFolders(
+                    library.watched_folders.clone(),
+                    task,
+                ))
+        {
+            error!(
+                "Failed to send reconcile folders command to database: {}",
+                err
+            );
+        }
+
+        let debounce = Duration::from_secs_f32(library.watch_debounce_secs.max(0.0));
+        match LibraryWatcher::new(&library.watched_folders, debounce) {
+            Ok(watcher) => self.library_watcher = Some(watcher),
+            Err(err) => error!("Failed to start watching library folders: {}", err),
+        }
+    }
+
+    /// Syncs whatever has changed in the watched folders since they last settled.
+    fn handle_library_changes(&mut self) {
+        let Some(changes) = self
+            .library_watcher
+            .as_ref()
+            .and_then(LibraryWatcher::try_changes)
+        else {
+            return;
+        };
+
+        let task = self.context.borrow_mut().tasks.register(
+            TaskKind::Sync,
+            "Sync watched folders".to_string(),
+            changes.added.len() + changes.modified.len(),
+        );
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::SyncLibraryChanges(changes, task))
+        {
+            error!("Failed to send sync library command to database: {}", err);
         }
     }
 
+    /// Commits the pending removal of tracks to the database once it can't be undone anymore.
+    fn commit_expired_removal(&mut self) {
+        let expired = self
+            .context
+            .borrow_mut()
+            .removal
+            .take_expired(Instant::now());
+
+        if let Some(removal) = expired {
+            self.commit_removal(&removal);
+        }
+    }
+
+    fn commit_removal(&self, removal: &PendingRemoval) {
+        debug!("Committing removal: {}", removal.description());
+
+        if let Err(err) = self.channels.database_command_tx.send(removal.command()) {
+            error!("Failed to send track removal to database: {}", err);
+        }
+    }
+
+    /// Picks the tracks of smart playlists again once the library or tags have changed.
+    fn refresh_smart_playlists(&mut self) {
+        if !self.context.borrow().storage.smart_playlists_outdated() {
+            return;
+        }
+
+        let matcher = self.config.borrow().search.strategy.get_matcher();
+        self.context
+            .borrow_mut()
+            .storage
+            .refresh_smart_playlists(&matcher);
+    }
+
     fn handle_keybinds(&mut self, ctx: &egui::Context) {
         // Debug wireframe
         #[cfg(debug_assertions)]
@@ -145,11 +463,35 @@ impl App {
                 // TODO: Change to UI context
                 && !self.components.track_table.search_focused()
                 && !self.context.borrow().ui.visibility.playlist_modal()
+                // Such as when renaming a playlist or tag
+                && !ctx.wants_keyboard_input()
         {
             let _ = self.channels.player_command_tx.send(PlayerCommand::Toggle);
             return;
         }
 
+        // Skip back or forward through the playing track, unless the arrow keys are moving a text cursor
+        if !ctx.wants_keyboard_input() {
+            let (back, forward) = ctx.input(|i| {
+                (
+                    i.key_pressed(Key::ArrowLeft),
+                    i.key_pressed(Key::ArrowRight),
+                )
+            });
+
+            if back {
+                self.components
+                    .playback_bar
+                    .seek_relative(PlayDirection::Backward);
+                return;
+            } else if forward {
+                self.components
+                    .playback_bar
+                    .seek_relative(PlayDirection::Forward);
+                return;
+            }
+        }
+
         // Open OS file explorer to select folder of tracks
         if ctx.input_mut(|i| {
             i.consume_shortcut(&KeyboardShortcut {
@@ -161,33 +503,11 @@ impl App {
                 "`Ctrl + Shift + O` has been used to open OS file explorer for track folder selection"
             );
 
+            // Folders are imported through the new playlist modal, to pick how they're scanned first
             if let Some(selected_folders) = select_folders_dialog() {
-                for folder in selected_folders {
-                    let folder_tracks = get_folder_tracks(&folder, false);
-
-                    debug!(
-                        "Found {} total track(s) in selected folders",
-                        folder_tracks.len()
-                    );
-
-                    // TODO: Ask the user with a popup if a playlist should be created from this
-                    let playlist_name = folder
-                        .file_name()
-                        .and_then(|file_name| file_name.to_str())
-                        .map(|folder| folder.to_string());
-
-                    self.context
-                        .borrow_mut()
-                        .processing
-                        .add(playlist_name.clone(), folder_tracks.len());
-
-                    let insert_tracks =
-                        DatabaseCommand::InsertTracks(folder_tracks, playlist_name, None);
-
-                    if let Err(err) = self.channels.database_command_tx.send(insert_tracks) {
-                        error!("Failed to send insert tracks command to database: {}", err);
-                    }
-                }
+                self.components
+                    .create_playlist
+                    .open_with_folders(selected_folders);
             }
         }
         // Open OS file explorer to select a file as a track
@@ -200,9 +520,14 @@ impl App {
             debug!("`Ctrl + O` has been used to open OS file explorer for track file selection");
 
             if let Some(selected_file) = select_file_dialog() {
-                self.context.borrow_mut().processing.add(None, 1);
+                let task = self.context.borrow_mut().tasks.register(
+                    TaskKind::Import,
+                    format!("Import file {}", selected_file.display()),
+                    1,
+                );
 
-                let insert_tracks = DatabaseCommand::InsertTracks(vec![selected_file], None, None);
+                let insert_tracks =
+                    DatabaseCommand::InsertTracks(vec![selected_file], None, None, task);
 
                 if let Err(err) = self.channels.database_command_tx.send(insert_tracks) {
                     error!("Failed to send insert track command to database: {}", err);
@@ -255,18 +580,135 @@ impl App {
             .check_matcher(search_config);
     }
 
+    /// Finishes the play history entry of the previous track, and starts one for the new track.
+    /// A track is counted as skipped if it was changed before reaching the end.
+    fn record_play_history(&self, context: &mut Context, track: &Track) {
+        let listened_secs = context
+            .playback
+            .control
+            .current_progress()
+            .map_or(0.0, |progress| progress.as_secs_f64());
+        // Enqueued tracks are only started by the player once the previous one has played through
+        let enqueued = context
+            .playback
+            .autoplay
+            .enqueued()
+            .is_some_and(|enqueued| enqueued.track.track.id == track.id);
+        let skipped = !context.playback.control.changing_track && !enqueued;
+
+        if let Some(finished) = context.play_history.finish_current(listened_secs, skipped) {
+            let _ = self
+                .channels
+                .database_command_tx
+                .send(DatabaseCommand::UpdatePlayHistory(finished));
+        }
+
+        let playlist_id = context.ui.playlist.autoplay().map(|playlist| playlist.id);
+        let started = context.play_history.start(track, playlist_id);
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::InsertPlayHistory(started))
+        {
+            error!("Failed to send play history to database: {}", err);
+        }
+    }
+
+    /// Applies any change to the layout of tabs requested from the window menu,
+    /// and lets the menu know which tabs are open.
+    fn update_layout(&mut self) {
+        let request = self.context.borrow_mut().ui.layout.take_request();
+
+        match request {
+            Some(LayoutRequest::Open(tab)) => {
+                if let Some((surface_index, node_index, tab_index)) = self.dock_state.find_tab(&tab)
+                {
+                    self.dock_state
+                        .set_active_tab((surface_index, node_index, tab_index));
+                    self.dock_state
+                        .set_focused_node_and_surface((surface_index, node_index));
+                } else {
+                    self.dock_state.push_to_focused_leaf(tab);
+                }
+            }
+            Some(LayoutRequest::Close(tab)) => {
+                if let Some(location) = self.dock_state.find_tab(&tab) {
+                    self.dock_state.remove_tab(location);
+                }
+            }
+            Some(LayoutRequest::Reset) => {
+                self.dock_state = self.components.component_tab_layout();
+            }
+            None => {}
+        }
+
+        let open_tabs = self
+            .dock_state
+            .iter_all_tabs()
+            .map(|(_, tab)| *tab)
+            .collect();
+        self.context.borrow_mut().ui.layout.set_open_tabs(open_tabs);
+    }
+
+    /// Stores the play queue whenever it has been modified, so it can be restored on the next startup.
+    fn persist_queue(&mut self) {
+        let track_ids = {
+            let mut context = self.context.borrow_mut();
+            let queue = &mut context.playback.queue;
+
+            if !queue.take_changed() {
+                return;
+            }
+
+            queue.tracks().iter().map(|track| track.id).collect()
+        };
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::SetQueue(track_ids))
+        {
+            error!("Failed to send queue to database: {}", err);
+        }
+    }
+
     fn handle_player_event_repaint(&mut self, ctx: &egui::Context) {
         let mut context = self.context.borrow_mut();
 
+        // Progress is reported continuously, so everything that arrived since the last frame is handled at once
+        let mut received_event = false;
+        while let Ok(player_event) = self.channels.player_event_rx.try_recv() {
+            match &player_event {
+                PlayerEvent::TrackChanged(track) => {
+                    self.record_play_history(&mut context, track);
+                }
+                // A loaded track is only counted as played once it's resumed
+                PlayerEvent::TrackPlayingStatus(true) if !context.play_history.is_recording() => {
+                    let track = context
+                        .playback
+                        .selected_track
+                        .as_ref()
+                        .map(|selected| selected.track.clone());
+
+                    if let Some(track) = track {
+                        self.record_play_history(&mut context, &track);
+                    }
+                }
+                _ => {}
+            }
+
+            context.playback.handle_player_event(player_event);
+            received_event = true;
+        }
+
         // TODO: Are these repaints necessary?
-        if let Ok(player_event) = self.channels.player_event_rx.try_recv() {
-            context.playback.handle_player_event(player_event.clone());
-            ctx.request_repaint();
-        } else if context
-            .playback
-            .selected_track
-            .as_ref()
-            .is_some_and(|track| track.playing)
+        if received_event
+            || context
+                .playback
+                .selected_track
+                .as_ref()
+                .is_some_and(|track| track.playing)
         {
             ctx.request_repaint();
         }
@@ -289,26 +731,50 @@ impl App {
             .frame(Frame::central_panel(&ctx.style()).inner_margin(0.))
             .show(ctx, |ui| {
                 DockArea::new(&mut self.dock_state)
-                    .show_close_buttons(false)
                     .show_leaf_collapse_buttons(false)
-                    .show_secondary_button_hint(false)
-                    .secondary_button_on_modifier(false)
-                    .secondary_button_context_menu(false)
                     .show_leaf_close_all_buttons(false)
-                    .draggable_tabs(false)
                     .show_inside(ui, &mut self.components);
             });
 
         self.components.settings.ui(ctx);
+        self.components.resume_session.ui(ctx);
+        self.components.relocate_tracks.ui(ctx);
         self.components.debug.ui(ctx);
         self.components.create_playlist.ui(ctx);
     }
 }
 
 impl eframe::App for App {
-    // fn save(&mut self, storage: &mut dyn eframe::Storage) {
-    //     eframe::set_value(storage, eframe::APP_KEY, self);
-    // }
+    /// Called on exit and every so often, to store the current session for resuming on the next startup.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        let context = self.context.borrow();
+
+        // A session that hasn't been resumed yet is kept until something else is played
+        let session = match self.components.resume_session.pending() {
+            Some(pending) if context.playback.selected_track.is_none() => pending.clone(),
+            _ => Session::capture(&context),
+        };
+
+        eframe::set_value(storage, SESSION_STORAGE_KEY, &session);
+        eframe::set_value(storage, DOCK_STATE_STORAGE_KEY, &self.dock_state);
+        eframe::set_value(
+            storage,
+            TRACK_SORTS_STORAGE_KEY,
+            context.storage.track_sorts(),
+        );
+    }
+
+    /// Removed tracks can't be undone after exiting, so they're committed straight away.
+    /// Running tasks are cancelled first, as the database thread is waited on after exiting.
+    fn on_exit(&mut self) {
+        self.context.borrow().tasks.cancel_running();
+
+        let pending = self.context.borrow_mut().removal.take();
+
+        if let Some(removal) = pending {
+            self.commit_removal(&removal);
+        }
+    }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -321,12 +787,21 @@ impl eframe::App for App {
         ctx.request_repaint_after(Duration::from_millis(16));
 
         self.handle_database_events();
+        self.commit_expired_removal();
+        self.schedule_validation();
+        self.update_library_watcher();
+        self.handle_library_changes();
+        self.refresh_smart_playlists();
         self.handle_keybinds(ctx);
         self.check_search_matcher();
         self.handle_player_event_repaint(ctx);
+        self.components.track_table.select_new_track();
 
         self.ui(ctx);
 
+        self.update_layout();
+        self.persist_queue();
+
         if let Some(start) = start {
             let duration = start.elapsed();
             self.context
@@ -1,6 +1,9 @@
 use egui::{Color32, UiKind};
 
-use crate::context::SharedContext;
+use crate::{
+    components::ComponentTab,
+    context::{SharedContext, ui::LayoutRequest},
+};
 
 const GITHUB_REPOSITORY_URL: &str = "https://github.com/Xithrius/daemos";
 
@@ -55,6 +58,33 @@ impl MenuBar {
 
     fn ui_window(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("Window", |ui| {
+            // Tabs can be closed from the dock area, and brought back from here
+            for tab in ComponentTab::ALL {
+                let mut open = self.context.borrow().ui.layout.is_open(tab);
+
+                if ui.checkbox(&mut open, tab.to_string()).clicked() {
+                    let request = if open {
+                        LayoutRequest::Open(tab)
+                    } else {
+                        LayoutRequest::Close(tab)
+                    };
+
+                    self.context.borrow_mut().ui.layout.request(request);
+                    ui.close_kind(UiKind::Menu)
+                }
+            }
+
+            if ui.button("Reset layout").clicked() {
+                self.context
+                    .borrow_mut()
+                    .ui
+                    .layout
+                    .request(LayoutRequest::Reset);
+                ui.close_kind(UiKind::Menu)
+            }
+
+            ui.separator();
+
             ui.menu_button("Debug", |ui| {
                 if ui.button("Performance metrics").clicked() {
                     self.context
@@ -82,11 +112,11 @@ impl MenuBar {
     }
 
     fn processing_spinner(&mut self, ui: &mut egui::Ui) {
-        let processing_tracks = self.context.borrow().processing.total();
+        let running_tasks = self.context.borrow().tasks.running().count();
 
-        if processing_tracks > 0 {
-            let processing_tracks_text = format!("Processing {processing_tracks} track(s)");
-            ui.label(processing_tracks_text);
+        if running_tasks > 0 {
+            let running_tasks_text = format!("Running {running_tasks} task(s)");
+            ui.label(running_tasks_text);
 
             let spinner = egui::Spinner::new().size(14.0).color(Color32::GRAY);
             ui.add(spinner);
@@ -99,7 +129,7 @@ impl MenuBar {
                 // Debug build status
                 egui::warn_if_debug_build(ui);
 
-                // Track processing spinner
+                // Background task spinner
                 self.processing_spinner(ui);
             })
         });
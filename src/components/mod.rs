@@ -9,8 +9,15 @@ use std::{fmt, rc::Rc};
 
 use crossbeam::channel::Sender;
 use egui_dock::{DockState, NodeIndex, TabViewer};
-use popups::settings::SettingsPopup;
-use tables::{playlists::PlaylistTable, tags::TagTable, tasks::TaskTable, tracks::TrackTable};
+use popups::{
+    relocate_tracks::RelocateTracksPopup, resume_session::ResumeSessionPopup,
+    settings::SettingsPopup,
+};
+use serde::{Deserialize, Serialize};
+use tables::{
+    history::HistoryTable, playlists::PlaylistTable, queue::QueueTable, tags::TagTable,
+    tasks::TaskTable, tracks::TrackTable,
+};
 
 use crate::{
     components::{
@@ -23,12 +30,29 @@ use crate::{
     playback::state::PlayerCommand,
 };
 
-#[derive(Debug, Clone)]
+/// Key the layout of tabs is stored under in eframe's app storage.
+pub const DOCK_STATE_STORAGE_KEY: &str = "dock_state";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
 pub enum ComponentTab {
     Playlists,
     Tracks,
+    Queue,
     Tags,
     Tasks,
+    RecentlyPlayed,
+}
+
+impl ComponentTab {
+    /// Every tab, in the order they're listed in the window menu.
+    pub const ALL: [ComponentTab; 6] = [
+        ComponentTab::Playlists,
+        ComponentTab::Tracks,
+        ComponentTab::Queue,
+        ComponentTab::Tags,
+        ComponentTab::Tasks,
+        ComponentTab::RecentlyPlayed,
+    ];
 }
 
 impl fmt::Display for ComponentTab {
@@ -36,8 +60,10 @@ impl fmt::Display for ComponentTab {
         let label = match self {
             ComponentTab::Playlists => "Playlists",
             ComponentTab::Tracks => "Tracks",
+            ComponentTab::Queue => "Queue",
             ComponentTab::Tags => "Tags",
             ComponentTab::Tasks => "Tasks",
+            ComponentTab::RecentlyPlayed => "Recently Played",
         };
 
         write!(f, "{label}")
@@ -68,10 +94,14 @@ pub struct Components {
 
     pub playlist_table: PlaylistTable,
     pub track_table: TrackTable,
+    pub queue_table: QueueTable,
     pub tag_table: TagTable,
     pub task_table: TaskTable,
+    pub history_table: HistoryTable,
 
     pub settings: SettingsPopup,
+    pub resume_session: ResumeSessionPopup,
+    pub relocate_tracks: RelocateTracksPopup,
     pub debug: PerformanceMetricsPopup,
     pub create_playlist: CreatePlaylistModal,
 }
@@ -88,10 +118,18 @@ impl Components {
 
             playlist_table: PlaylistTable::new(context.clone(), channels.clone()),
             track_table: TrackTable::new(config.clone(), context.clone(), channels.clone()),
-            tag_table: TagTable::default(),
-            task_table: TaskTable::default(),
+            queue_table: QueueTable::new(context.clone(), channels.clone()),
+            tag_table: TagTable::new(context.clone(), channels.clone()),
+            task_table: TaskTable::new(context.clone(), channels.clone()),
+            history_table: HistoryTable::new(config.clone(), context.clone(), channels.clone()),
 
             settings: SettingsPopup::new(config.clone(), context.clone()),
+            resume_session: ResumeSessionPopup::new(
+                config.clone(),
+                context.clone(),
+                channels.clone(),
+            ),
+            relocate_tracks: RelocateTracksPopup::new(channels.clone()),
             debug: PerformanceMetricsPopup::new(config.clone(), context.clone()),
             create_playlist: CreatePlaylistModal::new(context.clone(), channels.clone()),
         }
@@ -100,8 +138,10 @@ impl Components {
     pub fn component_tab_layout(&self) -> DockState<ComponentTab> {
         let mut dock_state = DockState::new(vec![
             ComponentTab::Tracks,
+            ComponentTab::Queue,
             ComponentTab::Tags,
             ComponentTab::Tasks,
+            ComponentTab::RecentlyPlayed,
         ]);
 
         let surface = dock_state.main_surface_mut();
@@ -127,12 +167,18 @@ impl TabViewer for Components {
             ComponentTab::Tracks => {
                 self.track_table.ui(ui);
             }
+            ComponentTab::Queue => {
+                self.queue_table.ui(ui);
+            }
             ComponentTab::Tags => {
                 self.tag_table.ui(ui);
             }
             ComponentTab::Tasks => {
                 self.task_table.ui(ui);
             }
+            ComponentTab::RecentlyPlayed => {
+                self.history_table.ui(ui);
+            }
         }
     }
 }
@@ -1,28 +1,71 @@
-use std::{path::PathBuf, rc::Rc};
+use std::{
+    path::PathBuf,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
 
+use crossbeam::channel::{Receiver, bounded};
 use egui::{Id, Modal};
 use tracing::error;
 
 use crate::{
     components::{ComponentChannels, modals::UIModal},
     context::SharedContext,
-    database::connection::DatabaseCommand,
-    files::open::{get_file_name, get_folder_tracks, select_folders_dialog},
-    utils::regex::RegexExtract,
+    database::{
+        connection::DatabaseCommand,
+        models::{
+            playlists::smart_playlist::SmartPlaylist,
+            sort::{SortOrder, TrackColumn, TrackSort},
+        },
+    },
+    files::open::{ScanOptions, get_file_name, select_folders_dialog},
+    tasks::TaskKind,
+    utils::{
+        query::{Query, QueryError},
+        regex::RegexExtract,
+    },
 };
 
 const DEFAULT_PLAYLIST_MODAL_WINDOW_SIZE: [f32; 2] = [300.0, 200.0];
 const PLAYLIST_MODAL_ID: &str = "create_playlist_modal";
+const DEFAULT_SMART_PLAYLIST_LIMIT: usize = 50;
+const MAX_SMART_PLAYLIST_LIMIT: usize = 10_000;
+const DEFAULT_SCAN_DEPTH: usize = 3;
+const PREVIEW_MAX_HEIGHT: f32 = 150.0;
+/// How long the scan options have to stay the same before the folders are scanned again
+const SCAN_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The tracks found in the folders, or why the scan options couldn't be used
+type ScanResult = Result<Vec<PathBuf>, String>;
 
 #[derive(Debug, Clone, Default)]
 
 pub struct CreatePlaylistState {
     name: String,
+    /// Folders to import tracks from, which are scanned again whenever the scan options change
+    folders: Vec<PathBuf>,
+    scan: ScanOptions,
+    /// Comma separated glob patterns, parsed into the scan options
+    include: String,
+    exclude: String,
+    scan_error: Option<String>,
+    /// When the scan options were last changed, if the folders haven't been scanned with them yet
+    scan_changed_at: Option<Instant>,
+    /// The scan running in the background, replaced (and its result dropped) when another one starts
+    scan_rx: Option<Receiver<ScanResult>>,
+    /// The tracks that were found with the current scan options
     track_paths: Vec<PathBuf>,
     regex_match: String,
     regex_group: String,
     regex_extract: Option<RegexExtract>,
     example_output: Option<String>,
+    /// If tracks are picked by a query instead of being added
+    smart: bool,
+    query: String,
+    query_error: Option<QueryError>,
+    sort: TrackSort,
+    limit: Option<usize>,
 }
 
 #[derive(Debug, Clone)]
@@ -65,15 +108,58 @@ impl CreatePlaylistModal {
         &self.state.name
     }
 
+    /// Opens the modal with the folders ready to be imported, named after the first folder.
+    pub fn open_with_folders(&mut self, folders: Vec<PathBuf>) {
+        self.set_visibility(true);
+
+        self.state.name = folders
+            .first()
+            .and_then(|folder| folder.file_name())
+            .map(|file_name| file_name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.state.folders = folders;
+        self.rescan();
+    }
+
     pub fn create_playlist(&mut self) {
         let new_playlist_name = self.state.name.clone().trim().to_string();
 
+        // Tracks without a playlist name are only added to the library
+        if new_playlist_name.is_empty() && !self.state.smart && !self.state.track_paths.is_empty() {
+            self.send_tracks();
+            return;
+        }
+
         if new_playlist_name.is_empty() {
             error!("Cannot create playlist with empty name");
 
             return;
         }
 
+        if self.state.smart {
+            let smart = SmartPlaylist::new(
+                self.state.query.trim().to_string(),
+                self.state.sort,
+                self.state.limit,
+            );
+
+            if let Err(err) =
+                self.channels
+                    .database_command_tx
+                    .send(DatabaseCommand::InsertSmartPlaylist(
+                        new_playlist_name,
+                        smart,
+                    ))
+            {
+                error!(
+                    "Failed to send smart playlist command for new playlist: {:?}",
+                    err
+                );
+            }
+
+            return;
+        }
+
         if self.state.track_paths.is_empty() {
             if let Err(err) = self
                 .channels
@@ -95,13 +181,92 @@ impl CreatePlaylistModal {
     pub fn select_files(&mut self) {
         if let Some(selected_folders) = select_folders_dialog() {
             for folder in selected_folders {
-                let folder_tracks = get_folder_tracks(&folder, false);
+                if !self.state.folders.contains(&folder) {
+                    self.state.folders.push(folder);
+                }
+            }
+
+            self.rescan();
+        }
+    }
+
+    /// Starts finding the tracks in the selected folders again with the current scan options,
+    /// on another thread as large folders can take a while to walk.
+    fn rescan(&mut self) {
+        self.state.scan.include = Self::split_patterns(&self.state.include);
+        self.state.scan.exclude = Self::split_patterns(&self.state.exclude);
+        self.state.scan_changed_at = None;
+
+        let folders = self.state.folders.clone();
+        let scan = self.state.scan.clone();
+        let (scan_tx, scan_rx) = bounded(1);
+
+        thread::spawn(move || {
+            let scanned: Result<Vec<Vec<PathBuf>>, _> =
+                folders.iter().map(|folder| scan.scan(folder)).collect();
+
+            let result = scanned
+                .map(|scanned| {
+                    let mut track_paths: Vec<PathBuf> = scanned.into_iter().flatten().collect();
+                    // Overlapping folders would otherwise find the same files twice
+                    track_paths.sort();
+                    track_paths.dedup();
+                    track_paths
+                })
+                .map_err(|err| err.to_string());
+
+            let _ = scan_tx.send(result);
+        });
+
+        self.state.scan_rx = Some(scan_rx);
+    }
+
+    /// Rescans once the scan options have settled, and picks up the tracks of a finished scan.
+    fn update_scan(&mut self) {
+        if self
+            .state
+            .scan_changed_at
+            .is_some_and(|changed_at| changed_at.elapsed() >= SCAN_DEBOUNCE)
+        {
+            self.rescan();
+        }
 
-                self.state.track_paths.extend(folder_tracks);
+        let Some(result) = self
+            .state
+            .scan_rx
+            .as_ref()
+            .and_then(|scan_rx| scan_rx.try_recv().ok())
+        else {
+            return;
+        };
+        self.state.scan_rx = None;
+
+        match result {
+            Ok(track_paths) => {
+                self.state.track_paths = track_paths;
+                self.state.scan_error = None;
+            }
+            Err(err) => {
+                self.state.track_paths.clear();
+                self.state.scan_error = Some(err);
             }
         }
     }
 
+    /// Whether the tracks shown aren't up to date with the scan options yet.
+    fn is_scanning(&self) -> bool {
+        self.state.scan_changed_at.is_some() || self.state.scan_rx.is_some()
+    }
+
+    fn split_patterns(patterns: &str) -> Vec<String> {
+        patterns
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
     pub fn send_tracks(&self) {
         let tracks = &self.state.track_paths;
 
@@ -109,13 +274,18 @@ impl CreatePlaylistModal {
             return;
         }
 
-        let playlist_name = self.state.name.clone();
-        self.context
-            .borrow_mut()
-            .processing
-            .add(Some(playlist_name), tracks.len());
+        let playlist_name = Some(self.playlist_name().trim().to_owned())
+            .filter(|playlist_name| !playlist_name.is_empty());
 
-        let playlist_name = self.playlist_name().to_owned();
+        let label = match &playlist_name {
+            Some(playlist_name) => format!("Import tracks to playlist {playlist_name}"),
+            None => "Import tracks".to_string(),
+        };
+        let task = self
+            .context
+            .borrow_mut()
+            .tasks
+            .register(TaskKind::Import, label, tracks.len());
 
         let regex_extract = self
             .state
@@ -123,109 +293,295 @@ impl CreatePlaylistModal {
             .clone()
             .map(|regex_extract| regex_extract.extract());
         let insert_tracks =
-            DatabaseCommand::InsertTracks(tracks.to_vec(), Some(playlist_name), regex_extract);
+            DatabaseCommand::InsertTracks(tracks.to_vec(), playlist_name, regex_extract, task);
 
         if let Err(err) = self.channels.database_command_tx.send(insert_tracks) {
             error!("Failed to send insert tracks command to database: {}", err);
         }
     }
 
-    pub fn ui(&mut self, ctx: &egui::Context) {
-        let show_modal = {
-            let context = self.context.borrow();
-            context.ui.visibility.playlist_modal()
-        };
+    /// Tracks to add to a regular playlist, and how to extract their names.
+    fn ui_tracks(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let folder_button = ui.button("Open folder");
+            if folder_button.clicked() {
+                self.select_files();
+            }
 
-        if !show_modal {
-            return;
-        }
+            let selected_text = format!("{} folder(s) selected", self.state.folders.len());
+            ui.label(selected_text);
 
-        let mut should_close = false;
+            if !self.state.folders.is_empty() && ui.small_button("Clear").clicked() {
+                self.state.folders.clear();
+                self.rescan();
+            }
+        });
 
-        let modal = Modal::new(Id::new(PLAYLIST_MODAL_ID)).show(ctx, |ui| {
-            ui.set_min_size(DEFAULT_PLAYLIST_MODAL_WINDOW_SIZE.into());
+        self.ui_scan_options(ui);
 
-            ui.heading("New Playlist");
+        ui.add_space(10.0);
 
-            ui.separator();
+        ui.vertical(|ui| {
+            let mut apply_clicked = false;
 
             ui.horizontal(|ui| {
-                ui.label("Name");
-                ui.text_edit_singleline(&mut self.state.name);
-            });
+                ui.label("Regex group match");
+                ui.text_edit_singleline(&mut self.state.regex_match);
+                ui.label("Group");
+                ui.text_edit_singleline(&mut self.state.regex_group);
 
-            ui.add_space(10.0);
+                apply_clicked = ui.button("Apply").clicked();
+            });
 
             ui.horizontal(|ui| {
-                let folder_button = ui.button("Open folder");
-                if folder_button.clicked() {
-                    self.select_files();
+                if let Some(example_output) = self.state.example_output.as_ref() {
+                    let example_track_name = format!("Example track name: {example_output}");
+
+                    ui.label(example_track_name);
+                }
+
+                if !apply_clicked {
+                    return;
+                }
+
+                let Some(first_track) = self.state.track_paths.first() else {
+                    return;
+                };
+
+                let Some(track_name) = get_file_name(first_track.to_path_buf()) else {
+                    return;
+                };
+
+                let Ok(regex_group) = self.state.regex_group.parse::<usize>() else {
+                    return;
+                };
+
+                let regex_extract =
+                    RegexExtract::new(self.state.regex_match.clone(), regex_group).ok();
+                self.state.regex_extract = regex_extract.clone();
+
+                let Some(regex_extract) = regex_extract else {
+                    return;
+                };
+
+                let Some(example_track) = regex_extract.extract_group(&track_name) else {
+                    return;
+                };
+
+                self.state.example_output = Some(example_track);
+            });
+        });
+    }
+
+    /// How the selected folders are scanned, and a preview of the tracks that will be imported.
+    fn ui_scan_options(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut self.state.scan.recursive, "Include subfolders")
+                .changed();
+
+            ui.add_enabled_ui(self.state.scan.recursive, |ui| {
+                let mut limited = self.state.scan.max_depth.is_some();
+                if ui.checkbox(&mut limited, "Up to").changed() {
+                    self.state.scan.max_depth = limited.then_some(DEFAULT_SCAN_DEPTH);
+                    changed = true;
                 }
 
-                let selected_text = format!("{} track(s) selected", self.state.track_paths.len());
-                ui.label(selected_text);
+                if let Some(max_depth) = self.state.scan.max_depth.as_mut() {
+                    changed |= ui
+                        .add(egui::DragValue::new(max_depth).range(1..=usize::MAX))
+                        .changed();
+                    ui.label("level(s) deep");
+                }
+
+                changed |= ui
+                    .checkbox(&mut self.state.scan.follow_symlinks, "Follow symlinks")
+                    .changed();
             });
+        });
 
-            ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("Include");
+            changed |= ui
+                .text_edit_singleline(&mut self.state.include)
+                .on_hover_text("Only import files matching one of these comma separated patterns, such as *.flac")
+                .changed();
+        });
 
-            ui.vertical(|ui| {
-                let mut apply_clicked = false;
+        ui.horizontal(|ui| {
+            ui.label("Exclude");
+            changed |= ui
+                .text_edit_singleline(&mut self.state.exclude)
+                .on_hover_text(
+                    "Leave out files and folders matching these comma separated patterns, such as Samples/, *.demo.mp3",
+                )
+                .changed();
+        });
 
-                ui.horizontal(|ui| {
-                    ui.label("Regex group match");
-                    ui.text_edit_singleline(&mut self.state.regex_match);
-                    ui.label("Group");
-                    ui.text_edit_singleline(&mut self.state.regex_group);
+        // Typing a pattern or dragging the depth changes the options many times in a row
+        if changed {
+            self.state.scan_changed_at = Some(Instant::now());
+        }
+        self.update_scan();
 
-                    apply_clicked = ui.button("Apply").clicked();
-                });
+        if let Some(err) = &self.state.scan_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+            return;
+        }
 
-                ui.horizontal(|ui| {
-                    if let Some(example_output) = self.state.example_output.as_ref() {
-                        let example_track_name = format!("Example track name: {example_output}");
+        if self.state.folders.is_empty() {
+            return;
+        }
 
-                        ui.label(example_track_name);
+        if self.is_scanning() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Looking for tracks...");
+            });
+            return;
+        }
+
+        egui::CollapsingHeader::new(format!(
+            "{} track(s) will be imported",
+            self.state.track_paths.len()
+        ))
+        .id_salt("import_preview")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(PREVIEW_MAX_HEIGHT)
+                .show_rows(
+                    ui,
+                    ui.text_style_height(&egui::TextStyle::Body),
+                    self.state.track_paths.len(),
+                    |ui, rows| {
+                        for path in &self.state.track_paths[rows] {
+                            ui.label(path.to_string_lossy());
+                        }
+                    },
+                );
+        });
+    }
+
+    /// The query, sort and limit that pick the tracks of a smart playlist.
+    fn ui_smart(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Query");
+
+            if ui
+                .text_edit_singleline(&mut self.state.query)
+                .on_hover_text("Same as searching tracks, such as artist:\"daft punk\" added:<30d")
+                .changed()
+            {
+                self.state.query_error = Query::parse(&self.state.query).err();
+            }
+        });
+
+        if let Some(err) = &self.state.query_error {
+            ui.colored_label(ui.visuals().error_fg_color, err.describe(&self.state.query));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Sort by");
+
+            egui::ComboBox::from_id_salt("smart_playlist_sort_column")
+                .selected_text(self.state.sort.column.to_string())
+                .show_ui(ui, |ui| {
+                    for column in TrackColumn::ALL.into_iter().filter(TrackColumn::sortable) {
+                        ui.selectable_value(
+                            &mut self.state.sort.column,
+                            column,
+                            column.to_string(),
+                        );
                     }
+                });
 
-                    if !apply_clicked {
-                        return;
+            egui::ComboBox::from_id_salt("smart_playlist_sort_order")
+                .selected_text(self.state.sort.order.to_string())
+                .show_ui(ui, |ui| {
+                    for order in [SortOrder::Ascending, SortOrder::Descending] {
+                        ui.selectable_value(&mut self.state.sort.order, order, order.to_string());
                     }
+                });
+        });
 
-                    let Some(first_track) = self.state.track_paths.first() else {
-                        return;
-                    };
+        ui.horizontal(|ui| {
+            let mut limited = self.state.limit.is_some();
+            if ui.checkbox(&mut limited, "Limit to").changed() {
+                self.state.limit = limited.then_some(DEFAULT_SMART_PLAYLIST_LIMIT);
+            }
 
-                    let Some(track_name) = get_file_name(first_track.to_path_buf()) else {
-                        return;
-                    };
+            if let Some(limit) = self.state.limit.as_mut() {
+                ui.add(egui::DragValue::new(limit).range(1..=MAX_SMART_PLAYLIST_LIMIT));
+                ui.label("track(s)");
+            }
+        });
+    }
 
-                    let Ok(regex_group) = self.state.regex_group.parse::<usize>() else {
-                        return;
-                    };
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        let show_modal = {
+            let context = self.context.borrow();
+            context.ui.visibility.playlist_modal()
+        };
 
-                    let regex_extract =
-                        RegexExtract::new(self.state.regex_match.clone(), regex_group).ok();
-                    self.state.regex_extract = regex_extract.clone();
+        if !show_modal {
+            return;
+        }
 
-                    let Some(regex_extract) = regex_extract else {
-                        return;
-                    };
+        let mut should_close = false;
 
-                    let Some(example_track) = regex_extract.extract_group(&track_name) else {
-                        return;
-                    };
+        let modal = Modal::new(Id::new(PLAYLIST_MODAL_ID)).show(ctx, |ui| {
+            ui.set_min_size(DEFAULT_PLAYLIST_MODAL_WINDOW_SIZE.into());
 
-                    self.state.example_output = Some(example_track);
-                });
+            ui.heading("New Playlist");
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut self.state.name)
+                    .on_hover_text("Leave empty to only add the tracks to the library");
             });
 
+            ui.checkbox(&mut self.state.smart, "Smart playlist")
+                .on_hover_text(
+                    "Pick tracks from the library with a search query, instead of adding them",
+                );
+
+            ui.add_space(10.0);
+
+            if self.state.smart {
+                self.ui_smart(ui);
+            } else {
+                self.ui_tracks(ui);
+            }
+
             ui.separator();
 
             egui::Sides::new().show(
                 ui,
                 |_ui| {},
                 |ui| {
-                    if ui.button("Create").clicked() {
+                    // Smart playlists need a query that can be parsed
+                    let can_create = !self.state.smart
+                        || (!self.state.query.trim().is_empty()
+                            && self.state.query_error.is_none());
+
+                    // Without a name, tracks are imported into the library without a playlist
+                    let import_only = !self.state.smart && self.state.name.trim().is_empty();
+                    let (label, can_create) = if import_only {
+                        ("Import", !self.state.track_paths.is_empty())
+                    } else {
+                        ("Create", can_create)
+                    };
+                    // The tracks to import aren't known until the folders have been scanned
+                    let can_create = can_create && (self.state.smart || !self.is_scanning());
+
+                    if ui
+                        .add_enabled(can_create, egui::Button::new(label))
+                        .clicked()
+                    {
                         self.create_playlist();
 
                         should_close = true;
@@ -5,11 +5,12 @@ use std::{
 };
 
 use egui::{ImageButton, ImageSource, RichText, include_image};
+use tracing::error;
 
 use super::ComponentChannels;
 use crate::{
-    config::core::SharedConfig,
-    context::{AutoplayType, PlayDirection, SharedContext},
+    config::{core::SharedConfig, save_config},
+    context::{AutoplayType, PlayDirection, RepeatMode, SharedContext},
     playback::state::PlayerCommand,
     utils::formatting::human_duration,
 };
@@ -27,6 +28,9 @@ const SKIP_NEXT_IMAGE: egui::ImageSource<'_> = include_image!("../../static/asse
 const PLAY_IMAGE: egui::ImageSource<'_> = include_image!("../../static/assets/play.png");
 const PAUSE_IMAGE: egui::ImageSource<'_> = include_image!("../../static/assets/pause.png");
 const VOLUME_IMAGE: egui::ImageSource<'_> = include_image!("../../static/assets/volume-up.png");
+const REPEAT_IMAGE: egui::ImageSource<'_> = include_image!("../../static/assets/repeat.png");
+const REPLAY_IMAGE: egui::ImageSource<'_> = include_image!("../../static/assets/replay-10.png");
+const FORWARD_IMAGE: egui::ImageSource<'_> = include_image!("../../static/assets/forward-10.png");
 
 const AUTOPLAY_FONT_SIZE: f32 = 12.0;
 
@@ -63,6 +67,34 @@ impl PlaybackBar {
         }
     }
 
+    /// Skips forward or back through the playing track by the configured step.
+    pub fn seek_relative(&self, direction: PlayDirection) {
+        let step = Duration::from_secs_f32(self.config.borrow().playback.seek_step_secs.max(0.0));
+
+        let mut context = self.context.borrow_mut();
+        let playback = &mut context.playback;
+
+        let Some(track_context) = &playback.selected_track else {
+            return;
+        };
+
+        if playback.control.changing_track {
+            return;
+        }
+
+        let track_duration = Duration::from_secs_f64(track_context.track.duration_secs.max(0.0));
+        playback
+            .control
+            .seek_relative(step, &direction, track_duration);
+
+        let command = match direction {
+            PlayDirection::Forward => PlayerCommand::SeekForward(step),
+            PlayDirection::Backward => PlayerCommand::SeekBackward(step),
+        };
+
+        let _ = self.channels.player_command_tx.send(command);
+    }
+
     pub fn ui_playback_controls(&mut self, ui: &mut egui::Ui) {
         let button = |ui: &mut egui::Ui, image: ImageSource, image_size: f32| -> bool {
             let image_button = ImageButton::new(image).frame(false);
@@ -70,6 +102,9 @@ impl PlaybackBar {
                 .clicked()
         };
 
+        let seek_step_secs = self.config.borrow().playback.seek_step_secs;
+        let mut seek_direction = None;
+
         let mut context = self.context.borrow_mut();
 
         // TODO: Get rid of this terrible layout
@@ -78,18 +113,23 @@ impl PlaybackBar {
                 // TODO: Dynamic spacing based on something else if this layout has to be kept?
                 ui.add_space(8.0);
 
-                // TODO: Configure based on autoplay direction
-                // Skip back a track
+                // Skip back to the previously played track
                 if button(ui, SKIP_BACK_IMAGE, MEDIUM_BUTTON_SIZE) {
-                    if context.playback.autoplay.is_shuffle() {
-                        // TODO: Save the previous track and go there instead of selecting another random one
-                        context.playback.autoplay.set_select_new_track(true);
-                    } else {
-                        context.playback.autoplay.set_incoming_track(
-                            true,
-                            Some(AutoplayType::Iterative(PlayDirection::Backward)),
-                        );
-                    }
+                    context.playback.autoplay.request_previous_track();
+                }
+            });
+
+            ui.vertical(|ui| {
+                ui.add_space(12.0);
+
+                // Go back through the track by the configured step
+                let replay_button = ImageButton::new(REPLAY_IMAGE).frame(false);
+                if ui
+                    .add_sized([SMALL_BUTTON_SIZE, SMALL_BUTTON_SIZE], replay_button)
+                    .on_hover_text(format!("Back {seek_step_secs} seconds"))
+                    .clicked()
+                {
+                    seek_direction = Some(PlayDirection::Backward);
                 }
             });
 
@@ -107,17 +147,69 @@ impl PlaybackBar {
                 let _ = self.channels.player_command_tx.send(PlayerCommand::Toggle);
             }
 
+            ui.vertical(|ui| {
+                ui.add_space(12.0);
+
+                // Go forward through the track by the configured step
+                let forward_button = ImageButton::new(FORWARD_IMAGE).frame(false);
+                if ui
+                    .add_sized([SMALL_BUTTON_SIZE, SMALL_BUTTON_SIZE], forward_button)
+                    .on_hover_text(format!("Forward {seek_step_secs} seconds"))
+                    .clicked()
+                {
+                    seek_direction = Some(PlayDirection::Forward);
+                }
+            });
+
             // Skip to the next track
             if button(ui, SKIP_NEXT_IMAGE, MEDIUM_BUTTON_SIZE) {
-                if context.playback.autoplay.is_shuffle() {
-                    context.playback.autoplay.set_select_new_track(true);
-                } else {
-                    context.playback.autoplay.set_incoming_track(
-                        true,
-                        Some(AutoplayType::Iterative(PlayDirection::Forward)),
-                    );
+                context.playback.autoplay.request_next_track();
+            }
+
+            ui.vertical(|ui| {
+                ui.add_space(12.0);
+
+                Self::ui_repeat(ui, &self.config);
+            });
+        });
+
+        drop(context);
+
+        if let Some(direction) = seek_direction {
+            self.seek_relative(direction);
+        }
+    }
+
+    /// Cycles through the repeat modes, dimming the button when repeating is off.
+    /// The chosen mode is saved to the config so it's kept across restarts.
+    fn ui_repeat(ui: &mut egui::Ui, config: &SharedConfig) {
+        let repeat = config.borrow().playback.repeat;
+
+        let tint = if repeat == RepeatMode::Off {
+            ui.visuals().weak_text_color()
+        } else {
+            ui.visuals().strong_text_color()
+        };
+
+        ui.horizontal(|ui| {
+            let image = egui::Image::new(REPEAT_IMAGE).tint(tint);
+            let repeat_button = ImageButton::new(image).frame(false);
+
+            if ui
+                .add_sized([SMALL_BUTTON_SIZE, SMALL_BUTTON_SIZE], repeat_button)
+                .on_hover_text(format!("Repeat: {repeat}"))
+                .clicked()
+            {
+                config.borrow_mut().playback.repeat = repeat.next();
+
+                if let Err(err) = save_config(&config.borrow()) {
+                    error!("Failed to save repeat mode: {}", err);
                 }
             }
+
+            if repeat == RepeatMode::One {
+                ui.label(RichText::new("1").size(AUTOPLAY_FONT_SIZE));
+            }
         });
     }
 
@@ -158,29 +250,21 @@ impl PlaybackBar {
 
         let mut context = self.context.borrow_mut();
 
-        let (mut playback_secs, total_duration_secs, has_hours, needs_new_track) = {
+        let (mut playback_secs, total_duration_secs, has_hours) = {
             let playback = &mut context.playback;
 
             if let (Some(progress), Some(track_context)) = (
                 playback.control.current_progress(),
                 &playback.selected_track,
             ) {
-                let playback_secs = progress.as_secs_f64();
+                // The estimate may run past the end until the player reports the track has finished
                 let total_duration_secs = track_context.track.duration_secs;
-                let control = &mut playback.control;
-
-                let needs_new_track =
-                    playback_secs >= total_duration_secs && !control.changing_track;
+                let playback_secs = progress.as_secs_f64().min(total_duration_secs);
 
                 let has_hours =
                     (Duration::from_secs_f64(total_duration_secs.floor()).as_secs() / 3600) > 0;
 
-                (
-                    playback_secs,
-                    total_duration_secs,
-                    has_hours,
-                    needs_new_track,
-                )
+                (playback_secs, total_duration_secs, has_hours)
             } else {
                 let mut dummy = 0.0;
                 let slider = egui::Slider::new(&mut dummy, 0.0..=1.0).show_value(false);
@@ -192,12 +276,6 @@ impl PlaybackBar {
             }
         };
 
-        if needs_new_track {
-            let playback = &mut context.playback;
-            playback.control.changing_track = true;
-            playback.autoplay.set_select_new_track(true);
-        }
-
         let current_time = Duration::from_secs_f64(playback_secs.floor());
         let total_time = Duration::from_secs_f64(total_duration_secs.floor());
 
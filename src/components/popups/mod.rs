@@ -1,2 +1,4 @@
 pub mod debug;
+pub mod relocate_tracks;
+pub mod resume_session;
 pub mod settings;
@@ -0,0 +1,125 @@
+use std::rc::Rc;
+
+use tracing::{debug, error};
+
+use crate::{
+    components::ComponentChannels,
+    database::{connection::DatabaseCommand, models::tracks::TrackRelocation},
+};
+
+const RELOCATIONS_MAX_HEIGHT: f32 = 300.0;
+
+/// Shows where missing tracks were found as a dry run, only relocating the ones that are kept checked.
+#[derive(Debug, Clone)]
+pub struct RelocateTracksPopup {
+    channels: Rc<ComponentChannels>,
+
+    /// Each found relocation, and whether it will be applied
+    relocations: Option<Vec<(TrackRelocation, bool)>>,
+}
+
+impl RelocateTracksPopup {
+    pub fn new(channels: Rc<ComponentChannels>) -> Self {
+        Self {
+            channels,
+            relocations: None,
+        }
+    }
+
+    pub fn set_relocations(&mut self, relocations: Vec<TrackRelocation>) {
+        debug!("Found {} missing tracks", relocations.len());
+
+        self.relocations = Some(
+            relocations
+                .into_iter()
+                .map(|relocation| (relocation, true))
+                .collect(),
+        );
+    }
+
+    fn apply(&mut self) {
+        let Some(relocations) = self.relocations.take() else {
+            return;
+        };
+
+        let relocations: Vec<TrackRelocation> = relocations
+            .into_iter()
+            .filter_map(|(relocation, checked)| checked.then_some(relocation))
+            .collect();
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::RelocateTracks(relocations))
+        {
+            error!(
+                "Failed to send relocate tracks command to database: {}",
+                err
+            );
+        }
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        let Some(relocations) = self.relocations.as_mut() else {
+            return;
+        };
+
+        let mut apply_clicked = false;
+        let mut close_clicked = false;
+
+        egui::Window::new("Locate missing tracks")
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                if relocations.is_empty() {
+                    ui.label("None of the missing tracks were found in the chosen folders");
+                    ui.add_space(5.0);
+
+                    close_clicked = ui.button("Close").clicked();
+                    return;
+                }
+
+                ui.label(format!(
+                    "Found {} missing tracks. Nothing has been changed yet.",
+                    relocations.len()
+                ));
+                ui.add_space(5.0);
+
+                egui::ScrollArea::vertical()
+                    .max_height(RELOCATIONS_MAX_HEIGHT)
+                    .show(ui, |ui| {
+                        egui::Grid::new("relocations_grid")
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (relocation, checked) in relocations.iter_mut() {
+                                    ui.checkbox(checked, &relocation.track.name);
+                                    ui.label(relocation.track.path.to_string_lossy());
+                                    ui.label("→");
+                                    ui.label(relocation.path.to_string_lossy());
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.add_space(5.0);
+
+                let checked_count = relocations.iter().filter(|(_, checked)| *checked).count();
+
+                ui.horizontal(|ui| {
+                    apply_clicked = ui
+                        .add_enabled(
+                            checked_count > 0,
+                            egui::Button::new(format!("Relocate {checked_count} tracks")),
+                        )
+                        .clicked();
+                    close_clicked = ui.button("Cancel").clicked();
+                });
+            });
+
+        if apply_clicked {
+            self.apply();
+        } else if close_clicked {
+            self.relocations = None;
+        }
+    }
+}
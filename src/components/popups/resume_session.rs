@@ -0,0 +1,238 @@
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, error};
+
+use crate::{
+    components::ComponentChannels,
+    config::{core::SharedConfig, general::SessionRestore},
+    context::{
+        SharedContext,
+        playback::{PlaylistState, SelectedTrackContext},
+    },
+    database::{connection::DatabaseCommand, models::tracks::Track},
+    playback::state::PlayerCommand,
+    session::Session,
+    utils::formatting::human_duration,
+};
+
+/// How long to wait for the tracks of the last session's playlist to load,
+/// before resuming the track without its playlist.
+const PLAYLIST_LOAD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Offers to resume the track from the last session once the library has loaded,
+/// or resumes it straight away if configured to.
+#[derive(Debug, Clone)]
+pub struct ResumeSessionPopup {
+    config: SharedConfig,
+    context: SharedContext,
+    channels: Rc<ComponentChannels>,
+
+    session: Option<Session>,
+    playlist_requested: bool,
+    started_at: Instant,
+}
+
+impl ResumeSessionPopup {
+    pub fn new(
+        config: SharedConfig,
+        context: SharedContext,
+        channels: Rc<ComponentChannels>,
+    ) -> Self {
+        Self {
+            config,
+            context,
+            channels,
+            session: None,
+            playlist_requested: false,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// The session waiting to be resumed, if it hasn't been resumed or dismissed yet.
+    pub fn pending(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub fn set_session(&mut self, session: Session) {
+        if session.track_id.is_some() {
+            self.session = Some(session);
+            self.started_at = Instant::now();
+        }
+    }
+
+    /// Finds the session's track and playlist in what's been loaded so far, requesting the playlist's
+    /// tracks if they haven't been loaded yet.
+    fn resolve(&mut self) -> Option<(Track, Option<PlaylistState>)> {
+        let session = self.session.as_ref()?;
+
+        let (track, playlist) = {
+            let context = self.context.borrow();
+            let storage = &context.storage;
+
+            let track = storage
+                .get_playlist_tracks(None)?
+                .iter()
+                .find(|track| Some(track.id) == session.track_id)?
+                .clone();
+
+            let Some(playlist_id) = session.playlist_id else {
+                return Some((track, None));
+            };
+
+            let playlist = storage
+                .playlists()
+                .find(|playlist| playlist.id == playlist_id)
+                .cloned();
+
+            if let Some(playlist) = &playlist
+                && let Some(tracks) = storage.get_playlist_tracks(Some(playlist))
+                && tracks
+                    .iter()
+                    .any(|playlist_track| playlist_track.id == track.id)
+            {
+                let playlist_state = PlaylistState::new(playlist.clone(), tracks.clone());
+
+                return Some((track, Some(playlist_state)));
+            }
+
+            (track, playlist)
+        };
+
+        if let Some(playlist) = playlist
+            && !self.playlist_requested
+        {
+            self.playlist_requested = true;
+
+            if let Err(err) = self
+                .channels
+                .database_command_tx
+                .send(DatabaseCommand::QueryTracks(Some(playlist)))
+            {
+                error!(
+                    "Failed to request tracks of last session's playlist: {}",
+                    err
+                );
+            }
+        }
+
+        (self.started_at.elapsed() > PLAYLIST_LOAD_TIMEOUT).then_some((track, None))
+    }
+
+    /// Selects the session's track and playlist, and loads the track into the player paused where it was left off.
+    fn restore(&mut self, track: Track, playlist_state: Option<PlaylistState>) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+
+        debug!("Resuming last session at track {:?}", track.path);
+
+        let volume = self.config.borrow().playback.volume;
+        let position = Duration::from_secs_f64(session.position_secs.max(0.0));
+
+        let mut context = self.context.borrow_mut();
+
+        let playlist = playlist_state.as_ref().map(PlaylistState::playlist);
+        let index = match &playlist_state {
+            Some(playlist_state) => playlist_state
+                .tracks()
+                .iter()
+                .position(|playlist_track| playlist_track.id == track.id),
+            None => context
+                .storage
+                .get_playlist_tracks(None)
+                .and_then(|tracks| {
+                    tracks
+                        .iter()
+                        .position(|library_track| library_track.id == track.id)
+                }),
+        }
+        .unwrap_or_default();
+
+        context.ui.playlist.set_selected(playlist.clone());
+        context.ui.playlist.set_autoplay(playlist);
+        context
+            .playback
+            .selected_playlist
+            .set_playlist(playlist_state);
+        context
+            .playback
+            .selected_playlist
+            .set_played_tracks(session.played_tracks);
+        context
+            .playback
+            .select_track(Some(SelectedTrackContext::new(track.clone(), index, false)));
+
+        if let Err(err) = self
+            .channels
+            .player_command_tx
+            .send(PlayerCommand::Restore(track, volume, position))
+        {
+            error!("Failed to send restore command to player: {}", err);
+        }
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        if self.session.is_none() {
+            return;
+        }
+
+        // Nothing to resume if restoring was turned off, or something else has been played since
+        let restore_session = self.config.borrow().general.restore_session;
+        if restore_session == SessionRestore::Off
+            || self.context.borrow().playback.selected_track.is_some()
+        {
+            self.session = None;
+            return;
+        }
+
+        let Some((track, playlist_state)) = self.resolve() else {
+            return;
+        };
+
+        if restore_session == SessionRestore::Paused {
+            self.restore(track, playlist_state);
+            return;
+        }
+
+        let position_secs = self
+            .session
+            .as_ref()
+            .map_or(0.0, |session| session.position_secs);
+        let position = Duration::from_secs_f64(position_secs.max(0.0).floor());
+
+        let mut resume_clicked = false;
+        let mut dismiss_clicked = false;
+
+        egui::Window::new("Resume last session")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "Pick up {} at {}?",
+                    track.name,
+                    human_duration(position, false)
+                ));
+
+                if let Some(playlist_state) = &playlist_state {
+                    ui.label(format!("From playlist {}", playlist_state.playlist().name));
+                }
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    resume_clicked = ui.button("Resume").clicked();
+                    dismiss_clicked = ui.button("Dismiss").clicked();
+                });
+            });
+
+        if resume_clicked {
+            self.restore(track, playlist_state);
+        } else if dismiss_clicked {
+            self.session = None;
+        }
+    }
+}
@@ -1,17 +1,36 @@
+use std::ops::RangeInclusive;
+
 use tracing::{error, info};
 
 use crate::{
     config::{
         core::{CoreConfig, SharedConfig},
+        general::SessionRestore,
+        library::LibraryConfig,
+        playback::MAX_CROSSFADE_SECS,
         save_config,
         search::SearchMatchingStrategy,
     },
     context::{AutoplayType, PlayDirection, SharedContext, ShuffleType},
+    files::open::select_folders_dialog,
     themes::AppTheme,
 };
 
 const DEFAULT_SETTINGS_WINDOW_SIZE: [f32; 2] = [150.0, 200.0];
 
+const SESSION_RESTORE_OPTIONS: [SessionRestore; 3] = [
+    SessionRestore::Ask,
+    SessionRestore::Paused,
+    SessionRestore::Off,
+];
+
+const SEEK_STEP_RANGE: RangeInclusive<f32> = 1.0..=60.0;
+
+/// Up to a week between validating the library
+const VALIDATION_INTERVAL_RANGE: RangeInclusive<u32> = 0..=168;
+
+const WATCH_DEBOUNCE_RANGE: RangeInclusive<f32> = 0.5..=60.0;
+
 const AUTOPLAY_OPTIONS: [AutoplayType; 4] = [
     AutoplayType::Iterative(PlayDirection::Backward),
     AutoplayType::Iterative(PlayDirection::Forward),
@@ -78,6 +97,18 @@ impl SettingsPopup {
                         &mut changed,
                     );
 
+                    Self::render_crossfade_section(
+                        ui,
+                        &mut self.selected.playback.crossfade_secs,
+                        &mut changed,
+                    );
+
+                    Self::render_seek_step_section(
+                        ui,
+                        &mut self.selected.playback.seek_step_secs,
+                        &mut changed,
+                    );
+
                     Self::render_search_section(
                         ui,
                         self.selected.search.strategy.clone(),
@@ -85,6 +116,25 @@ impl SettingsPopup {
                         &mut changed,
                     );
 
+                    Self::render_session_restore_section(
+                        ui,
+                        &mut self.selected.general.restore_session,
+                        &mut changed,
+                    );
+
+                    Self::render_validation_section(
+                        ui,
+                        &mut self.selected.general.validate_on_startup,
+                        &mut self.selected.general.validation_interval_hours,
+                        &mut changed,
+                    );
+
+                    Self::render_watched_folders_section(
+                        ui,
+                        &mut self.selected.library,
+                        &mut changed,
+                    );
+
                     ui.add_space(10.0);
                     ui.separator();
 
@@ -141,6 +191,12 @@ impl SettingsPopup {
         // Apply immediate UI/playback changes that need special handling
         Self::apply_immediate_changes(ctx, &current_config, &self.selected, &mut self.context);
 
+        // Keep what's been changed from the playback bar since the settings were opened
+        self.selected.playback.volume = current_config.playback.volume;
+        self.selected.playback.repeat = current_config.playback.repeat;
+        // Same goes for the columns chosen in the track table
+        self.selected.ui.track_columns = current_config.ui.track_columns.clone();
+
         // Replace the entire shared config with the selected config
         *self.config.borrow_mut() = self.selected.clone();
     }
@@ -243,6 +299,42 @@ impl SettingsPopup {
         });
     }
 
+    fn render_crossfade_section(ui: &mut egui::Ui, crossfade_secs: &mut f32, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.label("Crossfade");
+
+            let response = ui
+                .add(
+                    egui::Slider::new(crossfade_secs, 0.0..=MAX_CROSSFADE_SECS)
+                        .step_by(0.5)
+                        .suffix(" s"),
+                )
+                .on_hover_text("Set to 0 to turn crossfading off");
+
+            if response.changed() {
+                *changed = true;
+            }
+        });
+    }
+
+    fn render_seek_step_section(ui: &mut egui::Ui, seek_step_secs: &mut f32, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.label("Seek step");
+
+            let response = ui
+                .add(
+                    egui::Slider::new(seek_step_secs, SEEK_STEP_RANGE)
+                        .step_by(1.0)
+                        .suffix(" s"),
+                )
+                .on_hover_text("How far the seek buttons and arrow keys skip through a track");
+
+            if response.changed() {
+                *changed = true;
+            }
+        });
+    }
+
     fn render_search_section(
         ui: &mut egui::Ui,
         current_search: SearchMatchingStrategy,
@@ -273,6 +365,122 @@ impl SettingsPopup {
         });
     }
 
+    fn render_session_restore_section(
+        ui: &mut egui::Ui,
+        selected_restore: &mut SessionRestore,
+        changed: &mut bool,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Restore last session");
+
+            egui::ComboBox::from_id_salt("Session restore combobox")
+                .selected_text(selected_restore.to_string())
+                .show_ui(ui, |ui| {
+                    for restore_option in SESSION_RESTORE_OPTIONS {
+                        if ui
+                            .selectable_value(
+                                selected_restore,
+                                restore_option,
+                                restore_option.to_string(),
+                            )
+                            .clicked()
+                        {
+                            *changed = true;
+                        }
+                    }
+                });
+        });
+    }
+
+    fn render_validation_section(
+        ui: &mut egui::Ui,
+        validate_on_startup: &mut bool,
+        validation_interval_hours: &mut u32,
+        changed: &mut bool,
+    ) {
+        ui.horizontal(|ui| {
+            ui.label("Validate library");
+
+            let response = ui
+                .checkbox(validate_on_startup, "On startup")
+                .on_hover_text("Check that every track's file still exists and is unchanged");
+            if response.changed() {
+                *changed = true;
+            }
+
+            ui.label("Every");
+
+            let response = ui
+                .add(
+                    egui::DragValue::new(validation_interval_hours)
+                        .range(VALIDATION_INTERVAL_RANGE)
+                        .suffix(" h"),
+                )
+                .on_hover_text("Set to 0 to only validate on startup or from the tasks tab");
+            if response.changed() {
+                *changed = true;
+            }
+        });
+    }
+
+    fn render_watched_folders_section(
+        ui: &mut egui::Ui,
+        library: &mut LibraryConfig,
+        changed: &mut bool,
+    ) {
+        ui.label("Watched folders").on_hover_text(
+            "New, removed and renamed files in these folders are synced with the library",
+        );
+
+        let mut removed_folder = None;
+
+        for (index, folder) in library.watched_folders.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("x")
+                    .on_hover_text("Stop watching")
+                    .clicked()
+                {
+                    removed_folder = Some(index);
+                }
+
+                ui.label(folder.to_string_lossy());
+            });
+        }
+
+        if let Some(index) = removed_folder {
+            library.watched_folders.remove(index);
+            *changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Add folders...").clicked()
+                && let Some(folders) = select_folders_dialog()
+            {
+                for folder in folders {
+                    if !library.watched_folders.contains(&folder) {
+                        library.watched_folders.push(folder);
+                        *changed = true;
+                    }
+                }
+            }
+
+            ui.label("Settle for");
+
+            let response = ui
+                .add(
+                    egui::DragValue::new(&mut library.watch_debounce_secs)
+                        .range(WATCH_DEBOUNCE_RANGE)
+                        .speed(0.1)
+                        .suffix(" s"),
+                )
+                .on_hover_text("How long to wait for files to stop changing before syncing them");
+            if response.changed() {
+                *changed = true;
+            }
+        });
+    }
+
     fn render_buttons(
         ui: &mut egui::Ui,
         changed: &mut bool,
@@ -0,0 +1,170 @@
+use std::{rc::Rc, time::Duration};
+
+use chrono::Local;
+use egui::CursorIcon;
+use egui_extras::{Column, TableBuilder};
+use tracing::{debug, error};
+
+use super::{TABLE_HEADER_HEIGHT, TABLE_ROW_HEIGHT};
+use crate::{
+    components::ComponentChannels,
+    config::core::SharedConfig,
+    context::{
+        SharedContext,
+        play_history::RECENT_PLAY_HISTORY_LIMIT,
+        playback::{PlaylistState, SelectedTrackContext},
+    },
+    database::{
+        connection::DatabaseCommand,
+        models::{play_history::PlayHistory, tracks::Track},
+    },
+    playback::state::PlayerCommand,
+    utils::formatting::human_duration,
+};
+
+const PLAYED_AT_COLUMN_WIDTH: f32 = 130.0;
+const LISTENED_COLUMN_WIDTH: f32 = 80.0;
+const SKIPPED_COLUMN_WIDTH: f32 = 60.0;
+
+#[derive(Debug, Clone)]
+pub struct HistoryTable {
+    config: SharedConfig,
+    context: SharedContext,
+    channels: Rc<ComponentChannels>,
+}
+
+impl HistoryTable {
+    pub fn new(
+        config: SharedConfig,
+        context: SharedContext,
+        channels: Rc<ComponentChannels>,
+    ) -> Self {
+        let _ = channels
+            .database_command_tx
+            .send(DatabaseCommand::QueryPlayHistory(RECENT_PLAY_HISTORY_LIMIT));
+
+        Self {
+            config,
+            context,
+            channels,
+        }
+    }
+
+    /// Plays a track from the history again, autoplaying from the playlist it was originally played in
+    /// if that playlist still exists, otherwise from all tracks.
+    fn replay(&mut self, play_history: &PlayHistory, track: &Track) {
+        let volume = self.config.borrow().playback.volume;
+
+        if let Err(err) = self
+            .channels
+            .player_command_tx
+            .send(PlayerCommand::Create(track.clone(), volume))
+        {
+            error!("Failed to replay track on path {:?}: {}", track.path, err);
+            return;
+        }
+
+        debug!("Replaying track {:?} from play history", track.path);
+
+        let mut context = self.context.borrow_mut();
+
+        let playlist = play_history.playlist_id.and_then(|playlist_id| {
+            context
+                .storage
+                .playlists()
+                .find(|playlist| playlist.id == playlist_id)
+                .cloned()
+        });
+
+        let tracks = context
+            .storage
+            .get_playlist_tracks(playlist.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        let index = tracks
+            .iter()
+            .position(|other_track| other_track.id == track.id)
+            .unwrap_or_default();
+
+        context.ui.playlist.set_autoplay(playlist.clone());
+        context
+            .playback
+            .selected_playlist
+            .set_playlist(playlist.map(|playlist| PlaylistState::new(playlist, tracks)));
+        context
+            .playback
+            .select_track(Some(SelectedTrackContext::new(track.clone(), index, true)));
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        let height = ui.available_height();
+
+        let recent = self.context.borrow().play_history.recent().to_vec();
+
+        TableBuilder::new(ui)
+            .max_scroll_height(height)
+            .column(Column::remainder())
+            .column(Column::auto().at_least(PLAYED_AT_COLUMN_WIDTH))
+            .column(Column::auto().at_least(LISTENED_COLUMN_WIDTH))
+            .column(Column::auto().at_least(SKIPPED_COLUMN_WIDTH))
+            .sense(egui::Sense::click())
+            .header(TABLE_HEADER_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Track");
+                });
+                header.col(|ui| {
+                    ui.heading("Played");
+                });
+                header.col(|ui| {
+                    ui.heading("Listened");
+                });
+                header.col(|ui| {
+                    ui.heading("Skipped");
+                });
+            })
+            .body(|body| {
+                body.rows(TABLE_ROW_HEIGHT, recent.len(), |mut row| {
+                    let Some((play_history, track)) = recent.get(row.index()) else {
+                        return;
+                    };
+
+                    // Labels take clicks before the row does, so each one is checked as well
+                    let mut replay = false;
+
+                    row.col(|ui| {
+                        let label = ui.label(&track.name).on_hover_cursor(CursorIcon::Default);
+                        replay |= label.double_clicked();
+                    });
+
+                    row.col(|ui| {
+                        let played_at = play_history
+                            .started_at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M");
+                        let label = ui
+                            .label(played_at.to_string())
+                            .on_hover_cursor(CursorIcon::Default);
+                        replay |= label.double_clicked();
+                    });
+
+                    row.col(|ui| {
+                        let listened = Duration::from_secs_f64(play_history.listened_secs.floor());
+                        let label = ui
+                            .label(human_duration(listened, false))
+                            .on_hover_cursor(CursorIcon::Default);
+                        replay |= label.double_clicked();
+                    });
+
+                    row.col(|ui| {
+                        let skipped = if play_history.skipped { "Yes" } else { "" };
+                        let label = ui.label(skipped).on_hover_cursor(CursorIcon::Default);
+                        replay |= label.double_clicked();
+                    });
+
+                    if replay || row.response().double_clicked() {
+                        self.replay(play_history, track);
+                    }
+                });
+            });
+    }
+}
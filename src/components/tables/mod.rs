@@ -1,4 +1,6 @@
+pub mod history;
 pub mod playlists;
+pub mod queue;
 pub mod tags;
 pub mod tasks;
 pub mod tracks;
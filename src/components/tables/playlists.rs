@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use egui::Vec2;
+use egui::{Id, Key, Modal, UiKind, Vec2};
 use egui_extras::{Column, TableBuilder};
 use tracing::{debug, error};
 
@@ -11,10 +11,16 @@ use crate::{
     database::{connection::DatabaseCommand, models::playlists::playlist::Playlist},
 };
 
+const DELETE_PLAYLIST_MODAL_ID: &str = "delete_playlist_modal";
+
 #[derive(Debug, Clone)]
 pub struct PlaylistTable {
     context: SharedContext,
     channels: Rc<ComponentChannels>,
+    /// The playlist currently being renamed, along with the name being typed in
+    renaming: Option<(Playlist, String)>,
+    /// The playlist waiting on confirmation to be deleted
+    deleting: Option<Playlist>,
 }
 
 impl PlaylistTable {
@@ -23,10 +29,105 @@ impl PlaylistTable {
             .database_command_tx
             .send(DatabaseCommand::QueryPlaylists);
 
-        Self { context, channels }
+        Self {
+            context,
+            channels,
+            renaming: None,
+            deleting: None,
+        }
+    }
+
+    fn send_command(&self, command: DatabaseCommand) {
+        if let Err(err) = self.channels.database_command_tx.send(command) {
+            error!("Failed to send playlist command to database: {}", err);
+        }
+    }
+
+    fn rename_playlist(&mut self) {
+        let Some((playlist, new_name)) = self.renaming.take() else {
+            return;
+        };
+
+        let new_name = new_name.trim().to_string();
+
+        if new_name.is_empty() || new_name == playlist.name {
+            return;
+        }
+
+        self.send_command(DatabaseCommand::RenamePlaylist(playlist, new_name));
+    }
+
+    fn duplicate_playlist(&mut self, playlist: &Playlist) {
+        let name = self
+            .context
+            .borrow()
+            .storage
+            .duplicate_playlist_name(playlist);
+
+        self.send_command(DatabaseCommand::DuplicatePlaylist(playlist.clone(), name));
+    }
+
+    fn ui_error(&mut self, ui: &mut egui::Ui) {
+        let Some(error) = self.context.borrow().ui.playlist.error().map(str::to_owned) else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+
+            if ui.small_button("Dismiss").clicked() {
+                self.context.borrow_mut().ui.playlist.set_error(None);
+            }
+        });
+
+        ui.separator();
+    }
+
+    fn ui_delete_confirmation(&mut self, ctx: &egui::Context) {
+        let Some(playlist) = self.deleting.clone() else {
+            return;
+        };
+
+        let mut should_close = false;
+
+        let modal = Modal::new(Id::new(DELETE_PLAYLIST_MODAL_ID)).show(ctx, |ui| {
+            ui.heading("Delete Playlist");
+
+            ui.separator();
+
+            ui.label(format!(
+                "Delete {}? Its tracks will stay in the library.",
+                playlist.name
+            ));
+
+            ui.separator();
+
+            egui::Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Delete").clicked() {
+                        self.send_command(DatabaseCommand::DeletePlaylist(playlist.clone()));
+
+                        should_close = true;
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        should_close = true;
+                    }
+                },
+            );
+        });
+
+        if modal.should_close() || should_close {
+            self.deleting = None;
+        }
     }
 
     pub fn ui(&mut self, ui: &mut egui::Ui) {
+        self.ui_error(ui);
+        self.ui_delete_confirmation(ui.ctx());
+
         let Vec2 {
             x: width,
             y: height,
@@ -61,17 +162,68 @@ impl PlaylistTable {
                 row.set_selected(selected);
 
                 row.col(|ui| {
-                    let label = ui
-                        .label(playlist.name.clone())
-                        .on_hover_cursor(egui::CursorIcon::Default);
-                    if label.clicked() {
+                    if let Some((renaming_playlist, new_name)) = self.renaming.as_mut()
+                        && renaming_playlist.id == playlist.id
+                    {
+                        let response = ui.text_edit_singleline(new_name);
+                        response.request_focus();
+
+                        if ui.input(|i| i.key_pressed(Key::Escape)) {
+                            self.renaming = None;
+                        } else if response.lost_focus() {
+                            self.rename_playlist();
+                        }
+
+                        return;
+                    }
+
+                    // Smart playlists are marked, and show what picks their tracks when hovered
+                    let label = if let Some(smart) = &playlist.smart {
+                        let limit = smart
+                            .limit
+                            .map(|limit| format!(", first {limit} track(s)"))
+                            .unwrap_or_default();
+
+                        ui.label(
+                            egui::RichText::new(format!("{} (smart)", playlist.name)).italics(),
+                        )
+                        .on_hover_text(format!(
+                            "{}\nSorted by {} ({}){limit}",
+                            smart.query,
+                            smart.sort.column,
+                            smart.sort.order.to_string().to_lowercase()
+                        ))
+                    } else {
+                        ui.label(playlist.name.clone())
+                    };
+
+                    if label.on_hover_cursor(egui::CursorIcon::Default).clicked() {
                         self.toggle_playlist_selection(playlist);
                     }
                 });
 
-                if row.response().clicked() {
+                let response = row.response();
+
+                if response.clicked() {
                     self.toggle_playlist_selection(playlist);
                 }
+
+                response.context_menu(|ui| {
+                    if ui.button("Rename").clicked() {
+                        self.renaming = Some((playlist.clone(), playlist.name.clone()));
+                        ui.close_kind(UiKind::Menu);
+                    }
+
+                    if ui.button("Duplicate").clicked() {
+                        self.duplicate_playlist(playlist);
+                        ui.close_kind(UiKind::Menu);
+                    }
+
+                    if ui.button("Delete...").clicked() {
+                        self.deleting = Some(playlist.clone());
+                        ui.close_kind(UiKind::Menu);
+                    }
+                });
             });
         });
     }
@@ -100,6 +252,11 @@ impl PlaylistTable {
             debug!("De-selected playlist, querying all");
 
             None
+        } else if playlist.is_smart() {
+            // The tracks of smart playlists are picked from all tracks, instead of being stored
+            debug!("Selected smart playlist: {}", playlist.name);
+
+            return;
         } else {
             debug!(
                 "Selected playlist: {:?}",
@@ -0,0 +1,151 @@
+use std::{rc::Rc, time::Duration};
+
+use egui::CursorIcon;
+use egui_extras::{Column, TableBuilder};
+
+use super::{TABLE_HEADER_HEIGHT, TABLE_ROW_HEIGHT};
+use crate::{
+    components::ComponentChannels, context::SharedContext, database::connection::DatabaseCommand,
+    utils::formatting::human_duration,
+};
+
+const POSITION_COLUMN_WIDTH: f32 = 40.0;
+const DURATION_COLUMN_WIDTH: f32 = 80.0;
+const ACTIONS_COLUMN_WIDTH: f32 = 150.0;
+
+/// Changes to the queue requested from a row, applied once the table has been drawn.
+#[derive(Debug, Clone, Copy)]
+enum QueueAction {
+    Move(usize, usize),
+    Remove(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct QueueTable {
+    context: SharedContext,
+}
+
+impl QueueTable {
+    pub fn new(context: SharedContext, channels: Rc<ComponentChannels>) -> Self {
+        let _ = channels
+            .database_command_tx
+            .send(DatabaseCommand::QueryQueue);
+
+        Self { context }
+    }
+
+    fn ui_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let (queue_len, queue_duration) = {
+                let context = self.context.borrow();
+                let tracks = context.playback.queue.tracks();
+                let duration_secs: f64 = tracks.iter().map(|track| track.duration_secs).sum();
+
+                (tracks.len(), Duration::from_secs_f64(duration_secs.floor()))
+            };
+
+            ui.label(format!(
+                "{queue_len} track(s), {}",
+                human_duration(queue_duration, false)
+            ));
+
+            if ui
+                .add_enabled(queue_len > 0, egui::Button::new("Clear"))
+                .clicked()
+            {
+                self.context.borrow_mut().playback.queue.clear();
+            }
+        });
+    }
+
+    fn ui_table(&mut self, ui: &mut egui::Ui) {
+        let height = ui.available_height();
+
+        let tracks = self.context.borrow().playback.queue.tracks().to_vec();
+        let last_index = tracks.len().saturating_sub(1);
+
+        let mut action = None;
+
+        TableBuilder::new(ui)
+            .max_scroll_height(height)
+            .column(Column::auto().at_least(POSITION_COLUMN_WIDTH))
+            .column(Column::remainder())
+            .column(Column::auto().at_least(DURATION_COLUMN_WIDTH))
+            .column(Column::auto().at_least(ACTIONS_COLUMN_WIDTH))
+            .header(TABLE_HEADER_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("#");
+                });
+                header.col(|ui| {
+                    ui.heading("Track");
+                });
+                header.col(|ui| {
+                    ui.heading("Duration");
+                });
+                header.col(|_| {});
+            })
+            .body(|body| {
+                body.rows(TABLE_ROW_HEIGHT, tracks.len(), |mut row| {
+                    let index = row.index();
+                    let Some(track) = tracks.get(index) else {
+                        return;
+                    };
+
+                    row.col(|ui| {
+                        ui.label((index + 1).to_string())
+                            .on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        ui.label(&track.name).on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        let duration = Duration::from_secs_f64(track.duration_secs.floor());
+                        ui.label(human_duration(duration, false))
+                            .on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        if ui
+                            .add_enabled(index > 0, egui::Button::new("Up").small())
+                            .clicked()
+                        {
+                            action = Some(QueueAction::Move(index, index - 1));
+                        }
+
+                        if ui
+                            .add_enabled(index < last_index, egui::Button::new("Down").small())
+                            .clicked()
+                        {
+                            action = Some(QueueAction::Move(index, index + 1));
+                        }
+
+                        if ui.add(egui::Button::new("Remove").small()).clicked() {
+                            action = Some(QueueAction::Remove(index));
+                        }
+                    });
+                });
+            });
+
+        let queue = &mut self.context.borrow_mut().playback.queue;
+
+        match action {
+            Some(QueueAction::Move(from, to)) => queue.move_track(from, to),
+            Some(QueueAction::Remove(index)) => {
+                queue.remove(index);
+            }
+            None => {}
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.vertical(|ui| {
+            self.ui_toolbar(ui);
+
+            ui.separator();
+
+            self.ui_table(ui);
+        });
+    }
+}
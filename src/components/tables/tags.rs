@@ -1,11 +1,219 @@
-#[derive(Debug, Clone, Default)]
+use std::rc::Rc;
+
+use egui::{CursorIcon, Key, UiKind};
+use egui_extras::{Column, TableBuilder};
+use tracing::{debug, error};
+
+use super::{TABLE_HEADER_HEIGHT, TABLE_ROW_HEIGHT};
+use crate::{
+    components::ComponentChannels,
+    context::SharedContext,
+    database::{connection::DatabaseCommand, models::tags::tag::Tag},
+};
+
+const TRACK_COUNT_COLUMN_WIDTH: f32 = 60.0;
+
+#[derive(Debug, Clone)]
 pub struct TagTable {
-    // context: SharedContext,
-    // tags: Vec<Playlist>,
+    context: SharedContext,
+    channels: Rc<ComponentChannels>,
+    new_tag_name: String,
+    /// The tag currently being renamed, along with the name being typed in
+    renaming: Option<(Tag, String)>,
 }
 
 impl TagTable {
+    pub fn new(context: SharedContext, channels: Rc<ComponentChannels>) -> Self {
+        let _ = channels
+            .database_command_tx
+            .send(DatabaseCommand::QueryTags);
+
+        Self {
+            context,
+            channels,
+            new_tag_name: String::new(),
+            renaming: None,
+        }
+    }
+
+    fn send_command(&self, command: DatabaseCommand) {
+        if let Err(err) = self.channels.database_command_tx.send(command) {
+            error!("Failed to send tag command to database: {}", err);
+        }
+    }
+
+    fn create_tag(&mut self) {
+        let tag_name = self.new_tag_name.trim().to_string();
+
+        if tag_name.is_empty() {
+            error!("Cannot create tag with empty name");
+
+            return;
+        }
+
+        self.send_command(DatabaseCommand::InsertTag(tag_name));
+        self.new_tag_name.clear();
+    }
+
+    fn rename_tag(&mut self) {
+        let Some((tag, new_name)) = self.renaming.take() else {
+            return;
+        };
+
+        let new_name = new_name.trim().to_string();
+
+        if new_name.is_empty() || new_name == tag.name {
+            return;
+        }
+
+        self.send_command(DatabaseCommand::RenameTag(tag, new_name));
+    }
+
+    fn toggle_tag_selection(&mut self, tag: &Tag) {
+        let mut context = self.context.borrow_mut();
+
+        if context
+            .ui
+            .tag
+            .selected()
+            .is_some_and(|selected_tag| selected_tag.id == tag.id)
+        {
+            debug!("De-selected tag {}", tag.name);
+            context.ui.tag.set_selected(None);
+        } else {
+            debug!("Selected tag {}", tag.name);
+            context.ui.tag.set_selected(Some(tag.clone()));
+        }
+    }
+
+    fn ui_create(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let response =
+                ui.add(egui::TextEdit::singleline(&mut self.new_tag_name).hint_text("New tag..."));
+
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+
+            if ui.button("Create").clicked() || submitted {
+                self.create_tag();
+            }
+        });
+    }
+
+    fn ui_error(&mut self, ui: &mut egui::Ui) {
+        let Some(error) = self.context.borrow().ui.tag.error().map(str::to_owned) else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+
+            if ui.small_button("Dismiss").clicked() {
+                self.context.borrow_mut().ui.tag.set_error(None);
+            }
+        });
+    }
+
+    fn ui_table(&mut self, ui: &mut egui::Ui) {
+        let height = ui.available_height();
+
+        let tags: Vec<(Tag, usize)> = {
+            let context = self.context.borrow();
+            context
+                .storage
+                .tags()
+                .map(|(tag, track_ids)| (tag.clone(), track_ids.len()))
+                .collect()
+        };
+
+        let selected_tag = self.context.borrow().ui.tag.selected();
+
+        TableBuilder::new(ui)
+            .max_scroll_height(height)
+            .column(Column::remainder())
+            .column(Column::auto().at_least(TRACK_COUNT_COLUMN_WIDTH))
+            .sense(egui::Sense::click())
+            .header(TABLE_HEADER_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Tag");
+                });
+                header.col(|ui| {
+                    ui.heading("Tracks");
+                });
+            })
+            .body(|body| {
+                body.rows(TABLE_ROW_HEIGHT, tags.len(), |mut row| {
+                    let Some((tag, track_count)) = tags.get(row.index()) else {
+                        return;
+                    };
+
+                    row.set_selected(
+                        selected_tag
+                            .as_ref()
+                            .is_some_and(|selected_tag| selected_tag.id == tag.id),
+                    );
+
+                    row.col(|ui| {
+                        if let Some((renaming_tag, new_name)) = self.renaming.as_mut()
+                            && renaming_tag.id == tag.id
+                        {
+                            let response = ui.text_edit_singleline(new_name);
+                            response.request_focus();
+
+                            if ui.input(|i| i.key_pressed(Key::Escape)) {
+                                self.renaming = None;
+                            } else if response.lost_focus() {
+                                self.rename_tag();
+                            }
+
+                            return;
+                        }
+
+                        let label = ui
+                            .label(tag.name.clone())
+                            .on_hover_cursor(CursorIcon::Default);
+                        if label.clicked() {
+                            self.toggle_tag_selection(tag);
+                        }
+                    });
+
+                    row.col(|ui| {
+                        let label = ui
+                            .label(track_count.to_string())
+                            .on_hover_cursor(CursorIcon::Default);
+                        if label.clicked() {
+                            self.toggle_tag_selection(tag);
+                        }
+                    });
+
+                    let response = row.response();
+
+                    if response.clicked() {
+                        self.toggle_tag_selection(tag);
+                    }
+
+                    response.context_menu(|ui| {
+                        if ui.button("Rename").clicked() {
+                            self.renaming = Some((tag.clone(), tag.name.clone()));
+                            ui.close_kind(UiKind::Menu);
+                        }
+
+                        if ui.button("Delete").clicked() {
+                            self.send_command(DatabaseCommand::DeleteTag(tag.clone()));
+                            ui.close_kind(UiKind::Menu);
+                        }
+                    });
+                });
+            });
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) {
-        ui.label("I am a tag table");
+        ui.vertical(|ui| {
+            self.ui_create(ui);
+            self.ui_error(ui);
+
+            ui.separator();
+
+            self.ui_table(ui);
+        });
     }
 }
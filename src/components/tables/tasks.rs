@@ -1,10 +1,332 @@
-#[derive(Debug, Clone, Default)]
+use std::rc::Rc;
+
+use egui::{CursorIcon, ProgressBar};
+use egui_extras::{Column, TableBuilder};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use super::{TABLE_HEADER_HEIGHT, TABLE_ROW_HEIGHT};
+use crate::{
+    components::ComponentChannels,
+    context::{SharedContext, tasks::Task},
+    database::connection::DatabaseCommand,
+    files::open::select_folders_dialog,
+    tasks::{TaskKind, TaskStatus},
+    utils::formatting::human_duration,
+};
+
+const PROGRESS_COLUMN_WIDTH: f32 = 150.0;
+const TIME_COLUMN_WIDTH: f32 = 70.0;
+const STATUS_COLUMN_WIDTH: f32 = 140.0;
+const ACTION_COLUMN_WIDTH: f32 = 60.0;
+
+#[derive(Debug, Clone)]
 pub struct TaskTable {
-    // context: SharedContext,
+    context: SharedContext,
+    channels: Rc<ComponentChannels>,
+    /// The task whose failures are being shown below the table
+    selected: Option<Uuid>,
 }
 
 impl TaskTable {
+    pub fn new(context: SharedContext, channels: Rc<ComponentChannels>) -> Self {
+        Self {
+            context,
+            channels,
+            selected: None,
+        }
+    }
+
+    fn rehash_tracks(&self) {
+        let track_count = self
+            .context
+            .borrow()
+            .storage
+            .get_playlist_tracks(None)
+            .map_or(0, |tracks| tracks.len());
+
+        let task = self.context.borrow_mut().tasks.register(
+            TaskKind::Rehash,
+            "Rehash all tracks".to_string(),
+            track_count,
+        );
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::RehashTracks(task))
+        {
+            error!("Failed to send rehash tracks command to database: {}", err);
+        }
+    }
+
+    /// Starts validating every track's file, unless a validation is already running.
+    pub fn validate_tracks(&self) {
+        if self.is_running(TaskKind::Validation) {
+            debug!("Library validation is already running");
+            return;
+        }
+
+        let track_count = self
+            .context
+            .borrow()
+            .storage
+            .get_playlist_tracks(None)
+            .map_or(0, |tracks| tracks.len());
+
+        let task = self.context.borrow_mut().tasks.register(
+            TaskKind::Validation,
+            "Validate library".to_string(),
+            track_count,
+        );
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::ValidateTracks(task))
+        {
+            error!(
+                "Failed to send validate tracks command to database: {}",
+                err
+            );
+        }
+    }
+
+    /// Asks for the folders to look through, and starts looking for the files of missing tracks in them.
+    fn locate_missing_tracks(&self) {
+        let Some(roots) = select_folders_dialog() else {
+            return;
+        };
+
+        // How many files there are to look through isn't known until the folders are walked
+        let task = self.context.borrow_mut().tasks.register(
+            TaskKind::Relocation,
+            "Locate missing tracks".to_string(),
+            0,
+        );
+
+        if let Err(err) = self
+            .channels
+            .database_command_tx
+            .send(DatabaseCommand::LocateMissingTracks(roots, task))
+        {
+            error!(
+                "Failed to send locate missing tracks command to database: {}",
+                err
+            );
+        }
+    }
+
+    fn is_running(&self, kind: TaskKind) -> bool {
+        self.context
+            .borrow()
+            .tasks
+            .running()
+            .any(|task| task.kind == kind)
+    }
+
+    fn toggle_task_selection(&mut self, task: &Task) {
+        if self.selected == Some(task.id()) {
+            self.selected = None;
+        } else {
+            debug!("Selected task {}", task.label);
+            self.selected = Some(task.id());
+        }
+    }
+
+    fn status_text(task: &Task) -> String {
+        match (&task.status, task.failures.len()) {
+            (TaskStatus::Running | TaskStatus::Completed | TaskStatus::Cancelled, 0) => {
+                task.status.to_string()
+            }
+            (status, failures) => format!("{status} ({failures} failed)"),
+        }
+    }
+
+    fn ui_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let rehash_running = self.is_running(TaskKind::Rehash);
+
+            let rehash_button = ui
+                .add_enabled(!rehash_running, egui::Button::new("Rehash all tracks"))
+                .on_hover_text("This might take a while, as every track file is read again");
+            if rehash_button.clicked() {
+                self.rehash_tracks();
+            }
+
+            let validation_running = self.is_running(TaskKind::Validation);
+
+            let validate_button = ui
+                .add_enabled(!validation_running, egui::Button::new("Validate library"))
+                .on_hover_text(
+                    "Check that every track's file still exists, marking the ones that don't",
+                );
+            if validate_button.clicked() {
+                self.validate_tracks();
+            }
+
+            let relocation_running = self.is_running(TaskKind::Relocation);
+
+            let locate_button = ui
+                .add_enabled(
+                    !relocation_running,
+                    egui::Button::new("Locate missing tracks..."),
+                )
+                .on_hover_text(
+                    "Look through folders for files with the same contents as missing tracks.\n\
+                    What's found is shown before anything is changed",
+                );
+            if locate_button.clicked() {
+                self.locate_missing_tracks();
+            }
+
+            if ui.button("Clear finished").clicked() {
+                self.context.borrow_mut().tasks.clear_finished();
+                self.selected = None;
+            }
+        });
+    }
+
+    fn ui_table(&mut self, ui: &mut egui::Ui, height: f32) {
+        // Borrowed through its own handle, so the selection can still change while the tasks are shown
+        let context = Rc::clone(&self.context);
+        let context = context.borrow();
+        let tasks = context.tasks.tasks();
+
+        TableBuilder::new(ui)
+            .id_salt("task_table")
+            .max_scroll_height(height)
+            .column(Column::remainder())
+            .column(Column::auto().at_least(PROGRESS_COLUMN_WIDTH))
+            .column(Column::auto().at_least(TIME_COLUMN_WIDTH))
+            .column(Column::auto().at_least(TIME_COLUMN_WIDTH))
+            .column(Column::auto().at_least(STATUS_COLUMN_WIDTH))
+            .column(Column::auto().at_least(ACTION_COLUMN_WIDTH))
+            .sense(egui::Sense::click())
+            .header(TABLE_HEADER_HEIGHT, |mut header| {
+                header.col(|ui| {
+                    ui.heading("Task");
+                });
+                header.col(|ui| {
+                    ui.heading("Progress");
+                });
+                header.col(|ui| {
+                    ui.heading("Started");
+                });
+                header.col(|ui| {
+                    ui.heading("Elapsed");
+                });
+                header.col(|ui| {
+                    ui.heading("Status");
+                });
+                header.col(|_| {});
+            })
+            .body(|body| {
+                body.rows(TABLE_ROW_HEIGHT, tasks.len(), |mut row| {
+                    let Some(task) = tasks.get(row.index()) else {
+                        return;
+                    };
+
+                    row.set_selected(self.selected == Some(task.id()));
+
+                    row.col(|ui| {
+                        ui.label(&task.label).on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        let progress_bar = ProgressBar::new(task.progress())
+                            .text(format!("{}/{}", task.completed, task.total));
+                        ui.add(progress_bar);
+                    });
+
+                    row.col(|ui| {
+                        ui.label(task.started_at.format("%H:%M:%S").to_string())
+                            .on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        let elapsed = task.elapsed().to_std().unwrap_or_default();
+                        ui.label(human_duration(elapsed, false))
+                            .on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        ui.label(Self::status_text(task))
+                            .on_hover_cursor(CursorIcon::Default);
+                    });
+
+                    row.col(|ui| {
+                        if task.status.is_running() && ui.button("Cancel").clicked() {
+                            debug!("Cancelling task {}", task.label);
+                            context.tasks.cancel(task.id());
+                        }
+                    });
+
+                    if row.response().clicked() {
+                        self.toggle_task_selection(task);
+                    }
+                });
+            });
+    }
+
+    fn ui_failures(&mut self, ui: &mut egui::Ui) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+
+        let context = self.context.borrow();
+        let Some(task) = context
+            .tasks
+            .tasks()
+            .iter()
+            .find(|task| task.id() == selected)
+        else {
+            return;
+        };
+
+        ui.separator();
+
+        if task.failures.is_empty() {
+            ui.label(format!("No failures for {}", task.label));
+            return;
+        }
+
+        ui.label(format!(
+            "{} failure(s) for {}",
+            task.failures.len(),
+            task.label
+        ));
+
+        egui::ScrollArea::vertical()
+            .id_salt("task_failures")
+            .auto_shrink([false, true])
+            .show(ui, |ui| {
+                for failure in &task.failures {
+                    ui.label(format!("{}: {}", failure.path.display(), failure.reason));
+                }
+            });
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) {
-        ui.label("I am a tasks table");
+        // Keep elapsed times and progress moving while anything is running
+        if self.context.borrow().tasks.running().next().is_some() {
+            ui.ctx().request_repaint();
+        }
+
+        ui.vertical(|ui| {
+            self.ui_toolbar(ui);
+
+            ui.separator();
+
+            let height = if self.selected.is_some() {
+                ui.available_height() / 2.0
+            } else {
+                ui.available_height()
+            };
+
+            self.ui_table(ui, height);
+
+            self.ui_failures(ui);
+        });
     }
 }
@@ -1,31 +1,77 @@
 use std::{
+    collections::HashSet,
     rc::Rc,
     time::{Duration, Instant},
 };
 
-use egui::CursorIcon;
+use chrono::{Local, Utc};
+use egui::{CursorIcon, Modifiers, UiKind};
 use egui_extras::{Column, TableBuilder, TableRow};
 use rand::RngExt;
 use tracing::{debug, error};
+use uuid::Uuid;
 
 use super::{TABLE_HEADER_HEIGHT, TABLE_ROW_HEIGHT};
 use crate::{
     components::ComponentChannels,
     config::{
         core::SharedConfig,
+        playback::MAX_CROSSFADE_SECS,
+        save_config,
         search::{MatcherFn, SearchMatchingStrategy},
     },
     context::{
-        AutoplayType, PlayDirection, SharedContext, ShuffleType,
-        playback::{PlaylistState, SelectedTrackContext},
+        AutoplayType, Context, PendingRemoval, PlayDirection, RemovalTarget, RepeatMode,
+        SharedContext, ShuffleType, StorageContext,
+        playback::{
+            EnqueuedFrom, EnqueuedTrack, PlaybackContext, PlaylistState, SelectedTrackContext,
+        },
     },
-    database::{connection::DatabaseCommand, models::tracks::Track},
+    database::{
+        connection::DatabaseCommand,
+        models::{
+            sort::{SortOrder, TrackColumn, TrackSort},
+            tags::tag::Tag,
+            tracks::{Track, TrackRelocation},
+        },
+    },
+    files::open::select_file_dialog,
     playback::state::PlayerCommand,
-    utils::{formatting::human_duration, random::filtered_random_index},
+    utils::{
+        formatting::human_duration,
+        query::{Query, QueryEnv, QueryError},
+        random::filtered_random_index,
+    },
 };
 
 const INDEX_COLUMN_WIDTH: f32 = 50.0;
 const DURATION_COLUMN_WIDTH: f32 = 100.0;
+const DATE_ADDED_COLUMN_WIDTH: f32 = 140.0;
+const HASH_COLUMN_WIDTH: f32 = 160.0;
+const VALID_COLUMN_WIDTH: f32 = 60.0;
+const TAG_COLUMN_WIDTH: f32 = 140.0;
+const NUMBER_COLUMN_WIDTH: f32 = 60.0;
+
+const INVALID_TRACK_HELP: &str =
+    "This track's file was missing or couldn't be read when the library was last validated.
+Right click to relocate it, ignore it, or remove it from the library.";
+
+const SEARCH_HELP: &str =
+    "Words match the start of words in any field or tag of a track, and all have to match unless
+joined with OR. Quote words to match them as a phrase. Exclude a term with a leading - or NOT,
+and group terms with parentheses.
+
+Search a field with field:value, or compare with <, <=, > and >=:
+  artist:\"daft punk\" album:discovery genre:house tag:live path:/mnt/vinyl
+  year>=2000 track:1 disc:2 duration>4:00 added:<30d added:>2024-01-31 valid:no";
+
+/// A full text search that's waiting on its results from the database.
+pub struct PendingFullText {
+    pub full_text: String,
+    /// The rest of the query, which the found tracks still have to match
+    pub filters: Query,
+    pub start: Instant,
+}
 
 pub struct TrackSearch {
     pub text: String,
@@ -35,6 +81,10 @@ pub struct TrackSearch {
     pub focus_requested: bool,
     pub duration: Option<Duration>,
     pub yielded_results: usize,
+    /// Why the current search couldn't be parsed, if it couldn't
+    pub error: Option<QueryError>,
+    /// The full text search sent to the database that hasn't been answered yet
+    pub pending_full_text: Option<PendingFullText>,
     pub matcher: SearchMatchingStrategy,
     pub matcher_fn: MatcherFn,
 }
@@ -49,6 +99,8 @@ impl Default for TrackSearch {
             focus_requested: false,
             duration: None,
             yielded_results: 0,
+            error: None,
+            pending_full_text: None,
             matcher: SearchMatchingStrategy::default(),
             matcher_fn: Box::new(|_, _| false),
         }
@@ -73,6 +125,10 @@ pub struct TrackTable {
     channels: Rc<ComponentChannels>,
     search: TrackSearch,
     scroll_to_selected: bool,
+    /// IDs of the tracks selected in the table, which are acted on together from the context menu
+    selection: HashSet<Uuid>,
+    /// Row the last selection started from, which a shift click selects up to
+    selection_anchor: Option<usize>,
 }
 
 impl TrackTable {
@@ -94,6 +150,8 @@ impl TrackTable {
             channels,
             search,
             scroll_to_selected: false,
+            selection: HashSet::new(),
+            selection_anchor: None,
         }
     }
 
@@ -109,6 +167,39 @@ impl TrackTable {
         self.search.focus_requested = true;
     }
 
+    /// Shows the ranked results of a full text search, unless the search has changed since.
+    pub fn show_search_results(&mut self, full_text: &str, track_ids: &[Uuid]) {
+        let Some(pending) = self
+            .search
+            .pending_full_text
+            .take_if(|pending| pending.full_text == full_text)
+        else {
+            debug!("Ignoring results of outdated search {:?}", full_text);
+            return;
+        };
+
+        let selected_playlist = { self.context.borrow().ui.playlist.selected() };
+
+        let mut context = self.context.borrow_mut();
+        let storage_context = &mut context.storage;
+
+        let env = QueryEnv {
+            matcher: &self.search.matcher_fn,
+            track_tags: storage_context.tag_names_by_track(),
+            now: Utc::now(),
+        };
+
+        let predicate = |track: &Track| pending.filters.matches(track, &env);
+
+        if let Some(filtered) =
+            storage_context.filter_ranked(&selected_playlist, track_ids, predicate)
+        {
+            self.search.yielded_results = filtered.len();
+        }
+
+        self.search.duration = Some(pending.start.elapsed());
+    }
+
     fn toggle_row_play(&mut self, row_index: usize, track: &Track) {
         // if the selected track is one that is playing, pause it.
         if self
@@ -185,36 +276,59 @@ impl TrackTable {
             .select_track(Some(new_track_context));
     }
 
-    /// Selects the next track from the track table tracks attribute
+    /// Selects the next track from the track table tracks attribute, once the current one has
+    /// finished or a playback control asked for another track.
+    ///
+    /// Shortly before the current track ends, the next track is picked the same way and enqueued
+    /// in the player instead, so that it starts without a gap.
+    ///
+    /// Called every frame regardless of which tabs are visible, so autoplay keeps going.
     // TODO: Move handling selection of the new track into the playback context
-    fn select_new_track(&mut self) {
-        let track_context = {
+    pub fn select_new_track(&mut self) {
+        let (track_context, enqueue) = {
             let context = self.context.borrow();
 
             let Some(track) = &context.playback.selected_track else {
                 return;
             };
 
-            if !context.playback.autoplay.select_new_track() {
+            let autoplay = &context.playback.autoplay;
+            let enqueue = if autoplay.select_new_track() {
+                false
+            } else if autoplay.enqueue_next_track() {
+                true
+            } else {
                 return;
-            }
+            };
 
-            Some(track.clone())
+            (Some(track.clone()), enqueue)
         };
 
-        let mut context = self.context.borrow_mut();
+        // Borrowed through its own handle, so the track can be handed off with `play_new_track`
+        let shared_context = self.context.clone();
+        let mut context = shared_context.borrow_mut();
 
-        // If a button for playback control (forward/backward) was pressed, select that instead of autoplay
-        let mut controlled = false;
-        let autoplay_selector =
-            if let Some(controlled_autoplay) = context.playback.autoplay.consume_controlled() {
-                controlled = true;
-                controlled_autoplay
-            } else {
-                context.playback.autoplay.autoplay().to_owned()
-            };
+        let (autoplay_selector, controlled, requested_direction) = if enqueue {
+            context.playback.autoplay.set_enqueue_next_track(false);
 
-        context.playback.autoplay.set_select_new_track(false);
+            (context.playback.autoplay.autoplay().to_owned(), false, None)
+        } else {
+            // The player drops anything enqueued when it's given a new track
+            context.playback.autoplay.take_enqueued();
+            context.playback.autoplay.set_select_new_track(false);
+
+            // If a button for playback control (forward/backward) was pressed, select that instead of autoplay
+            let controlled_autoplay = context.playback.autoplay.consume_controlled();
+            let controlled = controlled_autoplay.is_some();
+            let autoplay_selector = controlled_autoplay
+                .unwrap_or_else(|| context.playback.autoplay.autoplay().to_owned());
+
+            (
+                autoplay_selector,
+                controlled,
+                context.playback.autoplay.consume_history_direction(),
+            )
+        };
 
         let tracks = if let Some(playlist_state) = &context.playback.selected_playlist.playlist() {
             &playlist_state.tracks()
@@ -226,14 +340,96 @@ impl TrackTable {
                 .unwrap_or_default()
         };
 
+        let volume = self.config.borrow().playback.volume;
+
+        // Playback controls always move to another track, so repeating only applies to autoplay
+        let repeat = if requested_direction.is_some() {
+            RepeatMode::All
+        } else {
+            self.config.borrow().playback.repeat
+        };
+        let history_direction = requested_direction.unwrap_or_default();
+
+        if repeat == RepeatMode::One
+            && let Some(track_context) = &track_context
+        {
+            let new_track_context =
+                SelectedTrackContext::new(track_context.track.clone(), track_context.index, true);
+
+            self.play_new_track(
+                &mut context.playback,
+                new_track_context,
+                EnqueuedFrom::Autoplay,
+                enqueue,
+                volume,
+            );
+
+            return;
+        }
+
+        // Walk through the session history before picking a new track
+        let history_track = match (&history_direction, enqueue) {
+            (PlayDirection::Forward, true) => context.playback.session_history.peek_forward(),
+            (PlayDirection::Forward, false) => context.playback.session_history.forward(),
+            (PlayDirection::Backward, _) => context.playback.session_history.back(),
+        };
+
+        if let Some(history_track) = history_track {
+            let history_index = tracks
+                .iter()
+                .position(|track| track.id == history_track.id)
+                .unwrap_or_default();
+
+            let new_track_context = SelectedTrackContext::new(history_track, history_index, true);
+
+            self.play_new_track(
+                &mut context.playback,
+                new_track_context,
+                EnqueuedFrom::History,
+                enqueue,
+                volume,
+            );
+
+            return;
+        }
+
+        // Queued tracks take priority over anything autoplay would pick.
+        // They're only taken off the queue once they start playing.
+        let queued_track = match (&history_direction, enqueue) {
+            (PlayDirection::Forward, true) => context.playback.queue.tracks().first().cloned(),
+            (PlayDirection::Forward, false) => context.playback.queue.pop_front(),
+            (PlayDirection::Backward, _) => None,
+        };
+
+        if let Some(queued_track) = queued_track {
+            let queued_index = tracks
+                .iter()
+                .position(|track| track.id == queued_track.id)
+                .unwrap_or_default();
+
+            let new_track_context = SelectedTrackContext::new(queued_track, queued_index, true);
+
+            self.play_new_track(
+                &mut context.playback,
+                new_track_context,
+                EnqueuedFrom::Queue,
+                enqueue,
+                volume,
+            );
+
+            return;
+        }
+
         let Some(index) = track_context.and_then(|track_context| {
             tracks
                 .iter()
                 .position(|track| track.hash == track_context.track.hash)
         }) else {
-            // Could not find a new track to play, clearing sink
-            let _ = self.channels.player_command_tx.send(PlayerCommand::Clear);
-            context.playback.select_track(None);
+            // Could not find a new track to play, clearing sink once the current track has finished
+            if !enqueue {
+                let _ = self.channels.player_command_tx.send(PlayerCommand::Clear);
+                context.playback.select_track(None);
+            }
 
             return;
         };
@@ -246,10 +442,17 @@ impl TrackTable {
 
         let tracks_len = tracks.len();
 
+        // Without repeating, autoplay stops once it reaches the end of the tracks
         let new_index = match autoplay_selector {
             AutoplayType::Iterative(play_direction) => match play_direction {
-                PlayDirection::Forward => (index + 1) % tracks_len,
-                PlayDirection::Backward => (index + tracks_len.saturating_sub(1)) % tracks_len,
+                PlayDirection::Forward if repeat == RepeatMode::Off && index + 1 >= tracks_len => {
+                    None
+                }
+                PlayDirection::Backward if repeat == RepeatMode::Off && index == 0 => None,
+                PlayDirection::Forward => Some((index + 1) % tracks_len),
+                PlayDirection::Backward => {
+                    Some((index + tracks_len.saturating_sub(1)) % tracks_len)
+                }
             },
             AutoplayType::Shuffle(shuffle_type) => match shuffle_type {
                 // TODO: There's the possibility of indices being offset during tracks being added to playlist(s)
@@ -258,131 +461,512 @@ impl TrackTable {
 
                     if let Some(filtered_index) = filtered_random_index(tracks_len, &played_tracks)
                     {
-                        filtered_index
+                        Some(filtered_index)
+                    } else if repeat == RepeatMode::Off {
+                        debug!("All tracks have been in the Pseudo random shuffler -- stopping");
+
+                        None
                     } else {
                         debug!("All tracks have been in the Pseudo random shuffler -- resetting");
 
                         context.playback.selected_playlist.clear_played_tracks();
 
                         let mut rng = rand::rng();
-                        rng.random_range(0..tracks_len)
+                        Some(rng.random_range(0..tracks_len))
                     }
                 }
                 ShuffleType::TrueRandom => {
                     let mut rng = rand::rng();
-                    rng.random_range(0..tracks_len)
+                    Some(rng.random_range(0..tracks_len))
                 }
             },
         };
 
         // TODO: Configurable value to autoplay from filtered tracks
-        let Some(new_track) = tracks.get(new_index) else {
-            let _ = self.channels.player_command_tx.send(PlayerCommand::Clear);
-            context.playback.select_track(None);
+        let Some((new_index, new_track)) =
+            new_index.and_then(|new_index| Some((new_index, tracks.get(new_index)?)))
+        else {
+            if !enqueue {
+                let _ = self.channels.player_command_tx.send(PlayerCommand::Clear);
+                context.playback.select_track(None);
+            }
 
             return;
         };
 
-        let volume = self.config.borrow().playback.volume;
+        let new_track_context = SelectedTrackContext::new(new_track.clone(), new_index, true);
+
+        self.play_new_track(
+            &mut context.playback,
+            new_track_context,
+            EnqueuedFrom::Autoplay,
+            enqueue,
+            volume,
+        );
+    }
+
+    /// Hands a newly picked track to the player. An enqueued track is only selected once the
+    /// player reports that it has started.
+    fn play_new_track(
+        &mut self,
+        playback: &mut PlaybackContext,
+        new_track_context: SelectedTrackContext,
+        from: EnqueuedFrom,
+        enqueue: bool,
+        volume: f32,
+    ) {
+        let track = new_track_context.track.clone();
+
+        if enqueue {
+            // Tracks that carry on from the same album are played gaplessly instead of crossfading
+            let crossfade_secs = self.config.borrow().playback.crossfade_secs;
+            let continues_album = playback
+                .selected_track
+                .as_ref()
+                .is_some_and(|current| track.continues_album(&current.track));
+            let crossfade = (crossfade_secs > 0.0 && !continues_album)
+                .then(|| Duration::from_secs_f32(crossfade_secs.min(MAX_CROSSFADE_SECS)));
+
+            let _ = self
+                .channels
+                .player_command_tx
+                .send(PlayerCommand::Enqueue(track, crossfade));
+
+            debug!("Enqueued track from {:?}: {:?}", from, new_track_context);
+
+            playback.autoplay.set_enqueued(Some(EnqueuedTrack {
+                track: new_track_context,
+                from,
+            }));
+
+            return;
+        }
 
         let _ = self
             .channels
             .player_command_tx
-            .send(PlayerCommand::Create(new_track.clone(), volume));
+            .send(PlayerCommand::Create(track, volume));
 
-        let new_track_context = SelectedTrackContext::new(new_track.clone(), new_index, true);
+        debug!("Selected track from {:?}: {:?}", from, new_track_context);
 
-        debug!("Selected new track with autoplay: {:?}", new_track_context);
+        match from {
+            EnqueuedFrom::History => playback.select_history_track(new_track_context),
+            EnqueuedFrom::Queue | EnqueuedFrom::Autoplay => {
+                playback.select_track(Some(new_track_context))
+            }
+        }
 
-        context.playback.select_track(Some(new_track_context));
+        self.scroll_to_selected = true;
+    }
+
+    /// Sorts the selected playlist's tracks, carrying autoplay over to the new order if it's the playlist
+    /// being autoplayed.
+    fn set_sort(&mut self, sort: TrackSort) {
+        let mut context = self.context.borrow_mut();
+
+        let selected_playlist = context.ui.playlist.selected();
+        let autoplay_playlist = context.playback.selected_playlist.playlist();
+        let autoplaying =
+            autoplay_playlist.as_ref().map(PlaylistState::playlist) == selected_playlist;
+
+        let previous_tracks = match &autoplay_playlist {
+            Some(playlist_state) => playlist_state.tracks(),
+            None => context
+                .storage
+                .get_playlist_tracks(None)
+                .cloned()
+                .unwrap_or_default(),
+        };
+
+        context
+            .storage
+            .set_track_sort(selected_playlist.as_ref(), sort);
+
+        if autoplaying {
+            let sorted_tracks = context
+                .storage
+                .get_playlist_tracks(selected_playlist.as_ref())
+                .cloned()
+                .unwrap_or_default();
+
+            context
+                .playback
+                .reorder_tracks(&previous_tracks, &sorted_tracks);
+        }
 
         self.scroll_to_selected = true;
     }
 
-    fn table_body_row(&mut self, mut row: TableRow<'_, '_>, track: &Track) {
+    fn table_column(column: TrackColumn) -> Column {
+        match column {
+            TrackColumn::Index => Column::auto().at_least(INDEX_COLUMN_WIDTH).resizable(true),
+            TrackColumn::Name | TrackColumn::Path => Column::remainder().clip(true),
+            TrackColumn::Title
+            | TrackColumn::Artist
+            | TrackColumn::AlbumArtist
+            | TrackColumn::Album
+            | TrackColumn::Genre => Column::initial(TAG_COLUMN_WIDTH).clip(true).resizable(true),
+            TrackColumn::TrackNumber | TrackColumn::DiscNumber | TrackColumn::Year => {
+                Column::auto().at_least(NUMBER_COLUMN_WIDTH)
+            }
+            TrackColumn::Duration => Column::auto().at_least(DURATION_COLUMN_WIDTH),
+            TrackColumn::DateAdded => Column::auto()
+                .at_least(DATE_ADDED_COLUMN_WIDTH)
+                .resizable(true),
+            TrackColumn::Hash => Column::initial(HASH_COLUMN_WIDTH)
+                .clip(true)
+                .resizable(true),
+            TrackColumn::Valid => Column::auto().at_least(VALID_COLUMN_WIDTH),
+        }
+    }
+
+    fn column_text(column: TrackColumn, row_index: usize, track: &Track) -> String {
+        match column {
+            TrackColumn::Index => row_index.to_string(),
+            TrackColumn::Name => track.name.clone(),
+            TrackColumn::Title => track.title.clone().unwrap_or_default(),
+            TrackColumn::Artist => track.artist.clone().unwrap_or_default(),
+            TrackColumn::AlbumArtist => track.album_artist.clone().unwrap_or_default(),
+            TrackColumn::Album => track.album.clone().unwrap_or_default(),
+            TrackColumn::TrackNumber => track
+                .track_number
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            TrackColumn::DiscNumber => track
+                .disc_number
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            TrackColumn::Year => track
+                .year
+                .map(|value| value.to_string())
+                .unwrap_or_default(),
+            TrackColumn::Genre => track.genre.clone().unwrap_or_default(),
+            TrackColumn::Duration => {
+                human_duration(Duration::from_secs_f64(track.duration_secs), false)
+            }
+            TrackColumn::DateAdded => track
+                .created_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            TrackColumn::Path => track.path.display().to_string(),
+            TrackColumn::Hash => track.hash.clone().unwrap_or_default(),
+            TrackColumn::Valid => if track.valid { "Yes" } else { "No" }.to_string(),
+        }
+    }
+
+    /// Selects a clicked row, adding it to the selection when ctrl is held,
+    /// or selecting every row up to it from the previously clicked row when shift is held.
+    fn select_row(&mut self, row_index: usize, modifiers: Modifiers, tracks: &[Track]) {
+        let Some(track) = tracks.get(row_index) else {
+            return;
+        };
+
+        if modifiers.shift
+            && let Some(anchor) = self.selection_anchor
+        {
+            let range = anchor.min(row_index)..=anchor.max(row_index);
+
+            if !modifiers.command {
+                self.selection.clear();
+            }
+            self.selection
+                .extend(tracks[range].iter().map(|track| track.id));
+
+            return;
+        }
+
+        if modifiers.command {
+            if !self.selection.remove(&track.id) {
+                self.selection.insert(track.id);
+            }
+        } else {
+            self.selection = HashSet::from([track.id]);
+        }
+
+        self.selection_anchor = Some(row_index);
+    }
+
+    /// Takes tracks out of storage, giving the removal an undo window before it's committed
+    /// to the database. A removal that was still waiting on its undo window is committed right away.
+    fn remove_tracks(&mut self, target: RemovalTarget, track_ids: Vec<Uuid>) {
+        let mut context = self.context.borrow_mut();
+
+        let removed = update_autoplayed_tracks(&mut context, |storage| match &target {
+            RemovalTarget::Playlist(playlist) => {
+                storage.remove_playlist_tracks(playlist, &track_ids)
+            }
+            RemovalTarget::Library => storage.remove_library_tracks(&track_ids),
+        });
+
+        for track_id in &track_ids {
+            self.selection.remove(track_id);
+        }
+
+        let removal = PendingRemoval::new(target, track_ids, removed);
+        debug!("{}", removal.description());
+
+        if let Some(previous) = context.removal.start(removal)
+            && let Err(err) = self.channels.database_command_tx.send(previous.command())
+        {
+            error!("Failed to send track removal to database: {}", err);
+        }
+    }
+
+    /// Puts the tracks of the pending removal back where they were.
+    fn undo_removal(&mut self) {
+        let mut context = self.context.borrow_mut();
+
+        let Some(removal) = context.removal.take() else {
+            return;
+        };
+
+        debug!("Undid: {}", removal.description());
+
+        update_autoplayed_tracks(&mut context, |storage| {
+            storage.restore_tracks(removal.removed);
+        });
+    }
+
+    /// Returns if the row was clicked, so it can be selected.
+    fn table_body_row(
+        &mut self,
+        mut row: TableRow<'_, '_>,
+        track: &Track,
+        columns: &[TrackColumn],
+    ) -> bool {
         let row_index = row.index();
+        let mut clicked = false;
 
         let playing = {
             let context = self.context.borrow();
             context.playback.selected_track.clone()
         };
 
-        row.set_selected(playing.as_ref().is_some_and(
-            |SelectedTrackContext {
-                 index: _,
-                 track:
-                     Track {
-                         id: _,
-                         path: _,
-                         name: _,
-                         hash,
-                         duration_secs: _,
-                         valid: _,
-                         created_at: _,
-                         updated_at: _,
-                     },
-                 playing: _,
-             }| { *hash == track.hash },
-        ));
-
-        row.col(|ui| {
-            let label = ui
-                .label(row_index.to_string())
-                .on_hover_cursor(CursorIcon::Default);
-            if label.double_clicked() {
-                self.toggle_row_play(row_index, track);
-            }
+        row.set_selected(
+            self.selection.contains(&track.id)
+                || playing.as_ref().is_some_and(
+                    |SelectedTrackContext {
+                         index: _,
+                         track: Track { hash, .. },
+                         playing: _,
+                     }| { *hash == track.hash },
+                ),
+        );
+
+        for (column_index, &column) in columns.iter().enumerate() {
+            row.col(|ui| {
+                let text = Self::column_text(column, row_index, track);
+
+                // Tracks whose file went missing during validation are marked in the first column
+                let label = if !track.valid && column_index == 0 {
+                    let text =
+                        egui::RichText::new(format!("⚠ {text}")).color(ui.visuals().warn_fg_color);
+                    ui.add(egui::Label::new(text).truncate())
+                        .on_hover_text(INVALID_TRACK_HELP)
+                } else {
+                    ui.add(egui::Label::new(text).truncate())
+                };
+                let label = label.on_hover_cursor(CursorIcon::Default);
+                if label.double_clicked() {
+                    self.toggle_row_play(row_index, track);
+                } else if label.clicked() {
+                    clicked = true;
+                }
+            });
+        }
+
+        let response = row.response();
+
+        if response.double_clicked() {
+            self.toggle_row_play(row_index, track);
+        } else if response.clicked() {
+            clicked = true;
+        }
+
+        response.context_menu(|ui| {
+            self.ui_track_context_menu(ui, track);
         });
 
-        row.col(|ui| {
-            let label = ui.label(&track.name).on_hover_cursor(CursorIcon::Default);
-            if label.double_clicked() {
-                self.toggle_row_play(row_index, track);
+        clicked
+    }
+
+    fn ui_track_context_menu(&mut self, ui: &mut egui::Ui, track: &Track) {
+        if !track.valid {
+            self.ui_invalid_track_menu(ui, track);
+            ui.separator();
+        }
+
+        let tags: Vec<(Tag, bool)> = {
+            let context = self.context.borrow();
+            context
+                .storage
+                .tags()
+                .map(|(tag, track_ids)| (tag.clone(), track_ids.contains(&track.id)))
+                .collect()
+        };
+
+        if ui.button("Play next").clicked() {
+            self.context
+                .borrow_mut()
+                .playback
+                .queue
+                .play_next(track.clone());
+            ui.close_kind(UiKind::Menu);
+        }
+
+        if ui.button("Add to queue").clicked() {
+            self.context.borrow_mut().playback.queue.add(track.clone());
+            ui.close_kind(UiKind::Menu);
+        }
+
+        ui.separator();
+
+        ui.menu_button("Tags", |ui| {
+            if tags.is_empty() {
+                ui.label("No tags have been created");
             }
-        });
 
-        row.col(|ui| {
-            let track_duration = Duration::from_secs_f64(track.duration_secs);
-            let readable_track_duration = human_duration(track_duration, false);
+            for (tag, mut assigned) in tags {
+                if !ui.checkbox(&mut assigned, &tag.name).changed() {
+                    continue;
+                }
+
+                let command = if assigned {
+                    DatabaseCommand::InsertTagTracks(tag, vec![track.id])
+                } else {
+                    DatabaseCommand::DeleteTagTracks(tag, vec![track.id])
+                };
 
-            let label = ui
-                .label(readable_track_duration)
-                .on_hover_cursor(CursorIcon::Default);
+                if let Err(err) = self.channels.database_command_tx.send(command) {
+                    error!("Failed to send track tag command to database: {}", err);
+                }
 
-            if label.double_clicked() {
-                self.toggle_row_play(row_index, track);
+                ui.close_kind(UiKind::Menu);
             }
         });
 
-        let response = row.response();
+        ui.separator();
 
-        if response.double_clicked() {
-            self.toggle_row_play(row_index, track);
+        // Removing a selected track removes every selected track
+        let track_ids: Vec<Uuid> = if self.selection.contains(&track.id) {
+            self.selection.iter().copied().collect()
+        } else {
+            vec![track.id]
+        };
+        let tracks = match track_ids.len() {
+            1 => "track".to_string(),
+            count => format!("{count} tracks"),
+        };
+
+        // Smart playlists pick their own tracks, so nothing can be removed from them
+        let selected_playlist = self.context.borrow().ui.playlist.selected();
+        if let Some(playlist) = selected_playlist.filter(|playlist| !playlist.is_smart())
+            && ui
+                .button(format!("Remove {tracks} from playlist"))
+                .clicked()
+        {
+            self.remove_tracks(RemovalTarget::Playlist(playlist), track_ids.clone());
+            ui.close_kind(UiKind::Menu);
+        }
+
+        if ui
+            .button(format!("Remove {tracks} from library"))
+            .on_hover_text("Files are left on disk")
+            .clicked()
+        {
+            self.remove_tracks(RemovalTarget::Library, track_ids);
+            ui.close_kind(UiKind::Menu);
+        }
+    }
+
+    /// Ways of fixing a track that failed validation, besides removing it from the library
+    fn ui_invalid_track_menu(&mut self, ui: &mut egui::Ui, track: &Track) {
+        if ui
+            .button("Relocate...")
+            .on_hover_text("Pick where the track's file has moved to")
+            .clicked()
+        {
+            ui.close_kind(UiKind::Menu);
+
+            if let Some(path) = select_file_dialog()
+                && let Err(err) =
+                    self.channels
+                        .database_command_tx
+                        .send(DatabaseCommand::RelocateTracks(vec![TrackRelocation {
+                            track: track.clone(),
+                            path,
+                        }]))
+            {
+                error!("Failed to send relocate track command to database: {}", err);
+            }
+        }
+
+        if ui
+            .button("Ignore")
+            .on_hover_text("Mark as valid until the library is next validated")
+            .clicked()
+        {
+            // Ignoring a selected track ignores every selected invalid track
+            let tracks: Vec<Track> = if self.selection.contains(&track.id) {
+                let context = self.context.borrow();
+                context
+                    .storage
+                    .get_playlist_tracks(None)
+                    .into_iter()
+                    .flatten()
+                    .filter(|track| !track.valid && self.selection.contains(&track.id))
+                    .cloned()
+                    .collect()
+            } else {
+                vec![track.clone()]
+            };
+
+            if let Err(err) = self
+                .channels
+                .database_command_tx
+                .send(DatabaseCommand::IgnoreInvalidTracks(tracks))
+            {
+                error!("Failed to send ignore tracks command to database: {}", err);
+            }
+
+            ui.close_kind(UiKind::Menu);
         }
     }
 
     fn ui_table(&mut self, ui: &mut egui::Ui, height: f32) {
-        self.select_new_track();
+        let columns = self.config.borrow().ui.track_columns.clone();
 
         let mut table = TableBuilder::new(ui)
             .max_scroll_height(height)
-            .column(Column::auto().at_least(INDEX_COLUMN_WIDTH).resizable(true))
-            .column(Column::remainder())
-            .column(Column::auto().at_least(DURATION_COLUMN_WIDTH))
             .sense(egui::Sense::click());
+        for &column in &columns {
+            table = table.column(Self::table_column(column));
+        }
 
-        let (filtered_tracks, selected_track, align_scroll) = {
+        let (filtered_tracks, selected_track, align_scroll, sort) = {
             let context = self.context.borrow();
             let selected_playlist = context.ui.playlist.selected();
 
-            let filtered_tracks = context
-                .storage
-                .filtered_tracks(selected_playlist.as_ref())
-                .to_vec();
+            let filtered_tracks = context.storage.filtered_tracks(selected_playlist.as_ref());
+
+            // Narrow the tracks down further to the members of the selected tag (if any)
+            let filtered_tracks = if let Some(tag) = context.ui.tag.selected() {
+                let tag_track_ids = context.storage.tag_track_ids(&tag);
+
+                filtered_tracks
+                    .iter()
+                    .filter(|track| tag_track_ids.is_some_and(|ids| ids.contains(&track.id)))
+                    .cloned()
+                    .collect()
+            } else {
+                filtered_tracks.to_vec()
+            };
 
             let selected = context.playback.selected_track.clone();
             let align = self.config.borrow().ui.align_scroll;
-            (filtered_tracks, selected, align)
+            let sort = context.storage.track_sort(selected_playlist.as_ref());
+            (filtered_tracks, selected, align, sort)
         };
 
         // If the currently selected playlist in the UI matches the one that has a track playing
@@ -409,19 +993,40 @@ impl TrackTable {
             self.scroll_to_selected = false;
         }
 
+        // Tracks that aren't shown anymore, such as after searching, can't stay selected
+        if !self.selection.is_empty() {
+            let shown: HashSet<Uuid> = filtered_tracks.iter().map(|track| track.id).collect();
+            self.selection.retain(|track_id| shown.contains(track_id));
+        }
+
         let num_rows = filtered_tracks.len();
+        let mut new_sort = None;
+        let mut clicked_row = None;
 
         table
             .header(TABLE_HEADER_HEIGHT, |mut header| {
-                header.col(|ui| {
-                    ui.heading("Index");
-                });
-                header.col(|ui| {
-                    ui.heading("Track");
-                });
-                header.col(|ui| {
-                    ui.heading("Duration");
-                });
+                for &column in &columns {
+                    header.col(|ui| {
+                        // Clicking a column's header sorts by it, and clicking it again reverses the order
+                        let heading = match (column == sort.column, sort.order) {
+                            (true, SortOrder::Ascending) => format!("{column} ^"),
+                            (true, SortOrder::Descending) => format!("{column} v"),
+                            (false, _) => column.to_string(),
+                        };
+
+                        let response = ui.add(
+                            egui::Label::new(egui::RichText::new(heading).heading())
+                                .selectable(false)
+                                .sense(egui::Sense::click()),
+                        );
+
+                        if column.sortable()
+                            && response.on_hover_cursor(CursorIcon::PointingHand).clicked()
+                        {
+                            new_sort = Some(sort.toggled(column));
+                        }
+                    });
+                }
             })
             .body(|body| {
                 body.rows(TABLE_ROW_HEIGHT, num_rows, |row| {
@@ -430,9 +1035,92 @@ impl TrackTable {
                         return;
                     };
 
-                    self.table_body_row(row, track);
+                    if self.table_body_row(row, track, &columns) {
+                        clicked_row = Some(index);
+                    }
                 });
             });
+
+        if let Some(row_index) = clicked_row {
+            let modifiers = ui.input(|i| i.modifiers);
+            self.select_row(row_index, modifiers, &filtered_tracks);
+        }
+
+        if let Some(sort) = new_sort {
+            self.set_sort(sort);
+        }
+    }
+
+    /// Lets columns be shown, hidden and moved around from a menu next to the search box.
+    /// The chosen columns are saved to the config straight away, like the rest of the settings.
+    fn ui_columns(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+
+        ui.menu_button("Columns", |ui| {
+            let mut config = self.config.borrow_mut();
+            let columns = &mut config.ui.track_columns;
+
+            // Shown columns are listed first in the order they're shown in, followed by hidden ones
+            let listed: Vec<TrackColumn> = columns
+                .iter()
+                .copied()
+                .chain(
+                    TrackColumn::ALL
+                        .into_iter()
+                        .filter(|column| !columns.contains(column)),
+                )
+                .collect();
+            let last_position = columns.len().saturating_sub(1);
+
+            for column in listed {
+                let position = columns.iter().position(|shown| *shown == column);
+
+                ui.horizontal(|ui| {
+                    let mut shown = position.is_some();
+                    // The last shown column can't be hidden
+                    let can_toggle = !shown || columns.len() > 1;
+
+                    if ui
+                        .add_enabled(
+                            can_toggle,
+                            egui::Checkbox::new(&mut shown, column.to_string()),
+                        )
+                        .changed()
+                    {
+                        if shown {
+                            columns.push(column);
+                        } else {
+                            columns.retain(|shown| *shown != column);
+                        }
+                        changed = true;
+                    }
+
+                    let Some(position) = position else {
+                        return;
+                    };
+
+                    if ui
+                        .add_enabled(position > 0, egui::Button::new("Up").small())
+                        .clicked()
+                    {
+                        columns.swap(position, position - 1);
+                        changed = true;
+                    }
+
+                    if ui
+                        .add_enabled(position < last_position, egui::Button::new("Down").small())
+                        .clicked()
+                    {
+                        columns.swap(position, position + 1);
+                        changed = true;
+                    }
+                });
+            }
+        });
+
+        if changed && let Err(err) = save_config(&self.config.borrow()) {
+            error!("Failed to save track columns: {}", err);
+        }
     }
 
     // TODO
@@ -462,7 +1150,7 @@ impl TrackTable {
         let search_text_edit =
             egui::TextEdit::singleline(&mut self.search.text).hint_text("Search...");
 
-        let response = ui.add(search_text_edit);
+        let response = ui.add(search_text_edit).on_hover_text(SEARCH_HELP);
 
         if self.search.focus_requested {
             response.request_focus();
@@ -480,15 +1168,42 @@ impl TrackTable {
         {
             let start = Instant::now();
 
-            let search_text = self.search.text.clone();
-
-            let matcher = &self.search.matcher_fn;
-
-            let predicate =
-                |track: &Track| search_text.is_empty() || (matcher)(&search_text, &track.name);
-
-            if let Some(filtered) = storage_context.filter_with(&selected_playlist, predicate) {
-                self.search.yielded_results = filtered.len();
+            // Malformed queries keep the results of the last query that could be parsed
+            match Query::parse(&self.search.text) {
+                Ok(query) => {
+                    self.search.error = None;
+                    self.search.pending_full_text = None;
+
+                    // Free text is searched for in the database's index, with the rest of the query
+                    // matched against the results once they're sent back
+                    if let (Some(full_text), filters) = query.split_full_text() {
+                        let _ = self
+                            .channels
+                            .database_command_tx
+                            .send(DatabaseCommand::SearchTracks(full_text.clone()));
+
+                        self.search.pending_full_text = Some(PendingFullText {
+                            full_text,
+                            filters,
+                            start,
+                        });
+                    } else {
+                        let env = QueryEnv {
+                            matcher: &self.search.matcher_fn,
+                            track_tags: storage_context.tag_names_by_track(),
+                            now: Utc::now(),
+                        };
+
+                        let predicate = |track: &Track| query.matches(track, &env);
+
+                        if let Some(filtered) =
+                            storage_context.filter_with(&selected_playlist, predicate)
+                        {
+                            self.search.yielded_results = filtered.len();
+                        }
+                    }
+                }
+                Err(err) => self.search.error = Some(err),
             }
 
             let duration = start.elapsed();
@@ -497,7 +1212,11 @@ impl TrackTable {
 
         self.search.previous_text = Some(previous_text);
 
-        if !self.search.text.is_empty()
+        if let Some(err) = &self.search.error {
+            ui.colored_label(ui.visuals().error_fg_color, err.describe(&self.search.text));
+        } else if self.search.pending_full_text.is_some() {
+            ui.spinner();
+        } else if !self.search.text.is_empty()
             && let Some(search_duration) = self.search.duration
         {
             ui.label(format!(
@@ -507,17 +1226,91 @@ impl TrackTable {
         }
     }
 
+    fn ui_tag_filter(&mut self, ui: &mut egui::Ui) {
+        let Some(tag) = self.context.borrow().ui.tag.selected() else {
+            return;
+        };
+
+        ui.separator();
+        ui.label(format!("Tag: {}", tag.name));
+
+        if ui.small_button("Clear").clicked() {
+            self.context.borrow_mut().ui.tag.set_selected(None);
+        }
+    }
+
+    fn ui_pending_removal(&mut self, ui: &mut egui::Ui) {
+        let Some((description, remaining)) = self
+            .context
+            .borrow()
+            .removal
+            .pending()
+            .map(|removal| (removal.description(), removal.remaining(Instant::now())))
+        else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{description} ({}s left to undo)",
+                remaining.as_secs() + 1
+            ));
+
+            if ui.button("Undo").clicked() {
+                self.undo_removal();
+            }
+        });
+
+        ui.separator();
+    }
+
     pub fn ui(&mut self, ui: &mut egui::Ui) {
         let height = ui.available_height();
 
         ui.vertical(|ui: &mut egui::Ui| {
             ui.horizontal(|ui| {
                 self.ui_search(ui);
+                self.ui_tag_filter(ui);
+                ui.separator();
+                self.ui_columns(ui);
             });
 
             ui.separator();
 
+            self.ui_pending_removal(ui);
             self.ui_table(ui, height);
         });
     }
 }
+
+/// Changes the loaded tracks, carrying autoplay over to what's left of the tracks being autoplayed.
+fn update_autoplayed_tracks<T>(
+    context: &mut Context,
+    change: impl FnOnce(&mut StorageContext) -> T,
+) -> T {
+    let autoplay_playlist = context.playback.selected_playlist.playlist();
+
+    let previous_tracks = match &autoplay_playlist {
+        Some(playlist_state) => playlist_state.tracks(),
+        None => context
+            .storage
+            .get_playlist_tracks(None)
+            .cloned()
+            .unwrap_or_default(),
+    };
+
+    let changed = change(&mut context.storage);
+
+    let autoplay_playlist = autoplay_playlist.map(|playlist_state| playlist_state.playlist());
+    let current_tracks = context
+        .storage
+        .get_playlist_tracks(autoplay_playlist.as_ref())
+        .cloned()
+        .unwrap_or_default();
+
+    context
+        .playback
+        .reorder_tracks(&previous_tracks, &current_tracks);
+
+    changed
+}
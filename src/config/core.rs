@@ -3,7 +3,8 @@ use std::{cell::RefCell, rc::Rc};
 use serde::{Deserialize, Serialize};
 
 use crate::config::{
-    general::GeneralConfig, playback::PlaybackConfig, search::SearchConfig, ui::UIConfig,
+    general::GeneralConfig, library::LibraryConfig, playback::PlaybackConfig, search::SearchConfig,
+    ui::UIConfig,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
@@ -13,6 +14,7 @@ pub struct CoreConfig {
     pub ui: UIConfig,
     pub playback: PlaybackConfig,
     pub search: SearchConfig,
+    pub library: LibraryConfig,
 }
 
 pub type SharedConfig = Rc<RefCell<CoreConfig>>;
@@ -1,5 +1,32 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
+/// What to do on startup with the track that was playing when the app was last closed.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionRestore {
+    /// Offer to resume the last track once it's been loaded
+    #[default]
+    Ask,
+    /// Load the last track straight away, paused where it was left off
+    Paused,
+    /// Start with nothing selected
+    Off,
+}
+
+impl fmt::Display for SessionRestore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            SessionRestore::Ask => "Ask",
+            SessionRestore::Paused => "Resume paused",
+            SessionRestore::Off => "Off",
+        };
+
+        write!(f, "{label}")
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(default)]
 pub struct GeneralConfig {
@@ -7,4 +34,10 @@ pub struct GeneralConfig {
     pub debug_wireframe: bool,
     /// Enables eframe's VSync.
     pub vsync: bool,
+    /// Whether the last session is restored on startup.
+    pub restore_session: SessionRestore,
+    /// Whether every track's file is validated on startup.
+    pub validate_on_startup: bool,
+    /// How many hours to wait between validating every track's file while running, or 0 to never.
+    pub validation_interval_hours: u32,
 }
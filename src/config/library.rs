@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_WATCH_DEBOUNCE_SECS: f32 = 2.0;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct LibraryConfig {
+    /// Folders whose audio files are kept in the library as they're added, removed or renamed
+    pub watched_folders: Vec<PathBuf>,
+    /// How many seconds a watched folder has to settle for before its changes are synced,
+    /// so copying many files at once is synced together
+    pub watch_debounce_secs: f32,
+}
+
+impl Default for LibraryConfig {
+    fn default() -> Self {
+        Self {
+            watched_folders: Vec::new(),
+            watch_debounce_secs: DEFAULT_WATCH_DEBOUNCE_SECS,
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod core;
 pub mod general;
+pub mod library;
 pub mod playback;
 pub mod search;
 pub mod ui;
@@ -1,23 +1,34 @@
 use serde::{Deserialize, Serialize};
 
-use crate::context::AutoplayType;
+use crate::context::{AutoplayType, RepeatMode};
 
 const DEFAULT_PLAYER_VOLUME: f32 = 0.5;
+/// Longest crossfade that can be set, which has to fit within how early the next track is enqueued
+pub const MAX_CROSSFADE_SECS: f32 = 8.0;
+const DEFAULT_SEEK_STEP_SECS: f32 = 10.0;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(default)]
 pub struct PlaybackConfig {
     pub autoplay: AutoplayType,
+    pub repeat: RepeatMode,
     pub volume: f32,
     pub add_to_seen_on_skip: bool,
+    /// How many seconds to fade between tracks during autoplay, or 0 for no crossfade
+    pub crossfade_secs: f32,
+    /// How many seconds to skip forward or back with the seek buttons and arrow keys
+    pub seek_step_secs: f32,
 }
 
 impl Default for PlaybackConfig {
     fn default() -> Self {
         Self {
             autoplay: AutoplayType::default(),
+            repeat: RepeatMode::default(),
             volume: DEFAULT_PLAYER_VOLUME,
             add_to_seen_on_skip: true,
+            crossfade_secs: 0.0,
+            seek_step_secs: DEFAULT_SEEK_STEP_SECS,
         }
     }
 }
@@ -1,13 +1,15 @@
 use egui::Align;
 use serde::{Deserialize, Serialize};
 
-use crate::themes::AppTheme;
+use crate::{database::models::sort::TrackColumn, themes::AppTheme};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(default)]
 pub struct UIConfig {
     pub theme: AppTheme,
     pub align_scroll: Option<Align>,
+    /// Columns shown in the track table, in the order they're shown in
+    pub track_columns: Vec<TrackColumn>,
 }
 
 impl Default for UIConfig {
@@ -15,6 +17,7 @@ impl Default for UIConfig {
         Self {
             theme: AppTheme::default(),
             align_scroll: Some(Align::Center),
+            track_columns: vec![TrackColumn::Index, TrackColumn::Name, TrackColumn::Duration],
         }
     }
 }
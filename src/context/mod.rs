@@ -9,28 +9,41 @@ pub use ui::UIContext;
 pub mod playlist;
 pub use playlist::UIPlaylistContext;
 
-pub mod processing;
-pub use processing::ProcessingContext;
+pub mod play_history;
+pub use play_history::PlayHistoryContext;
 
 pub mod performance;
 pub use performance::PerformanceMetricsContext;
 
 pub mod storage;
-pub use storage::StorageContext;
+pub use storage::{RemovedTracks, StorageContext};
+
+pub mod removal;
+pub use removal::{PendingRemoval, RemovalContext, RemovalTarget};
+
+pub mod tag;
+pub use tag::UITagContext;
+
+pub mod tasks;
+pub use tasks::TaskContext;
 
 #[derive(Debug, Clone, Default)]
 pub struct Context {
     /// All the tracks and playlists that have been loaded into memory from the database.
     /// It is also possible to modify this loaded data in order to be up to date with database entries.
     pub storage: StorageContext,
+    /// Tracks that have been removed from storage, waiting for their undo window to run out.
+    pub removal: RemovalContext,
     /// Selected items that are used for currently playing tracks within a playlist,
     /// configured shuffle direction and type from autoplay, and the state of progression
     /// through the playing track along with volume level.
     pub playback: PlaybackContext,
     /// What's currently being shown from the perspective of the UI.
     pub ui: UIContext,
-    /// Background jobs that are actively processing.
-    pub processing: ProcessingContext,
+    /// Tracks that have recently been played, and how long they were listened to.
+    pub play_history: PlayHistoryContext,
+    /// Long-running background jobs, such as imports, along with their progress and outcome.
+    pub tasks: TaskContext,
     /// Performance-related metrics data (latency, FPS, etc.).
     pub performance_metrics: PerformanceMetricsContext,
 }
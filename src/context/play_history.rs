@@ -0,0 +1,90 @@
+use uuid::Uuid;
+
+use crate::database::models::{play_history::PlayHistory, tracks::Track};
+
+/// How many of the most recently played tracks are kept in memory.
+pub const RECENT_PLAY_HISTORY_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, Default)]
+pub struct PlayHistoryContext {
+    /// Recently played tracks, newest first
+    recent: Vec<(PlayHistory, Track)>,
+    /// The entry of the track that's currently playing, which is finished once the track changes
+    current: Option<Uuid>,
+}
+
+impl PlayHistoryContext {
+    pub fn recent(&self) -> &[(PlayHistory, Track)] {
+        &self.recent
+    }
+
+    /// Replaces the recently played tracks with the ones loaded from the database.
+    /// Anything recorded during this session before the query returned is kept on top.
+    pub fn set_recent(&mut self, recent: Vec<(PlayHistory, Track)>) {
+        let mut session_entries: Vec<_> = self
+            .recent
+            .drain(..)
+            .filter(|(session_entry, _)| {
+                !recent.iter().any(|(entry, _)| entry.id == session_entry.id)
+            })
+            .collect();
+
+        session_entries.extend(recent);
+        self.recent = session_entries;
+        self.recent.truncate(RECENT_PLAY_HISTORY_LIMIT);
+    }
+
+    /// Whether the entry of the currently playing track has been started.
+    pub fn is_recording(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Starts a new entry for a track that has just started playing, returning it to be stored.
+    pub fn start(&mut self, track: &Track, playlist_id: Option<Uuid>) -> PlayHistory {
+        let entry = PlayHistory::new(track.id, playlist_id);
+
+        self.current = Some(entry.id);
+        self.recent.insert(0, (entry.clone(), track.clone()));
+        self.recent.truncate(RECENT_PLAY_HISTORY_LIMIT);
+
+        entry
+    }
+
+    /// Finishes the entry of the currently playing track, returning the updated entry to be stored.
+    pub fn finish_current(&mut self, listened_secs: f64, skipped: bool) -> Option<PlayHistory> {
+        let current = self.current.take()?;
+
+        let (entry, _) = self
+            .recent
+            .iter_mut()
+            .find(|(entry, _)| entry.id == current)?;
+        entry.listened_secs = listened_secs;
+        entry.skipped = skipped;
+
+        Some(entry.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finish_current_updates_started_entry() {
+        let mut play_history = PlayHistoryContext::default();
+        let first = Track::default();
+        let second = Track::default();
+
+        assert!(play_history.finish_current(1.0, true).is_none());
+
+        play_history.start(&first, None);
+        let finished = play_history.finish_current(12.5, true).unwrap();
+        play_history.start(&second, None);
+
+        assert_eq!(finished.track_id, first.id);
+        assert_eq!(play_history.recent().len(), 2);
+        assert_eq!(play_history.recent()[0].1.id, second.id);
+        assert_eq!(play_history.recent()[1].0.listened_secs, 12.5);
+        assert!(play_history.recent()[1].0.skipped);
+    }
+}
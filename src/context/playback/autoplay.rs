@@ -5,6 +5,8 @@ use serde::{
     de::{Error as SerdeError, Unexpected},
 };
 
+use crate::context::playback::SelectedTrackContext;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AutoplayType {
     /// Play the next (or previous) track in the track list
@@ -36,6 +38,42 @@ pub enum ShuffleType {
     TrueRandom,
 }
 
+/// What happens once autoplay reaches the end of the tracks it's playing through.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepeatMode {
+    /// Stop after the last track, or once every track has been shuffled through
+    Off,
+    /// Keep playing the same track
+    One,
+    /// Loop back around to the other side of the track list
+    #[default]
+    All,
+}
+
+impl RepeatMode {
+    /// The mode after this one when cycling through them with the repeat button.
+    pub fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+}
+
+impl fmt::Display for RepeatMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            RepeatMode::Off => "Off",
+            RepeatMode::One => "One",
+            RepeatMode::All => "All",
+        };
+
+        write!(f, "{label}")
+    }
+}
+
 impl Serialize for AutoplayType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -87,11 +125,32 @@ impl fmt::Display for AutoplayType {
     }
 }
 
+/// Where a track handed to the player ahead of time was picked from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnqueuedFrom {
+    History,
+    Queue,
+    Autoplay,
+}
+
+/// A track handed to the player before the current one has finished, so that there's no gap between them.
+/// It only becomes the selected track once the player reports that it has started.
+#[derive(Debug, Clone)]
+pub struct EnqueuedTrack {
+    pub track: SelectedTrackContext,
+    pub from: EnqueuedFrom,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct AutoplayContext {
     select_new_track: bool,
+    /// If the next track should be picked and handed to the player ahead of time
+    enqueue_next_track: bool,
+    enqueued: Option<EnqueuedTrack>,
     autoplay: AutoplayType,
     controlled_autoplay: Option<AutoplayType>,
+    /// Which way the session history was requested to be walked by playback controls
+    history_direction: Option<PlayDirection>,
 }
 
 impl AutoplayContext {
@@ -115,6 +174,26 @@ impl AutoplayContext {
         self.select_new_track = select_new_track;
     }
 
+    pub fn enqueue_next_track(&self) -> bool {
+        self.enqueue_next_track
+    }
+
+    pub fn set_enqueue_next_track(&mut self, enqueue_next_track: bool) {
+        self.enqueue_next_track = enqueue_next_track;
+    }
+
+    pub fn enqueued(&self) -> Option<&EnqueuedTrack> {
+        self.enqueued.as_ref()
+    }
+
+    pub fn set_enqueued(&mut self, enqueued: Option<EnqueuedTrack>) {
+        self.enqueued = enqueued;
+    }
+
+    pub fn take_enqueued(&mut self) -> Option<EnqueuedTrack> {
+        self.enqueued.take()
+    }
+
     pub fn set_incoming_track(&mut self, select_new_track: bool, autoplay: Option<AutoplayType>) {
         self.select_new_track = select_new_track;
         self.controlled_autoplay = autoplay;
@@ -128,4 +207,36 @@ impl AutoplayContext {
     pub fn consume_controlled(&mut self) -> Option<AutoplayType> {
         self.controlled_autoplay.take()
     }
+
+    /// Go back to the previously played track. If nothing was played before the current track,
+    /// the previous track in the list is picked instead, or a random one when shuffling.
+    pub fn request_previous_track(&mut self) {
+        let controlled_autoplay = if self.is_shuffle() {
+            None
+        } else {
+            Some(AutoplayType::Iterative(PlayDirection::Backward))
+        };
+
+        self.set_incoming_track(true, controlled_autoplay);
+        self.history_direction = Some(PlayDirection::Backward);
+    }
+
+    /// Go forward to the track that was played after the current one. If playback hasn't gone back
+    /// through the session history, the next track is picked as normal.
+    pub fn request_next_track(&mut self) {
+        let controlled_autoplay = if self.is_shuffle() {
+            None
+        } else {
+            Some(AutoplayType::Iterative(PlayDirection::Forward))
+        };
+
+        self.set_incoming_track(true, controlled_autoplay);
+        self.history_direction = Some(PlayDirection::Forward);
+    }
+
+    /// Which way a playback control asked to walk the session history when selecting a new track,
+    /// or nothing if a track is being autoplayed, which always walks forward.
+    pub fn consume_history_direction(&mut self) -> Option<PlayDirection> {
+        self.history_direction.take()
+    }
 }
@@ -1,5 +1,7 @@
 use std::time::{Duration, Instant};
 
+use crate::context::PlayDirection;
+
 #[derive(Debug, Clone)]
 pub struct ControlContext {
     pub volume: f32,
@@ -43,6 +45,27 @@ impl ControlContext {
         }
     }
 
+    /// Moves the progress by a step in either direction, staying within the track,
+    /// until the player reports back where it actually ended up.
+    pub fn seek_relative(
+        &mut self,
+        step: Duration,
+        direction: &PlayDirection,
+        track_duration: Duration,
+    ) {
+        let Some(progress) = self.current_progress() else {
+            return;
+        };
+
+        let progress = match direction {
+            PlayDirection::Forward => (progress + step).min(track_duration),
+            PlayDirection::Backward => progress.saturating_sub(step),
+        };
+
+        self.progress_base = Some(progress);
+        self.progress_timestamp = self.progress_timestamp.map(|_| Instant::now());
+    }
+
     pub fn set_progress(
         &mut self,
         progress_base: Option<Duration>,
@@ -52,3 +75,30 @@ impl ControlContext {
         self.progress_timestamp = progress_timestamp;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seek_relative_clamps_to_track() {
+        let mut control = ControlContext::default();
+        let track_duration = Duration::from_secs(60);
+        control.set_progress(Some(Duration::from_secs(55)), None);
+
+        control.seek_relative(
+            Duration::from_secs(10),
+            &PlayDirection::Forward,
+            track_duration,
+        );
+        assert_eq!(control.current_progress(), Some(track_duration));
+
+        control.set_progress(Some(Duration::from_secs(5)), None);
+        control.seek_relative(
+            Duration::from_secs(10),
+            &PlayDirection::Backward,
+            track_duration,
+        );
+        assert_eq!(control.current_progress(), Some(Duration::ZERO));
+    }
+}
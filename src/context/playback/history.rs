@@ -0,0 +1,95 @@
+use crate::database::models::tracks::Track;
+
+/// How many tracks are remembered for going back and forth through during a session.
+const SESSION_HISTORY_LIMIT: usize = 1000;
+
+/// Every track played during this session in order, along with where playback currently is within them.
+///
+/// Going back and then playing a track that wasn't reached by going forward discards everything
+/// after the current position, in the same way as a web browser's history.
+#[derive(Debug, Clone, Default)]
+pub struct SessionHistoryContext {
+    tracks: Vec<Track>,
+    /// Index of the currently playing track within [`Self::tracks`]
+    position: usize,
+}
+
+impl SessionHistoryContext {
+    /// Adds a newly selected track after the current position.
+    pub fn push(&mut self, track: Track) {
+        if !self.tracks.is_empty() {
+            self.tracks.truncate(self.position + 1);
+        }
+
+        self.tracks.push(track);
+
+        if self.tracks.len() > SESSION_HISTORY_LIMIT {
+            self.tracks.remove(0);
+        }
+
+        self.position = self.tracks.len() - 1;
+    }
+
+    /// Steps back to the previously played track, if there is one.
+    pub fn back(&mut self) -> Option<Track> {
+        let position = self.position.checked_sub(1)?;
+        let track = self.tracks.get(position)?.clone();
+
+        self.position = position;
+
+        Some(track)
+    }
+
+    /// The track that [`Self::forward`] would step to, without moving.
+    pub fn peek_forward(&self) -> Option<Track> {
+        self.tracks.get(self.position + 1).cloned()
+    }
+
+    /// Steps forward to a track that was played after the current one, if playback went back before.
+    pub fn forward(&mut self) -> Option<Track> {
+        let position = self.position + 1;
+        let track = self.tracks.get(position)?.clone();
+
+        self.position = position;
+
+        Some(track)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::track;
+
+    #[test]
+    fn test_back_and_forward_walk_history() {
+        let mut history = SessionHistoryContext::default();
+        assert!(history.back().is_none());
+
+        history.push(track("a"));
+        history.push(track("b"));
+        history.push(track("c"));
+
+        assert_eq!(history.back().unwrap().name, "b");
+        assert_eq!(history.back().unwrap().name, "a");
+        assert!(history.back().is_none());
+
+        assert_eq!(history.peek_forward().unwrap().name, "b");
+        assert_eq!(history.forward().unwrap().name, "b");
+        assert_eq!(history.forward().unwrap().name, "c");
+        assert!(history.forward().is_none());
+    }
+
+    #[test]
+    fn test_push_discards_forward_history() {
+        let mut history = SessionHistoryContext::default();
+
+        history.push(track("a"));
+        history.push(track("b"));
+        history.back();
+        history.push(track("c"));
+
+        assert!(history.forward().is_none());
+        assert_eq!(history.back().unwrap().name, "a");
+    }
+}
@@ -1,16 +1,35 @@
 pub mod autoplay;
-pub use autoplay::{AutoplayContext, AutoplayType, PlayDirection, ShuffleType};
+pub use autoplay::{
+    AutoplayContext, AutoplayType, EnqueuedFrom, EnqueuedTrack, PlayDirection, RepeatMode,
+    ShuffleType,
+};
 
 pub mod control;
 pub use control::ControlContext;
 
+pub mod history;
+pub use history::SessionHistoryContext;
+
+pub mod queue;
+pub use queue::QueueContext;
+
 pub mod selected;
-use std::time::{Duration, Instant};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 pub use selected::{PlaylistState, SelectedPlaylistContext, SelectedTrackContext};
 use tracing::{debug, warn};
+use uuid::Uuid;
 
-use crate::playback::state::PlayerEvent;
+use crate::{
+    database::models::{playlists::playlist::Playlist, tracks::Track},
+    playback::state::PlayerEvent,
+};
+
+/// How long before the end of the current track the next one is handed to the player.
+const ENQUEUE_AHEAD_SECS: f64 = 10.0;
 
 #[derive(Debug, Clone, Default)]
 pub struct PlaybackContext {
@@ -21,13 +40,111 @@ pub struct PlaybackContext {
     pub selected_playlist: SelectedPlaylistContext,
     pub control: ControlContext,
     pub autoplay: AutoplayContext,
+    /// Tracks played during this session, for going back and forth through with playback controls
+    pub session_history: SessionHistoryContext,
+    /// Tracks queued up by the user, which are played before autoplay picks anything
+    pub queue: QueueContext,
 }
 
 impl PlaybackContext {
+    /// Selects a new track, adding it to the session history.
     pub fn select_track(&mut self, track: Option<SelectedTrackContext>) {
+        if let Some(track_context) = track.as_ref() {
+            self.session_history.push(track_context.track.clone());
+        }
+
         self.selected_track = track;
     }
 
+    /// Selects a track that was reached by walking through the session history.
+    pub fn select_history_track(&mut self, track: SelectedTrackContext) {
+        self.selected_track = Some(track);
+    }
+
+    /// Selects a track that the player has started by itself, after it was enqueued ahead of time.
+    fn select_enqueued_track(&mut self, enqueued: EnqueuedTrack) {
+        match enqueued.from {
+            EnqueuedFrom::History => {
+                self.session_history.forward();
+                self.select_history_track(enqueued.track);
+            }
+            EnqueuedFrom::Queue => {
+                if self
+                    .queue
+                    .tracks()
+                    .first()
+                    .is_some_and(|track| track.id == enqueued.track.track.id)
+                {
+                    self.queue.pop_front();
+                }
+
+                self.select_track(Some(enqueued.track));
+            }
+            EnqueuedFrom::Autoplay => {
+                self.select_track(Some(enqueued.track));
+            }
+        }
+    }
+
+    /// Follows the tracks being autoplayed after they've been sorted differently (or had tracks removed or
+    /// put back), so that autoplay goes through them in the order they're displayed in. The played tracks and the selected track's index
+    /// are moved along to the tracks' new positions.
+    pub fn reorder_tracks(&mut self, previous: &[Track], sorted: &[Track]) {
+        let positions: HashMap<Uuid, usize> = sorted
+            .iter()
+            .enumerate()
+            .map(|(index, track)| (track.id, index))
+            .collect();
+
+        let played_tracks = self
+            .selected_playlist
+            .played_tracks()
+            .into_iter()
+            .filter_map(|index| positions.get(&previous.get(index)?.id).copied())
+            .collect();
+        self.selected_playlist.set_played_tracks(played_tracks);
+
+        if let Some(playlist_state) = self.selected_playlist.playlist() {
+            self.selected_playlist.set_playlist(Some(PlaylistState::new(
+                playlist_state.playlist(),
+                sorted.to_vec(),
+            )));
+        }
+
+        if let Some(selected_track) = self.selected_track.as_mut()
+            && let Some(&index) = positions.get(&selected_track.track.id)
+        {
+            selected_track.index = index;
+        }
+    }
+
+    /// Swaps in a renamed playlist if it's the one being autoplayed.
+    pub fn rename_playlist(&mut self, playlist: &Playlist) {
+        if let Some(playlist_state) = self.selected_playlist.playlist()
+            && playlist_state.playlist().id == playlist.id
+        {
+            self.selected_playlist.set_playlist(Some(PlaylistState::new(
+                playlist.clone(),
+                playlist_state.tracks(),
+            )));
+        }
+    }
+
+    /// Goes back to autoplaying all tracks if the playlist being autoplayed has been deleted,
+    /// moving the played tracks and the selected track's index over to their positions within all tracks.
+    pub fn remove_playlist(&mut self, playlist_id: Uuid, all_tracks: &[Track]) {
+        let Some(playlist_state) = self
+            .selected_playlist
+            .playlist()
+            .filter(|playlist_state| playlist_state.playlist().id == playlist_id)
+        else {
+            return;
+        };
+
+        self.selected_playlist.set_playlist(None);
+        self.reorder_tracks(&playlist_state.tracks(), all_tracks);
+    }
+
     pub fn select_playlist(&mut self, playlist: SelectedPlaylistContext) {
         self.selected_playlist = playlist;
     }
@@ -36,7 +153,15 @@ impl PlaybackContext {
         debug!("Handling playback event: {:?}", player_event);
 
         match player_event {
-            PlayerEvent::TrackChanged(track) => {
+            PlayerEvent::TrackChanged(track) | PlayerEvent::TrackLoaded(track) => {
+                // Creating a track drops whatever was enqueued, so it only matters if the player reached it
+                if let Some(enqueued) = self.autoplay.take_enqueued()
+                    && enqueued.track.track.id == track.id
+                {
+                    self.select_enqueued_track(enqueued);
+                }
+                self.autoplay.set_enqueue_next_track(false);
+
                 if let Some(track_state) = self.selected_track.as_mut() {
                     track_state.track = track;
                     track_state.playing = true;
@@ -77,18 +202,37 @@ impl PlaybackContext {
                     track.playing = playing;
                 }
             }
-            PlayerEvent::TrackProgress(duration) => {
-                // If duration is not synced properly, do it here
+            PlayerEvent::TrackFinished(track) => {
+                // Ignore tracks that finished after another one has already been selected
                 if self
-                    .control
-                    .progress_base
-                    .is_some_and(|progress_base| progress_base < duration)
+                    .selected_track
+                    .as_ref()
+                    .is_some_and(|track_state| track_state.track.id == track.id)
                 {
-                    warn!(
-                        "Track progress desync detected, setting progress base to received player position"
-                    );
-                    self.control.progress_base = Some(duration);
-                    self.control.progress_timestamp = Some(Instant::now());
+                    self.control.changing_track = true;
+                    self.autoplay.set_select_new_track(true);
+                }
+            }
+            PlayerEvent::TrackProgress(position) => {
+                // The player's position is authoritative, so the estimated progress is re-anchored to it
+                let playing = self
+                    .selected_track
+                    .as_ref()
+                    .is_some_and(|track| track.playing);
+
+                self.control.progress_base = Some(position);
+                self.control.progress_timestamp = playing.then(Instant::now);
+
+                let ending = self.selected_track.as_ref().is_some_and(|track| {
+                    track.track.duration_secs - position.as_secs_f64() <= ENQUEUE_AHEAD_SECS
+                });
+
+                if playing
+                    && ending
+                    && !self.control.changing_track
+                    && self.autoplay.enqueued().is_none()
+                {
+                    self.autoplay.set_enqueue_next_track(true);
                 }
             }
             PlayerEvent::CurrentVolume(volume) => {
@@ -103,3 +247,94 @@ impl PlaybackContext {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enqueued_track_selected_once_started() {
+        let mut playback = PlaybackContext::default();
+        let current = Track::default();
+        let queued = Track::default();
+
+        playback.select_track(Some(SelectedTrackContext::new(current.clone(), 0, true)));
+        playback.queue.add(queued.clone());
+        playback.autoplay.set_enqueued(Some(EnqueuedTrack {
+            track: SelectedTrackContext::new(queued.clone(), 1, true),
+            from: EnqueuedFrom::Queue,
+        }));
+
+        playback.handle_player_event(PlayerEvent::TrackChanged(queued.clone()));
+
+        assert!(playback.autoplay.enqueued().is_none());
+        assert!(playback.queue.is_empty());
+        assert_eq!(
+            playback.selected_track.as_ref().map(|track| track.index),
+            Some(1)
+        );
+        assert_eq!(playback.session_history.back().unwrap().id, current.id);
+    }
+
+    #[test]
+    fn test_removed_playlist_falls_back_to_all_tracks() {
+        let mut playback = PlaybackContext::default();
+        let all_tracks: Vec<Track> = (0..4).map(|_| Track::default()).collect();
+        let playlist = Playlist::default();
+        let playlist_tracks = vec![all_tracks[3].clone(), all_tracks[1].clone()];
+
+        playback
+            .selected_playlist
+            .set_playlist(Some(PlaylistState::new(playlist.clone(), playlist_tracks)));
+        playback.selected_playlist.add_played_track(0);
+        playback.select_track(Some(SelectedTrackContext::new(
+            all_tracks[1].clone(),
+            1,
+            true,
+        )));
+
+        playback.remove_playlist(Uuid::new_v4(), &all_tracks);
+        assert!(playback.selected_playlist.playlist().is_some());
+
+        playback.remove_playlist(playlist.id, &all_tracks);
+        assert!(playback.selected_playlist.playlist().is_none());
+        assert_eq!(
+            playback
+                .selected_playlist
+                .played_tracks()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [3]
+        );
+        assert_eq!(
+            playback.selected_track.as_ref().map(|track| track.index),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_reorder_tracks_follows_sort() {
+        let mut playback = PlaybackContext::default();
+        let tracks: Vec<Track> = (0..3).map(|_| Track::default()).collect();
+
+        playback.select_track(Some(SelectedTrackContext::new(tracks[0].clone(), 0, true)));
+        playback.selected_playlist.add_played_track(0);
+        playback.selected_playlist.add_played_track(1);
+
+        let sorted = vec![tracks[2].clone(), tracks[1].clone(), tracks[0].clone()];
+        playback.reorder_tracks(&tracks, &sorted);
+
+        assert_eq!(
+            playback.selected_track.as_ref().map(|track| track.index),
+            Some(2)
+        );
+        assert_eq!(
+            playback
+                .selected_playlist
+                .played_tracks()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+    }
+}
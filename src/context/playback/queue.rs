@@ -0,0 +1,127 @@
+use uuid::Uuid;
+
+use crate::database::models::tracks::Track;
+
+/// Tracks explicitly queued up to play next, taking priority over autoplay.
+#[derive(Debug, Clone, Default)]
+pub struct QueueContext {
+    tracks: Vec<Track>,
+    /// If the queue has been modified since it was last stored
+    changed: bool,
+}
+
+impl QueueContext {
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+
+    /// Replaces the queue with tracks loaded from storage, without marking it as changed.
+    pub fn set_tracks(&mut self, tracks: Vec<Track>) {
+        self.tracks = tracks;
+    }
+
+    /// Queues a track to play directly after the current one.
+    pub fn play_next(&mut self, track: Track) {
+        self.tracks.insert(0, track);
+        self.changed = true;
+    }
+
+    /// Queues a track to play after everything else in the queue.
+    pub fn add(&mut self, track: Track) {
+        self.tracks.push(track);
+        self.changed = true;
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Track> {
+        if index >= self.tracks.len() {
+            return None;
+        }
+
+        self.changed = true;
+
+        Some(self.tracks.remove(index))
+    }
+
+    /// Moves a queued track to another position, shifting the tracks in between.
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        if from >= self.tracks.len() || to >= self.tracks.len() || from == to {
+            return;
+        }
+
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        self.changed = true;
+    }
+
+    /// Takes tracks out of the queue by their IDs, such as after they've been removed from the library.
+    pub fn remove_tracks(&mut self, track_ids: &[Uuid]) {
+        let queued = self.tracks.len();
+        self.tracks.retain(|track| !track_ids.contains(&track.id));
+        self.changed |= self.tracks.len() != queued;
+    }
+
+    pub fn clear(&mut self) {
+        if !self.tracks.is_empty() {
+            self.tracks.clear();
+            self.changed = true;
+        }
+    }
+
+    /// Takes the next track to play off the front of the queue.
+    pub fn pop_front(&mut self) -> Option<Track> {
+        self.remove(0)
+    }
+
+    /// Returns if the queue has been modified since this was last called, resetting the flag.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::track;
+
+    fn names(queue: &QueueContext) -> Vec<&str> {
+        queue
+            .tracks()
+            .iter()
+            .map(|track| track.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_play_next_and_add_order() {
+        let mut queue = QueueContext::default();
+
+        queue.add(track("a"));
+        queue.add(track("b"));
+        queue.play_next(track("c"));
+
+        assert_eq!(names(&queue), ["c", "a", "b"]);
+        assert!(queue.take_changed());
+        assert!(!queue.take_changed());
+
+        assert_eq!(queue.pop_front().unwrap().name, "c");
+        assert_eq!(names(&queue), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_move_track() {
+        let mut queue = QueueContext::default();
+        queue.set_tracks(vec![track("a"), track("b"), track("c")]);
+        assert!(!queue.take_changed());
+
+        queue.move_track(2, 0);
+        assert_eq!(names(&queue), ["c", "a", "b"]);
+
+        queue.move_track(0, 5);
+        assert_eq!(names(&queue), ["c", "a", "b"]);
+        assert!(queue.take_changed());
+    }
+}
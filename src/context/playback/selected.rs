@@ -59,6 +59,10 @@ impl SelectedPlaylistContext {
         self.played_tracks.clone()
     }
 
+    pub fn set_played_tracks(&mut self, played_tracks: BTreeSet<usize>) {
+        self.played_tracks = played_tracks;
+    }
+
     pub fn add_played_track(&mut self, index: usize) -> bool {
         self.played_tracks.insert(index)
     }
@@ -1,3 +1,5 @@
+use uuid::Uuid;
+
 use crate::database::models::playlists::playlist::Playlist;
 
 #[derive(Debug, Clone, Default)]
@@ -7,6 +9,8 @@ pub struct UIPlaylistContext {
     /// has selected in the UI, and not what's being used for autoplay
     selected: Option<Playlist>,
     autoplay: Option<Playlist>,
+    /// Why the last change to a playlist failed, shown until dismissed
+    error: Option<String>,
 }
 
 impl UIPlaylistContext {
@@ -25,4 +29,36 @@ impl UIPlaylistContext {
     pub fn set_autoplay(&mut self, playlist: Option<Playlist>) {
         self.autoplay = playlist;
     }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+    }
+
+    /// Swaps in a renamed playlist wherever it's selected.
+    pub fn rename(&mut self, playlist: &Playlist) {
+        for selected in [&mut self.selected, &mut self.autoplay] {
+            if selected
+                .as_ref()
+                .is_some_and(|selected| selected.id == playlist.id)
+            {
+                *selected = Some(playlist.clone());
+            }
+        }
+    }
+
+    /// Deselects a deleted playlist wherever it's selected.
+    pub fn remove(&mut self, playlist_id: Uuid) {
+        for selected in [&mut self.selected, &mut self.autoplay] {
+            if selected
+                .as_ref()
+                .is_some_and(|selected| selected.id == playlist_id)
+            {
+                *selected = None;
+            }
+        }
+    }
 }
@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::{
+    context::storage::RemovedTracks,
+    database::{connection::DatabaseCommand, models::playlists::playlist::Playlist},
+};
+
+/// How long removed tracks can be put back before the removal is committed to the database.
+pub const REMOVAL_UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemovalTarget {
+    /// Tracks are only taken out of this playlist
+    Playlist(Playlist),
+    /// Tracks are deleted from the library, along with every playlist and tag they're in
+    Library,
+}
+
+/// Tracks that have been taken out of storage, but not yet out of the database.
+#[derive(Debug, Clone)]
+pub struct PendingRemoval {
+    pub target: RemovalTarget,
+    pub track_ids: Vec<Uuid>,
+    /// What's needed to put the tracks back in storage if the removal is undone
+    pub removed: RemovedTracks,
+    pub started: Instant,
+}
+
+impl PendingRemoval {
+    pub fn new(target: RemovalTarget, track_ids: Vec<Uuid>, removed: RemovedTracks) -> Self {
+        Self {
+            target,
+            track_ids,
+            removed,
+            started: Instant::now(),
+        }
+    }
+
+    /// How long is left to undo the removal.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        REMOVAL_UNDO_WINDOW.saturating_sub(now.duration_since(self.started))
+    }
+
+    /// If the removed tracks are taken out of the tracks of a playlist (or all tracks if none is passed).
+    pub fn affects(&self, playlist: Option<&Playlist>) -> bool {
+        match &self.target {
+            RemovalTarget::Playlist(removed_from) => {
+                playlist.is_some_and(|playlist| playlist.id == removed_from.id)
+            }
+            RemovalTarget::Library => true,
+        }
+    }
+
+    /// The command that commits the removal to the database.
+    pub fn command(&self) -> DatabaseCommand {
+        match &self.target {
+            RemovalTarget::Playlist(playlist) => {
+                DatabaseCommand::DeletePlaylistTracks(playlist.clone(), self.track_ids.clone())
+            }
+            RemovalTarget::Library => DatabaseCommand::DeleteTracks(self.track_ids.clone()),
+        }
+    }
+
+    /// What was removed, such as "Removed 3 tracks from the library".
+    pub fn description(&self) -> String {
+        let tracks = match self.track_ids.len() {
+            1 => "1 track".to_string(),
+            count => format!("{count} tracks"),
+        };
+
+        match &self.target {
+            RemovalTarget::Playlist(playlist) => format!("Removed {tracks} from {}", playlist.name),
+            RemovalTarget::Library => format!("Removed {tracks} from the library"),
+        }
+    }
+}
+
+/// The latest removal of tracks, which can be undone until its undo window runs out.
+/// Only one removal is pending at a time.
+#[derive(Debug, Clone, Default)]
+pub struct RemovalContext {
+    pending: Option<PendingRemoval>,
+}
+
+impl RemovalContext {
+    pub fn pending(&self) -> Option<&PendingRemoval> {
+        self.pending.as_ref()
+    }
+
+    /// Starts the undo window of a removal, returning the previous removal (if any) which can't be
+    /// undone anymore and has to be committed.
+    pub fn start(&mut self, removal: PendingRemoval) -> Option<PendingRemoval> {
+        self.pending.replace(removal)
+    }
+
+    /// Takes the pending removal, such as to undo it or to commit it early.
+    pub fn take(&mut self) -> Option<PendingRemoval> {
+        self.pending.take()
+    }
+
+    /// Takes the pending removal if its undo window has run out.
+    pub fn take_expired(&mut self, now: Instant) -> Option<PendingRemoval> {
+        self.pending
+            .take_if(|removal| removal.remaining(now).is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_removal_expires_after_undo_window() {
+        let mut removal = RemovalContext::default();
+        let first = PendingRemoval::new(
+            RemovalTarget::Library,
+            vec![Uuid::new_v4()],
+            RemovedTracks::default(),
+        );
+        let started = first.started;
+
+        assert!(removal.start(first).is_none());
+        assert!(removal.take_expired(started).is_none());
+        assert!(removal.pending().is_some());
+
+        let second = PendingRemoval::new(
+            RemovalTarget::Library,
+            vec![Uuid::new_v4(), Uuid::new_v4()],
+            RemovedTracks::default(),
+        );
+        let replaced = removal.start(second).unwrap();
+        assert_eq!(replaced.description(), "Removed 1 track from the library");
+
+        let expired = removal
+            .take_expired(Instant::now() + REMOVAL_UNDO_WINDOW)
+            .unwrap();
+        assert!(matches!(expired.command(), DatabaseCommand::DeleteTracks(ids) if ids.len() == 2));
+        assert!(removal.pending().is_none());
+    }
+}
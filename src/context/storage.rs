@@ -1,6 +1,18 @@
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-use crate::database::models::{playlists::playlist::Playlist, tracks::Track};
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    config::search::MatcherFn,
+    database::models::{
+        playlists::playlist::Playlist, sort::TrackSort, tags::tag::Tag, tracks::Track,
+    },
+    utils::query::QueryEnv,
+};
+
+/// Key the sort of each playlist's tracks is stored under in eframe's app storage.
+pub const TRACK_SORTS_STORAGE_KEY: &str = "track_sorts";
 
 #[derive(Debug, Clone, Default)]
 pub struct StorageContext {
@@ -16,6 +28,25 @@ pub struct StorageContext {
     /// Hashmap is used due to the playlists not being displayed directly in the UI, while [`Self::playlist_tracks`] is.
     /// Probably will switch to BTreeMap in the future once searching for playlists is implemented.
     filtered_playlist_tracks: HashMap<Playlist, Vec<Track>>,
+    /// All tags, and the IDs of the tracks each one is assigned to.
+    /// Only IDs are kept since tracks themselves are already loaded in [`Self::all_tracks`].
+    tag_tracks: BTreeMap<Tag, BTreeSet<Uuid>>,
+    /// How each playlist's tracks are sorted, keyed by playlist ID, or [`None`] for all tracks.
+    /// Playlists that haven't been sorted yet are sorted by [`TrackSort::default`].
+    track_sorts: HashMap<Option<Uuid>, TrackSort>,
+    /// If the library or tags have changed since the tracks of smart playlists were last picked
+    smart_playlists_outdated: bool,
+}
+
+/// Tracks taken out of the loaded track lists and tags, which can be put back where they were.
+#[derive(Debug, Clone, Default)]
+pub struct RemovedTracks {
+    /// Removed tracks of each list, keyed by playlist ID, or [`None`] for all tracks
+    tracks: Vec<(Option<Uuid>, Vec<Track>)>,
+    /// Removed tracks of each filtered list, keyed the same way
+    filtered_tracks: Vec<(Option<Uuid>, Vec<Track>)>,
+    /// Removed tracks of each tag, keyed by tag ID
+    tag_tracks: Vec<(Uuid, Vec<Uuid>)>,
 }
 
 impl StorageContext {
@@ -34,34 +65,140 @@ impl StorageContext {
         self.playlist_tracks.keys()
     }
 
-    /// Sets a playlist's tracks to the passed track vector after sorting it based on comparing track paths.
+    /// How a playlist's tracks are sorted, or all tracks if no playlist is passed.
+    pub fn track_sort(&self, playlist: Option<&Playlist>) -> TrackSort {
+        Self::sort_of(&self.track_sorts, playlist)
+    }
+
+    /// Same as [`Self::track_sort`], for when other fields are already borrowed.
+    /// Smart playlists that haven't been sorted yet are sorted the same way their tracks were picked.
+    fn sort_of(
+        track_sorts: &HashMap<Option<Uuid>, TrackSort>,
+        playlist: Option<&Playlist>,
+    ) -> TrackSort {
+        track_sorts
+            .get(&playlist.map(|playlist| playlist.id))
+            .copied()
+            .or_else(|| Some(playlist?.smart.as_ref()?.sort))
+            .unwrap_or_default()
+    }
+
+    /// Every sort that has been set, keyed by playlist ID, or [`None`] for all tracks.
+    pub fn track_sorts(&self) -> &HashMap<Option<Uuid>, TrackSort> {
+        &self.track_sorts
+    }
+
+    /// Replaces all sorts with ones loaded from a previous session, re-sorting any loaded tracks.
+    pub fn set_track_sorts(&mut self, track_sorts: HashMap<Option<Uuid>, TrackSort>) {
+        self.track_sorts = track_sorts;
+
+        let all_sort = Self::sort_of(&self.track_sorts, None);
+        all_sort.sort(&mut self.all_tracks);
+        if let Some(filtered_all_tracks) = &mut self.filtered_all_tracks {
+            all_sort.sort(filtered_all_tracks);
+        }
+
+        for (playlist, tracks) in self
+            .playlist_tracks
+            .iter_mut()
+            .chain(self.filtered_playlist_tracks.iter_mut())
+        {
+            Self::sort_of(&self.track_sorts, Some(playlist)).sort(tracks);
+        }
+    }
+
+    /// Sorts a playlist's tracks (including filtered ones) by another column or order,
+    /// or all tracks if no playlist is passed.
+    pub fn set_track_sort(&mut self, playlist: Option<&Playlist>, sort: TrackSort) {
+        self.track_sorts
+            .insert(playlist.map(|playlist| playlist.id), sort);
+
+        if let Some(playlist) = playlist {
+            let tracks = self
+                .playlist_tracks
+                .get_mut(playlist)
+                .into_iter()
+                .chain(self.filtered_playlist_tracks.get_mut(playlist));
+
+            for tracks in tracks {
+                sort.sort(tracks);
+            }
+        } else {
+            sort.sort(&mut self.all_tracks);
+            if let Some(filtered_all_tracks) = &mut self.filtered_all_tracks {
+                sort.sort(filtered_all_tracks);
+            }
+        }
+    }
+
+    /// Sets a playlist's tracks to the passed track vector after sorting it by the playlist's sort.
     /// If no playlist is passed, then all tracks are set to the sorted track vector.
+    ///
+    /// Smart playlists don't have tracks added to them, so their tracks are picked again instead.
     pub fn set_playlist_tracks(&mut self, playlist: Option<Playlist>, mut tracks: Vec<Track>) {
-        tracks.sort_by(|a, b| a.path.cmp(&b.path));
+        if playlist.as_ref().is_some_and(Playlist::is_smart) {
+            self.smart_playlists_outdated = true;
+            return;
+        }
+
+        self.track_sort(playlist.as_ref()).sort(&mut tracks);
 
         if let Some(playlist) = playlist {
             self.playlist_tracks.insert(playlist, tracks);
         } else {
             self.all_tracks = tracks;
+            self.smart_playlists_outdated = true;
         }
     }
 
-    /// Sorted in-place insertion of a track to [`Self::all_tracks`], comparing by path on insert.
-    fn insert_sorted_to_all_tracks(&mut self, track: Track) {
-        let pos = self
-            .all_tracks
-            .binary_search_by(|other_track| other_track.path.cmp(&track.path))
-            .unwrap_or_else(|e| e);
-        self.all_tracks.insert(pos, track);
+    pub fn smart_playlists_outdated(&self) -> bool {
+        self.smart_playlists_outdated
     }
 
-    /// Sorted in-place insertion of a track to a playlist's vector
-    /// in [`Self::playlist_tracks`], comparing by path on insert.
-    fn insert_sorted_to_playlist(playlist_tracks: &mut Vec<Track>, track: Track) {
-        let pos = playlist_tracks
-            .binary_search_by(|other_track| other_track.path.cmp(&track.path))
+    /// Picks the tracks of every smart playlist out of all tracks again, such as after the library has changed.
+    pub fn refresh_smart_playlists(&mut self, matcher: &MatcherFn) {
+        self.smart_playlists_outdated = false;
+
+        let env = QueryEnv {
+            matcher,
+            track_tags: self.tag_names_by_track(),
+            now: Utc::now(),
+        };
+
+        for (playlist, tracks) in self.playlist_tracks.iter_mut() {
+            let Some(smart) = &playlist.smart else {
+                continue;
+            };
+
+            *tracks = smart.evaluate(&self.all_tracks, &env);
+            Self::sort_of(&self.track_sorts, Some(playlist)).sort(tracks);
+
+            // Any search of the playlist was made on its previous tracks
+            self.filtered_playlist_tracks.remove(playlist);
+        }
+    }
+
+    /// Sorted in-place insertion of a track to a track vector, such as [`Self::all_tracks`]
+    /// or a playlist's vector in [`Self::playlist_tracks`], comparing with the vector's sort on insert.
+    fn insert_sorted(tracks: &mut Vec<Track>, track: Track, sort: TrackSort) {
+        let pos = tracks
+            .binary_search_by(|other_track| sort.compare(other_track, &track))
             .unwrap_or_else(|e| e);
-        playlist_tracks.insert(pos, track);
+        tracks.insert(pos, track);
+    }
+
+    /// Inserts a single track in place, but sorts many at once, such as a batch of imported tracks,
+    /// instead of shifting the list over for each of them.
+    fn insert_all_sorted(tracks: &mut Vec<Track>, mut new_tracks: Vec<Track>, sort: TrackSort) {
+        if new_tracks.len() == 1
+            && let Some(track) = new_tracks.pop()
+        {
+            Self::insert_sorted(tracks, track, sort);
+            return;
+        }
+
+        tracks.extend(new_tracks);
+        tracks.sort_by(|a, b| sort.compare(a, b));
     }
 
     /// Extends the track list in a playlist of [`Self::playlist_tracks`] with the passed through tracks.
@@ -70,18 +207,333 @@ impl StorageContext {
     /// When the playlist is some but doesn't exist in the [`Self::playlist_tracks`] attribute,
     /// a new vector is created and tracks are added to it.
     pub fn add_tracks_to_playlist(&mut self, playlist: Option<&Playlist>, tracks: Vec<Track>) {
-        for track in tracks {
-            if let Some(playlist) = playlist {
-                let playlist_tracks = self.playlist_tracks.entry(playlist.clone()).or_default();
-                Self::insert_sorted_to_playlist(playlist_tracks, track.clone());
-            }
-            self.insert_sorted_to_all_tracks(track);
+        let playlist_sort = self.track_sort(playlist);
+        let all_sort = self.track_sort(None);
+
+        if let Some(playlist) = playlist {
+            let playlist_tracks = self.playlist_tracks.entry(playlist.clone()).or_default();
+            Self::insert_all_sorted(playlist_tracks, tracks.clone(), playlist_sort);
         }
+        Self::insert_all_sorted(&mut self.all_tracks, tracks, all_sort);
+
+        self.smart_playlists_outdated = true;
     }
 
     /// Create a playlist in [`Self::playlist_tracks`] with an empty vector of tracks.
+    /// The tracks of a smart playlist are picked shortly after.
     pub fn add_empty_playlist(&mut self, playlist: &Playlist) {
         self.playlist_tracks.insert(playlist.clone(), Vec::new());
+        self.smart_playlists_outdated |= playlist.is_smart();
+    }
+
+    /// Replaces a playlist with its renamed version while keeping its tracks.
+    pub fn rename_playlist(&mut self, playlist: Playlist) {
+        let Some(previous) = self.find_playlist(playlist.id) else {
+            return;
+        };
+
+        if let Some(tracks) = self.playlist_tracks.remove(&previous) {
+            self.playlist_tracks.insert(playlist.clone(), tracks);
+        }
+
+        if let Some(filtered) = self.filtered_playlist_tracks.remove(&previous) {
+            self.filtered_playlist_tracks.insert(playlist, filtered);
+        }
+    }
+
+    /// Adds a copy of a playlist with its tracks, sorted the same way as the playlist it was copied from.
+    pub fn add_duplicate_playlist(
+        &mut self,
+        source: &Playlist,
+        duplicate: Playlist,
+        tracks: Vec<Track>,
+    ) {
+        if let Some(&sort) = self.track_sorts.get(&Some(source.id)) {
+            self.track_sorts.insert(Some(duplicate.id), sort);
+        }
+
+        self.add_empty_playlist(&duplicate);
+        self.set_playlist_tracks(Some(duplicate), tracks);
+    }
+
+    /// Removes a playlist by its ID along with its filtered tracks and sort, returning its tracks.
+    pub fn remove_playlist(&mut self, playlist_id: Uuid) -> Option<Vec<Track>> {
+        let playlist = self.find_playlist(playlist_id)?;
+
+        self.filtered_playlist_tracks.remove(&playlist);
+        self.track_sorts.remove(&Some(playlist_id));
+
+        self.playlist_tracks.remove(&playlist)
+    }
+
+    /// Takes tracks out of a playlist (including its filtered tracks) by their IDs,
+    /// returning them so they can be restored with [`Self::restore_tracks`].
+    pub fn remove_playlist_tracks(
+        &mut self,
+        playlist: &Playlist,
+        track_ids: &[Uuid],
+    ) -> RemovedTracks {
+        let track_ids: HashSet<Uuid> = track_ids.iter().copied().collect();
+        let mut removed = RemovedTracks::default();
+
+        if let Some(tracks) = self.playlist_tracks.get_mut(playlist) {
+            removed
+                .tracks
+                .push((Some(playlist.id), Self::take_tracks(tracks, &track_ids)));
+        }
+
+        if let Some(tracks) = self.filtered_playlist_tracks.get_mut(playlist) {
+            removed
+                .filtered_tracks
+                .push((Some(playlist.id), Self::take_tracks(tracks, &track_ids)));
+        }
+
+        removed
+    }
+
+    /// Takes tracks out of the library by their IDs, which removes them from all tracks, every playlist and
+    /// every tag. They're returned so they can be restored with [`Self::restore_tracks`].
+    pub fn remove_library_tracks(&mut self, track_ids: &[Uuid]) -> RemovedTracks {
+        let track_ids: HashSet<Uuid> = track_ids.iter().copied().collect();
+        let mut removed = RemovedTracks::default();
+
+        removed
+            .tracks
+            .push((None, Self::take_tracks(&mut self.all_tracks, &track_ids)));
+        if let Some(tracks) = &mut self.filtered_all_tracks {
+            removed
+                .filtered_tracks
+                .push((None, Self::take_tracks(tracks, &track_ids)));
+        }
+
+        for (playlist, tracks) in &mut self.playlist_tracks {
+            removed
+                .tracks
+                .push((Some(playlist.id), Self::take_tracks(tracks, &track_ids)));
+        }
+        for (playlist, tracks) in &mut self.filtered_playlist_tracks {
+            removed
+                .filtered_tracks
+                .push((Some(playlist.id), Self::take_tracks(tracks, &track_ids)));
+        }
+
+        for (tag, tag_track_ids) in &mut self.tag_tracks {
+            let taken: Vec<Uuid> = track_ids
+                .iter()
+                .filter(|track_id| tag_track_ids.remove(track_id))
+                .copied()
+                .collect();
+            removed.tag_tracks.push((tag.id, taken));
+        }
+
+        self.smart_playlists_outdated = true;
+
+        removed
+    }
+
+    /// Puts removed tracks back into the lists and tags they were taken out of, in sorted position.
+    /// Lists and tags that have been deleted since are skipped.
+    pub fn restore_tracks(&mut self, removed: RemovedTracks) {
+        for (playlist_id, tracks) in removed.tracks {
+            let playlist = playlist_id.and_then(|playlist_id| self.find_playlist(playlist_id));
+            let sort = Self::sort_of(&self.track_sorts, playlist.as_ref());
+
+            let list = match &playlist {
+                Some(playlist) => self.playlist_tracks.get_mut(playlist),
+                None if playlist_id.is_none() => Some(&mut self.all_tracks),
+                None => None,
+            };
+
+            if let Some(list) = list {
+                for track in tracks {
+                    Self::insert_sorted(list, track, sort);
+                }
+            }
+        }
+
+        for (playlist_id, tracks) in removed.filtered_tracks {
+            let playlist = playlist_id.and_then(|playlist_id| self.find_playlist(playlist_id));
+            let sort = Self::sort_of(&self.track_sorts, playlist.as_ref());
+
+            let list = match &playlist {
+                Some(playlist) => self.filtered_playlist_tracks.get_mut(playlist),
+                None if playlist_id.is_none() => self.filtered_all_tracks.as_mut(),
+                None => None,
+            };
+
+            if let Some(list) = list {
+                for track in tracks {
+                    Self::insert_sorted(list, track, sort);
+                }
+            }
+        }
+
+        for (tag_id, track_ids) in removed.tag_tracks {
+            if let Some((_, tag_track_ids)) =
+                self.tag_tracks.iter_mut().find(|(tag, _)| tag.id == tag_id)
+            {
+                tag_track_ids.extend(track_ids);
+            }
+        }
+
+        self.smart_playlists_outdated = true;
+    }
+
+    /// Takes the tracks with the passed through IDs out of a track list, keeping the order of the rest.
+    fn take_tracks(tracks: &mut Vec<Track>, track_ids: &HashSet<Uuid>) -> Vec<Track> {
+        tracks
+            .extract_if(.., |track| track_ids.contains(&track.id))
+            .collect()
+    }
+
+    fn find_playlist(&self, playlist_id: Uuid) -> Option<Playlist> {
+        self.playlists()
+            .find(|playlist| playlist.id == playlist_id)
+            .cloned()
+    }
+
+    /// Names a copy of a playlist after it, numbering the copy if that name is already taken.
+    pub fn duplicate_playlist_name(&self, playlist: &Playlist) -> String {
+        let taken = |name: &str| self.playlists().any(|playlist| playlist.name == name);
+
+        let name = format!("{} (copy)", playlist.name);
+        if !taken(&name) {
+            return name;
+        }
+
+        (2..)
+            .map(|number| format!("{} (copy {number})", playlist.name))
+            .find(|name| !taken(name))
+            .unwrap_or(name)
+    }
+
+    /// Replaces every loaded copy of the passed through tracks (matched by ID) with their updated versions,
+    /// including filtered tracks. Track lists are re-sorted, in case a sorted value has changed.
+    pub fn update_tracks(&mut self, tracks: &[Track]) {
+        let updated: HashMap<Uuid, &Track> = tracks.iter().map(|track| (track.id, track)).collect();
+
+        let all_sort = Self::sort_of(&self.track_sorts, None);
+        let track_lists = std::iter::once((&mut self.all_tracks, all_sort))
+            .chain(
+                self.filtered_all_tracks
+                    .as_mut()
+                    .map(|tracks| (tracks, all_sort)),
+            )
+            .chain(
+                self.playlist_tracks
+                    .iter_mut()
+                    .chain(self.filtered_playlist_tracks.iter_mut())
+                    .map(|(playlist, tracks)| {
+                        (tracks, Self::sort_of(&self.track_sorts, Some(playlist)))
+                    }),
+            );
+
+        for (track_list, sort) in track_lists {
+            let mut changed = false;
+
+            for track in track_list.iter_mut() {
+                if let Some(updated_track) = updated.get(&track.id) {
+                    *track = (*updated_track).clone();
+                    changed = true;
+                }
+            }
+
+            if changed {
+                sort.sort(track_list);
+            }
+        }
+
+        self.smart_playlists_outdated = true;
+    }
+
+    /// Get an iterator of all tags and the IDs of the tracks they're assigned to.
+    pub fn tags(&self) -> impl Iterator<Item = (&Tag, &BTreeSet<Uuid>)> {
+        self.tag_tracks.iter()
+    }
+
+    /// IDs of the tracks a tag is assigned to, if the tag exists.
+    pub fn tag_track_ids(&self, tag: &Tag) -> Option<&BTreeSet<Uuid>> {
+        self.tag_tracks.get(tag)
+    }
+
+    /// Get an iterator of all tags assigned to a track.
+    pub fn track_tags(&self, track_id: Uuid) -> impl Iterator<Item = &Tag> {
+        self.tag_tracks
+            .iter()
+            .filter(move |(_, track_ids)| track_ids.contains(&track_id))
+            .map(|(tag, _)| tag)
+    }
+
+    /// Names of the tags assigned to each track, for tracks that have any.
+    pub fn tag_names_by_track(&self) -> HashMap<Uuid, Vec<String>> {
+        let mut tag_names: HashMap<Uuid, Vec<String>> = HashMap::new();
+
+        for (tag, track_ids) in &self.tag_tracks {
+            for track_id in track_ids {
+                tag_names
+                    .entry(*track_id)
+                    .or_default()
+                    .push(tag.name.clone());
+            }
+        }
+
+        tag_names
+    }
+
+    /// Replaces all tags with the passed through tags and their track IDs.
+    pub fn set_tags(&mut self, tags: Vec<(Tag, Vec<Uuid>)>) {
+        self.tag_tracks = tags
+            .into_iter()
+            .map(|(tag, track_ids)| (tag, track_ids.into_iter().collect()))
+            .collect();
+
+        self.smart_playlists_outdated = true;
+    }
+
+    /// Create a tag in [`Self::tag_tracks`] that isn't assigned to any tracks,
+    /// keeping the existing assignments if the tag is already present.
+    pub fn add_empty_tag(&mut self, tag: &Tag) {
+        self.tag_tracks.entry(tag.clone()).or_default();
+    }
+
+    /// Replaces a tag with its renamed version while keeping the tracks it's assigned to.
+    pub fn rename_tag(&mut self, tag: Tag) {
+        let track_ids = self
+            .remove_tag(tag.id)
+            .map(|(_, track_ids)| track_ids)
+            .unwrap_or_default();
+
+        self.tag_tracks.insert(tag, track_ids);
+    }
+
+    /// Removes a tag by its ID, returning the tag and the tracks it was assigned to.
+    pub fn remove_tag(&mut self, tag_id: Uuid) -> Option<(Tag, BTreeSet<Uuid>)> {
+        let tag = self.tag_tracks.keys().find(|tag| tag.id == tag_id)?.clone();
+
+        self.smart_playlists_outdated = true;
+
+        self.tag_tracks.remove_entry(&tag)
+    }
+
+    /// Assigns a tag to tracks by their IDs.
+    pub fn add_tag_tracks(&mut self, tag: &Tag, track_ids: Vec<Uuid>) {
+        self.tag_tracks
+            .entry(tag.clone())
+            .or_default()
+            .extend(track_ids);
+
+        self.smart_playlists_outdated = true;
+    }
+
+    /// Removes a tag from tracks by their IDs.
+    pub fn remove_tag_tracks(&mut self, tag: &Tag, track_ids: &[Uuid]) {
+        if let Some(tag_track_ids) = self.tag_tracks.get_mut(tag) {
+            for track_id in track_ids {
+                tag_track_ids.remove(track_id);
+            }
+        }
+
+        self.smart_playlists_outdated = true;
     }
 
     /// Returns a playlist's tracks, checking filtered track attributes first, eventually narrowing down to the global tracks playlist [`Self::all_tracks`].
@@ -155,11 +607,55 @@ impl StorageContext {
             self.filtered_all_tracks.as_ref()
         }
     }
+
+    /// Filters the tracks of a playlist down to the tracks with the passed through IDs, such as
+    /// the results of a full text search, keeping them in the order of the IDs.
+    /// IDs of tracks that aren't in the playlist or don't match the predicate are skipped.
+    pub fn filter_ranked<F>(
+        &mut self,
+        playlist: &Option<Playlist>,
+        track_ids: &[Uuid],
+        predicate: F,
+    ) -> Option<&Vec<Track>>
+    where
+        F: Fn(&Track) -> bool,
+    {
+        let source = match playlist {
+            Some(playlist) => self.playlist_tracks.get(playlist)?,
+            None => &self.all_tracks,
+        };
+
+        let tracks_by_id: HashMap<Uuid, &Track> =
+            source.iter().map(|track| (track.id, track)).collect();
+
+        let filtered: Vec<Track> = track_ids
+            .iter()
+            .filter_map(|track_id| tracks_by_id.get(track_id))
+            .filter(|&&track| predicate(track))
+            .map(|&track| track.clone())
+            .collect();
+
+        if let Some(playlist) = playlist {
+            self.filtered_playlist_tracks
+                .insert(playlist.clone(), filtered);
+            self.filtered_playlist_tracks.get(playlist)
+        } else {
+            self.filtered_all_tracks = Some(filtered);
+            self.filtered_all_tracks.as_ref()
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::database::{
+        models::{
+            playlists::smart_playlist::SmartPlaylist,
+            sort::{SortOrder, TrackColumn},
+        },
+        test_utils::track,
+    };
 
     #[test]
     fn test_filter_with_global_tracks() {
@@ -206,4 +702,236 @@ mod tests {
         assert_eq!(result.unwrap().len(), 1);
         assert_eq!(result.unwrap()[0].name, "song 1");
     }
+
+    #[test]
+    fn test_rename_duplicate_and_remove_playlist() {
+        let mut storage = StorageContext::default();
+
+        let playlist = Playlist {
+            name: "mix".to_string(),
+            ..Default::default()
+        };
+        let tracks = vec![Track::default(), Track::default()];
+        let sort = TrackSort::default().toggled(TrackColumn::Duration);
+        storage.set_playlist_tracks(Some(playlist.clone()), tracks.clone());
+        storage.set_track_sort(Some(&playlist), sort);
+        storage.filter_with(&Some(playlist.clone()), |_| true);
+
+        let renamed = Playlist {
+            name: "road trip".to_string(),
+            ..playlist.clone()
+        };
+        storage.rename_playlist(renamed.clone());
+        assert_eq!(storage.playlists().collect::<Vec<_>>(), [&renamed]);
+        assert_eq!(
+            storage.get_playlist_tracks(Some(&renamed)).unwrap().len(),
+            2
+        );
+        assert_eq!(storage.filtered_tracks(Some(&renamed)).len(), 2);
+
+        assert_eq!(
+            storage.duplicate_playlist_name(&renamed),
+            "road trip (copy)"
+        );
+        let duplicate = Playlist {
+            name: storage.duplicate_playlist_name(&renamed),
+            ..Default::default()
+        };
+        storage.add_duplicate_playlist(&renamed, duplicate.clone(), tracks);
+        assert_eq!(storage.track_sort(Some(&duplicate)), sort);
+        assert_eq!(
+            storage.duplicate_playlist_name(&renamed),
+            "road trip (copy 2)"
+        );
+
+        assert_eq!(storage.remove_playlist(renamed.id).unwrap().len(), 2);
+        assert_eq!(storage.track_sort(Some(&renamed)), TrackSort::default());
+        assert_eq!(storage.playlists().collect::<Vec<_>>(), [&duplicate]);
+    }
+
+    #[test]
+    fn test_removed_library_tracks_can_be_restored() {
+        let mut storage = StorageContext::default();
+
+        let tracks: Vec<Track> = ["a", "b", "c"].into_iter().map(track).collect();
+        let playlist = Playlist {
+            name: "mix".to_string(),
+            ..Default::default()
+        };
+        let tag = Tag {
+            name: "live".to_string(),
+            ..Default::default()
+        };
+        storage.set_playlist_tracks(None, tracks.clone());
+        storage.set_playlist_tracks(Some(playlist.clone()), tracks[1..].to_vec());
+        storage.set_tags(vec![(tag.clone(), vec![tracks[1].id])]);
+        storage.filter_with(&None, |_| true);
+
+        let removed = storage.remove_library_tracks(&[tracks[1].id]);
+        let names = |tracks: &[Track]| -> Vec<String> {
+            tracks.iter().map(|track| track.name.clone()).collect()
+        };
+        assert_eq!(
+            names(storage.get_playlist_tracks(None).unwrap()),
+            ["a", "c"]
+        );
+        assert_eq!(names(storage.filtered_tracks(None)), ["a", "c"]);
+        assert_eq!(
+            names(storage.get_playlist_tracks(Some(&playlist)).unwrap()),
+            ["c"]
+        );
+        assert!(storage.tag_track_ids(&tag).unwrap().is_empty());
+
+        storage.restore_tracks(removed);
+        assert_eq!(
+            names(storage.get_playlist_tracks(None).unwrap()),
+            ["a", "b", "c"]
+        );
+        assert_eq!(names(storage.filtered_tracks(None)), ["a", "b", "c"]);
+        assert_eq!(
+            names(storage.get_playlist_tracks(Some(&playlist)).unwrap()),
+            ["b", "c"]
+        );
+        assert!(storage.tag_track_ids(&tag).unwrap().contains(&tracks[1].id));
+
+        let removed = storage.remove_playlist_tracks(&playlist, &[tracks[2].id]);
+        assert_eq!(
+            names(storage.get_playlist_tracks(None).unwrap()),
+            ["a", "b", "c"]
+        );
+        assert_eq!(
+            names(storage.get_playlist_tracks(Some(&playlist)).unwrap()),
+            ["b"]
+        );
+        storage.restore_tracks(removed);
+        assert_eq!(
+            names(storage.get_playlist_tracks(Some(&playlist)).unwrap()),
+            ["b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_rename_tag_keeps_tracks() {
+        let mut storage = StorageContext::default();
+
+        let tag = Tag {
+            name: "live".to_string(),
+            ..Default::default()
+        };
+        let track_ids = vec![Uuid::new_v4(), Uuid::new_v4()];
+        storage.set_tags(vec![(tag.clone(), track_ids.clone())]);
+
+        let renamed = Tag {
+            name: "concerts".to_string(),
+            ..tag.clone()
+        };
+        storage.rename_tag(renamed.clone());
+
+        assert!(storage.tag_track_ids(&tag).is_none());
+        assert_eq!(storage.tag_track_ids(&renamed).unwrap().len(), 2);
+        assert_eq!(storage.track_tags(track_ids[0]).next(), Some(&renamed));
+
+        storage.remove_tag_tracks(&renamed, &track_ids[..1]);
+        assert_eq!(storage.track_tags(track_ids[0]).count(), 0);
+
+        assert!(storage.remove_tag(renamed.id).is_some());
+        assert_eq!(storage.tags().count(), 0);
+    }
+
+    #[test]
+    fn test_update_tracks_replaces_filtered_tracks() {
+        let mut storage = StorageContext::default();
+
+        let track = Track {
+            name: "foo 1".to_string(),
+            hash: Some("old".to_string()),
+            ..Default::default()
+        };
+        storage.set_playlist_tracks(None, vec![track.clone()]);
+        storage.filter_with(&None, |track| track.name.contains("foo"));
+
+        let rehashed = Track {
+            hash: Some("new".to_string()),
+            ..track
+        };
+        storage.update_tracks(std::slice::from_ref(&rehashed));
+
+        assert_eq!(storage.get_playlist_tracks(None).unwrap()[0], rehashed);
+        assert_eq!(storage.filtered_tracks(None)[0], rehashed);
+    }
+
+    #[test]
+    fn test_track_sort_applies_to_inserted_tracks() {
+        let mut storage = StorageContext::default();
+
+        let track = |name: &str, duration_secs: f64| Track {
+            name: name.to_string(),
+            path: format!("/music/{name}.mp3").into(),
+            duration_secs,
+            ..Default::default()
+        };
+        storage.set_playlist_tracks(None, vec![track("a", 30.0), track("b", 10.0)]);
+
+        let sort = TrackSort {
+            column: TrackColumn::Duration,
+            order: SortOrder::Descending,
+        };
+        storage.set_track_sort(None, sort);
+        storage.add_tracks_to_playlist(None, vec![track("c", 20.0)]);
+
+        let names: Vec<&str> = storage
+            .filtered_tracks(None)
+            .iter()
+            .map(|track| track.name.as_str())
+            .collect();
+        assert_eq!(names, ["a", "c", "b"]);
+        assert_eq!(storage.track_sort(None), sort);
+    }
+
+    #[test]
+    fn test_smart_playlist_follows_library() {
+        let mut storage = StorageContext::default();
+
+        let track = |name: &str, duration_secs: f64| Track {
+            name: name.to_string(),
+            path: format!("/music/{name}.mp3").into(),
+            duration_secs,
+            ..Default::default()
+        };
+        let playlist = Playlist {
+            name: "Longest".to_string(),
+            smart: Some(SmartPlaylist::new(
+                "duration>1:00".to_string(),
+                TrackSort {
+                    column: TrackColumn::Duration,
+                    order: SortOrder::Descending,
+                },
+                Some(2),
+            )),
+            ..Default::default()
+        };
+        let matcher: MatcherFn = Box::new(|_, _| true);
+
+        storage.add_empty_playlist(&playlist);
+        storage.set_playlist_tracks(None, vec![track("a", 90.0), track("b", 30.0)]);
+        assert!(storage.smart_playlists_outdated());
+
+        storage.refresh_smart_playlists(&matcher);
+        assert!(!storage.smart_playlists_outdated());
+        assert_eq!(storage.filtered_tracks(Some(&playlist)).len(), 1);
+
+        storage.add_tracks_to_playlist(None, vec![track("c", 120.0), track("d", 100.0)]);
+        storage.refresh_smart_playlists(&matcher);
+
+        let names: Vec<&str> = storage
+            .filtered_tracks(Some(&playlist))
+            .iter()
+            .map(|track| track.name.as_str())
+            .collect();
+        assert_eq!(names, ["c", "d"]);
+
+        // Smart playlists aren't stored with tracks, so querying one doesn't replace its tracks
+        storage.set_playlist_tracks(Some(playlist.clone()), Vec::new());
+        assert_eq!(storage.filtered_tracks(Some(&playlist)).len(), 2);
+    }
 }
@@ -0,0 +1,27 @@
+use crate::database::models::tags::tag::Tag;
+
+#[derive(Debug, Clone, Default)]
+pub struct UITagContext {
+    /// Which tag is currently selected in the UI to filter the tracks table down to
+    selected: Option<Tag>,
+    /// Why the last change to a tag failed, shown until dismissed
+    error: Option<String>,
+}
+
+impl UITagContext {
+    pub fn selected(&self) -> Option<Tag> {
+        self.selected.clone()
+    }
+
+    pub fn set_selected(&mut self, tag: Option<Tag>) {
+        self.selected = tag;
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn set_error(&mut self, error: Option<String>) {
+        self.error = error;
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, Local, TimeDelta};
+use uuid::Uuid;
+
+use crate::tasks::{TaskEvent, TaskFailure, TaskHandle, TaskKind, TaskStatus};
+
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub kind: TaskKind,
+    pub label: String,
+    pub completed: usize,
+    pub total: usize,
+    pub started_at: DateTime<Local>,
+    pub finished_at: Option<DateTime<Local>>,
+    pub status: TaskStatus,
+    pub failures: Vec<TaskFailure>,
+    handle: TaskHandle,
+}
+
+impl Task {
+    pub fn id(&self) -> Uuid {
+        self.handle.id()
+    }
+
+    /// Fraction of work done, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            return if self.status.is_running() { 0.0 } else { 1.0 };
+        }
+
+        self.completed as f32 / self.total as f32
+    }
+
+    /// How long the task has been running for, or how long it took if it's finished.
+    pub fn elapsed(&self) -> TimeDelta {
+        self.finished_at.unwrap_or_else(Local::now) - self.started_at
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TaskContext {
+    /// Every task that has been registered this session, in the order they were started
+    tasks: Vec<Task>,
+}
+
+impl TaskContext {
+    /// Registers a new running task, returning the handle to send along with the work.
+    pub fn register(&mut self, kind: TaskKind, label: String, total: usize) -> TaskHandle {
+        let handle = TaskHandle::new();
+
+        self.tasks.push(Task {
+            kind,
+            label,
+            completed: 0,
+            total,
+            started_at: Local::now(),
+            finished_at: None,
+            status: TaskStatus::Running,
+            failures: Vec::new(),
+            handle: handle.clone(),
+        });
+
+        handle
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    pub fn running(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter().filter(|task| task.status.is_running())
+    }
+
+    /// Requests a running task to stop. The task is only marked as cancelled
+    /// once whatever is doing the work acknowledges it.
+    pub fn cancel(&self, id: Uuid) {
+        if let Some(task) = self.tasks.iter().find(|task| task.id() == id) {
+            task.handle.cancel();
+        }
+    }
+
+    /// Requests every running task to stop, such as when the app is closing.
+    pub fn cancel_running(&self) {
+        for task in self.running() {
+            task.handle.cancel();
+        }
+    }
+
+    /// Forget about all tasks that are no longer running.
+    pub fn clear_finished(&mut self) {
+        self.tasks.retain(|task| task.status.is_running());
+    }
+
+    pub fn handle_event(&mut self, event: TaskEvent) {
+        match event {
+            TaskEvent::Progress {
+                id,
+                completed,
+                total,
+            } => {
+                if let Some(task) = self.get_mut(id) {
+                    task.completed = completed;
+                    task.total = total;
+                }
+            }
+            TaskEvent::Failure { id, failure } => {
+                if let Some(task) = self.get_mut(id) {
+                    task.failures.push(failure);
+                }
+            }
+            TaskEvent::Finished { id, status } => {
+                if let Some(task) = self.get_mut(id) {
+                    task.status = status;
+                    task.finished_at = Some(Local::now());
+                }
+            }
+        }
+    }
+
+    fn get_mut(&mut self, id: Uuid) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|task| task.id() == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_task_events_update_task() {
+        let mut tasks = TaskContext::default();
+        let handle = tasks.register(TaskKind::Import, "Import".to_string(), 2);
+
+        tasks.handle_event(TaskEvent::Progress {
+            id: handle.id(),
+            completed: 1,
+            total: 2,
+        });
+        tasks.handle_event(TaskEvent::Failure {
+            id: handle.id(),
+            failure: TaskFailure::new(PathBuf::from("missing.mp3"), "File not found"),
+        });
+
+        let task = &tasks.tasks()[0];
+        assert_eq!(task.progress(), 0.5);
+        assert_eq!(task.failures.len(), 1);
+        assert_eq!(tasks.running().count(), 1);
+
+        tasks.handle_event(TaskEvent::Finished {
+            id: handle.id(),
+            status: TaskStatus::Completed,
+        });
+
+        assert_eq!(tasks.running().count(), 0);
+        assert!(tasks.tasks()[0].finished_at.is_some());
+
+        tasks.clear_finished();
+        assert!(tasks.tasks().is_empty());
+    }
+
+    #[test]
+    fn test_cancel_signals_handle() {
+        let mut tasks = TaskContext::default();
+        let handle = tasks.register(TaskKind::Rehash, "Rehash".to_string(), 0);
+
+        tasks.cancel(handle.id());
+
+        assert!(handle.is_cancelled());
+        // Still running until the worker acknowledges the cancellation
+        assert!(tasks.tasks()[0].status.is_running());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
+    components::ComponentTab,
     config::search::{SearchConfig, SearchMatchingStrategy},
-    context::UIPlaylistContext,
+    context::{UIPlaylistContext, UITagContext},
 };
 
 #[derive(Debug, Clone, Default)]
@@ -89,9 +90,46 @@ impl UISearchContext {
     }
 }
 
+/// A change to the layout of tabs, requested from outside of the dock area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutRequest {
+    /// Show a tab, reopening it if it was closed
+    Open(ComponentTab),
+    Close(ComponentTab),
+    /// Go back to the default layout
+    Reset,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UILayoutContext {
+    /// Tabs that are currently somewhere in the layout
+    open_tabs: Vec<ComponentTab>,
+    request: Option<LayoutRequest>,
+}
+
+impl UILayoutContext {
+    pub fn is_open(&self, tab: ComponentTab) -> bool {
+        self.open_tabs.contains(&tab)
+    }
+
+    pub fn set_open_tabs(&mut self, open_tabs: Vec<ComponentTab>) {
+        self.open_tabs = open_tabs;
+    }
+
+    pub fn request(&mut self, request: LayoutRequest) {
+        self.request = Some(request);
+    }
+
+    pub fn take_request(&mut self) -> Option<LayoutRequest> {
+        self.request.take()
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct UIContext {
     pub playlist: UIPlaylistContext,
+    pub tag: UITagContext,
     pub search: UISearchContext,
     pub visibility: UIVisibilityContext,
+    pub layout: UILayoutContext,
 }
@@ -1,4 +1,8 @@
-use std::{path::PathBuf, thread};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    thread::{self, JoinHandle},
+};
 
 use color_eyre::Result;
 use crossbeam::channel::{Receiver, Sender, unbounded};
@@ -6,24 +10,104 @@ use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tracing::{debug, error, info};
+use uuid::Uuid;
 
-use super::{local::get_database_storage_path, models::tracks::Track};
+use super::{
+    hash::hash_file,
+    import::{Import, ImportBatch, ProbedTrack},
+    local::get_database_storage_path,
+    models::tracks::{FileStamp, MissingTracks, Track, TrackRelocation},
+};
 use crate::{
-    database::models::playlists::{playlist::Playlist, playlist_tracks::PlaylistTrack},
+    database::models::{
+        play_history::PlayHistory,
+        playlists::{
+            playlist::Playlist, playlist_tracks::PlaylistTrack, smart_playlist::SmartPlaylist,
+        },
+        queue_tracks::QueueTrack,
+        tags::{tag::Tag, tag_tracks::TagTrack},
+        utils::errors::is_unique_violation,
+    },
+    files::{open::get_folder_tracks, watch::LibraryChanges},
+    tasks::{ProgressThrottle, TaskEvent, TaskFailure, TaskHandle, TaskStatus},
     utils::regex::RegexExtract,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum DatabaseCommand {
     /// All tracks to be added, the optional playlist, and an optional regex pattern
-    /// along with group position to extract the file name with.
-    InsertTracks(Vec<PathBuf>, Option<String>, Option<(String, usize)>),
+    /// along with group position to extract the file name with, reported on through the task.
+    /// The files are probed in parallel, and inserted in batches between other commands.
+    InsertTracks(
+        Vec<PathBuf>,
+        Option<String>,
+        Option<(String, usize)>,
+        TaskHandle,
+    ),
+    /// Insert tracks that have been probed off the database thread, as part of an import started with `InsertTracks`
+    InsertImportBatch(ImportBatch),
+    /// Recalculate the hash of every track in the library, reported on through the task
+    RehashTracks(TaskHandle),
+    /// Check that the file of every track in the library still exists and can be read, marking the tracks
+    /// that can't as invalid, and rehashing the ones that have been modified. Reported on through the task
+    ValidateTracks(TaskHandle),
+    /// Look through the folders for files with the same hash as tracks whose files are missing, reported on
+    /// through the task. Nothing is changed until the found relocations are applied
+    LocateMissingTracks(Vec<PathBuf>, TaskHandle),
+    /// Point tracks at files in other locations, keeping their playlists, tags and play history
+    RelocateTracks(Vec<TrackRelocation>),
+    /// Bring the library up to date with what changed in the watched folders, reported on through the task
+    SyncLibraryChanges(LibraryChanges, TaskHandle),
+    /// Compare the watched folders with the library as they are now, such as after being closed for a while,
+    /// and sync whatever is different. Reported on through the task
+    ReconcileLibraryFolders(Vec<PathBuf>, TaskHandle),
+    /// Mark tracks as valid again, ignoring what validation found wrong with them until it's next run
+    IgnoreInvalidTracks(Vec<Track>),
+    /// Record that a track has started playing
+    InsertPlayHistory(PlayHistory),
+    /// Update how long a played track was listened to, and if it was skipped
+    UpdatePlayHistory(PlayHistory),
+    /// Get up to this many of the most recently played tracks
+    QueryPlayHistory(usize),
+    /// Replace the stored play queue with these track IDs, in order
+    SetQueue(Vec<Uuid>),
+    /// Get the tracks in the stored play queue
+    QueryQueue,
     /// Get all tracks within a playlist, if provided then all tracks are returned
     QueryTracks(Option<Playlist>),
+    /// Remove tracks from the library by their IDs, along with every playlist and tag they're in.
+    /// Their files are left alone
+    DeleteTracks(Vec<Uuid>),
+    /// Remove tracks from a playlist by their IDs
+    DeletePlaylistTracks(Playlist, Vec<Uuid>),
+    /// Search the full text index of tracks with an FTS5 match expression
+    SearchTracks(String),
     /// Create a new playlist with the specified name
     InsertPlaylist(String),
+    /// Create a new smart playlist with the specified name, whose tracks are picked by a query
+    InsertSmartPlaylist(String, SmartPlaylist),
     /// Get all the playlists
     QueryPlaylists,
+    /// Give a playlist a new name
+    RenamePlaylist(Playlist, String),
+    /// Copy a playlist and its tracks under a new name
+    DuplicatePlaylist(Playlist, String),
+    /// Delete a playlist, leaving its tracks in the library
+    DeletePlaylist(Playlist),
+    /// Create a new tag with the specified name
+    InsertTag(String),
+    /// Get all the tags, along with the IDs of the tracks they're assigned to
+    QueryTags,
+    /// Give a tag a new name
+    RenameTag(Tag, String),
+    /// Delete a tag, removing it from all tracks
+    DeleteTag(Tag),
+    /// Assign a tag to tracks by their IDs
+    InsertTagTracks(Tag, Vec<Uuid>),
+    /// Remove a tag from tracks by their IDs
+    DeleteTagTracks(Tag, Vec<Uuid>),
+    /// Stop the database thread once every command sent before this one has been handled
+    Shutdown,
 }
 
 #[derive(Debug, Error)]
@@ -34,8 +118,11 @@ pub enum DatabaseError {
     #[error("Track {0} already exists in playlist {1}")]
     DuplicatePlaylistTrack(PathBuf, Playlist),
 
-    #[error("Playlist already exists")]
-    DuplicatePlaylist,
+    #[error("A playlist named {0} already exists")]
+    DuplicatePlaylist(String),
+
+    #[error("Tag {0} already exists")]
+    DuplicateTag(String),
 
     #[error("Database is unavailable")]
     DatabaseUnavailable,
@@ -44,94 +131,284 @@ pub enum DatabaseError {
     Unknown,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum DatabaseEvent {
-    InsertTrack(Track, Option<Playlist>),
+    /// Tracks that have been added to the library (and the playlist, if any), such as a batch of an import
+    InsertTracks(Vec<Track>, Option<Playlist>),
     QueryTracks(Vec<Track>, Option<Playlist>),
+    DeleteTracks(Vec<Uuid>),
+    DeletePlaylistTracks(Playlist, Vec<Uuid>),
+    /// The searched match expression, and the IDs of the matching tracks with the best matches first
+    SearchTracks(String, Vec<Uuid>),
     InsertPlaylist(Playlist),
     QueryPlaylists(Vec<Playlist>),
+    RenamePlaylist(Playlist),
+    /// The playlist that was copied, the copy, and the tracks of the copy
+    DuplicatePlaylist(Playlist, Playlist, Vec<Track>),
+    DeletePlaylist(Playlist),
+    InsertTag(Tag),
+    QueryTags(Vec<(Tag, Vec<Uuid>)>),
+    RenameTag(Tag),
+    DeleteTag(Tag),
+    InsertTagTracks(Tag, Vec<Uuid>),
+    DeleteTagTracks(Tag, Vec<Uuid>),
+    /// Tracks that have been modified in place, such as after being rehashed
+    UpdateTracks(Vec<Track>),
+    /// Where the missing tracks were found, waiting to be applied
+    LocateMissingTracks(Vec<TrackRelocation>),
+    QueryPlayHistory(Vec<(PlayHistory, Track)>),
+    QueryQueue(Vec<Track>),
+    Task(TaskEvent),
+}
+
+type DatabaseEventSender = Sender<Result<DatabaseEvent, DatabaseError>>;
+
+/// What became of a file that appeared in a watched folder
+enum SyncedTrack {
+    /// It's where a missing track moved to
+    Relocated(Track),
+    /// It's a new track
+    Inserted(Track),
 }
 
 #[derive(Debug)]
 pub struct Database;
 
 impl Database {
+    /// Spawns the database thread, which keeps running until it's sent [`DatabaseCommand::Shutdown`],
+    /// so it can be joined to make sure everything sent to it has been written.
     pub fn start() -> (
         Sender<DatabaseCommand>,
         Receiver<Result<DatabaseEvent, DatabaseError>>,
+        JoinHandle<()>,
     ) {
         let database_path = get_database_storage_path().expect("Failed to get DB path");
 
         let (command_tx, command_rx) = unbounded();
         let (event_tx, event_rx) = unbounded::<Result<DatabaseEvent, DatabaseError>>();
 
-        thread::spawn(move || {
-            let conn =
+        // Probed tracks of imports are sent back to the database thread to be inserted between other commands
+        let import_command_tx = command_tx.clone();
+
+        let database_thread = thread::spawn(move || {
+            let mut conn =
                 Connection::open(&database_path).expect("Failed to open database connection");
 
-            if let Err(err) = Database::create_tables(&conn) {
-                error!("Failed to create tables: {}", err);
+            if let Err(err) = Database::migrate(&mut conn, Some(&database_path)) {
+                error!("Failed to migrate database: {}", err);
                 std::process::exit(1);
             }
 
+            // Foreign keys are off by default, and are required for cascading deletes
+            if let Err(err) = conn.pragma_update(None, "foreign_keys", true) {
+                error!("Failed to enable foreign keys: {}", err);
+            }
+
             info!(
                 "Database thread running with connection at {:?}",
                 database_path
             );
 
+            // Imports whose files are being probed, by the ID of their task
+            let mut imports: HashMap<Uuid, Import> = HashMap::new();
+
             while let Ok(cmd) = command_rx.recv() {
                 match cmd {
-                    DatabaseCommand::InsertTracks(track_paths, playlist_name, regex_extract) => {
-                        let regex_extract =
-                            if let Some((pattern, group_position)) = regex_extract.clone() {
-                                RegexExtract::new(pattern, group_position).ok()
+                    DatabaseCommand::InsertTracks(
+                        track_paths,
+                        playlist_name,
+                        regex_extract,
+                        task,
+                    ) => {
+                        match Database::start_import(
+                            &conn,
+                            &import_command_tx,
+                            track_paths,
+                            playlist_name,
+                            regex_extract,
+                            task.clone(),
+                        ) {
+                            Ok(import) => {
+                                imports.insert(task.id(), import);
+                            }
+                            Err(err) => {
+                                error!("Error when inserting playlist: {}", err);
+                                Database::send_task_event(
+                                    &event_tx,
+                                    TaskEvent::Finished {
+                                        id: task.id(),
+                                        status: TaskStatus::Failed(err.to_string()),
+                                    },
+                                );
+                            }
+                        }
+                    }
+                    DatabaseCommand::InsertImportBatch(batch) => {
+                        let Some(import) = imports.get_mut(&batch.task_id) else {
+                            continue;
+                        };
+
+                        Database::insert_import_batch(&conn, &event_tx, import, batch.tracks);
+
+                        if batch.last {
+                            let status = if import.task.is_cancelled() {
+                                TaskStatus::Cancelled
                             } else {
-                                None
+                                TaskStatus::Completed
                             };
 
-                        let playlist = if let Some(playlist_name) = playlist_name {
-                            Playlist::create(&conn, playlist_name).unwrap_or_default()
-                        } else {
-                            None
+                            Database::send_task_event(
+                                &event_tx,
+                                TaskEvent::Finished {
+                                    id: batch.task_id,
+                                    status,
+                                },
+                            );
+                            imports.remove(&batch.task_id);
+                        }
+                    }
+                    DatabaseCommand::RehashTracks(task) => {
+                        let status = Database::rehash_tracks(&conn, &event_tx, &task);
+
+                        Database::send_task_event(
+                            &event_tx,
+                            TaskEvent::Finished {
+                                id: task.id(),
+                                status,
+                            },
+                        );
+                    }
+                    DatabaseCommand::ValidateTracks(task) => {
+                        let status = Database::validate_tracks(&conn, &event_tx, &task);
+
+                        Database::send_task_event(
+                            &event_tx,
+                            TaskEvent::Finished {
+                                id: task.id(),
+                                status,
+                            },
+                        );
+                    }
+                    DatabaseCommand::LocateMissingTracks(roots, task) => {
+                        let status =
+                            Database::locate_missing_tracks(&conn, &event_tx, roots, &task);
+
+                        Database::send_task_event(
+                            &event_tx,
+                            TaskEvent::Finished {
+                                id: task.id(),
+                                status,
+                            },
+                        );
+                    }
+                    DatabaseCommand::SyncLibraryChanges(changes, task) => {
+                        let status =
+                            Database::sync_library_changes(&conn, &event_tx, changes, &task);
+
+                        Database::send_task_event(
+                            &event_tx,
+                            TaskEvent::Finished {
+                                id: task.id(),
+                                status,
+                            },
+                        );
+                    }
+                    DatabaseCommand::ReconcileLibraryFolders(folders, task) => {
+                        let status = match Database::reconcile_library_folders(&conn, &folders) {
+                            Ok(changes) => {
+                                Database::sync_library_changes(&conn, &event_tx, changes, &task)
+                            }
+                            Err(err) => {
+                                error!("Error when reconciling library folders: {}", err);
+                                TaskStatus::Failed(err.to_string())
+                            }
                         };
 
-                        for track_path in track_paths {
-                            let track_result =
-                                Track::create(&conn, track_path.clone(), regex_extract.clone());
-
-                            let track = match track_result {
-                                Ok(Some(track)) => track,
-                                Ok(None) => {
-                                    let duplicate_error = if let Some(playlist) = playlist.as_ref()
-                                    {
-                                        DatabaseError::DuplicatePlaylistTrack(
-                                            track_path,
-                                            playlist.clone(),
-                                        )
-                                    } else {
-                                        DatabaseError::DuplicateTrack(track_path)
-                                    };
-                                    let _ = event_tx.send(Err(duplicate_error));
-                                    continue;
-                                }
-                                Err(err) => {
-                                    error!("Error when inserting track: {}", err);
-                                    continue;
-                                }
-                            };
+                        Database::send_task_event(
+                            &event_tx,
+                            TaskEvent::Finished {
+                                id: task.id(),
+                                status,
+                            },
+                        );
+                    }
+                    DatabaseCommand::RelocateTracks(relocations) => {
+                        let relocated_tracks: Vec<Track> = relocations
+                            .iter()
+                            .filter_map(|TrackRelocation { track, path }| {
+                                Track::relocate(&conn, track, path)
+                                    .inspect_err(|err| {
+                                        error!(
+                                            "Error when relocating track {:?} to {:?}: {}",
+                                            track.path, path, err
+                                        );
+                                    })
+                                    .ok()
+                            })
+                            .collect();
 
-                            let insert_track_event =
-                                DatabaseEvent::InsertTrack(track.clone(), playlist.clone());
-                            let _ = event_tx.send(Ok(insert_track_event));
+                        info!("Relocated {} tracks", relocated_tracks.len());
 
-                            if let Some(playlist_id) = playlist.as_ref().map(|playlist| playlist.id)
-                                && let Err(err) =
-                                    PlaylistTrack::create(&conn, playlist_id, track.id)
-                            {
-                                error!("Error when inserting track to playlist: {}", err);
+                        let _ = event_tx.send(Ok(DatabaseEvent::UpdateTracks(relocated_tracks)));
+                    }
+                    DatabaseCommand::IgnoreInvalidTracks(tracks) => {
+                        let updated_tracks: Vec<Track> = tracks
+                            .iter()
+                            .filter_map(|track| {
+                                Track::set_validation(
+                                    &conn,
+                                    track.id,
+                                    true,
+                                    track.hash.as_deref(),
+                                    track.file_stamp,
+                                )
+                                .inspect_err(|err| {
+                                    error!(
+                                        "Error when marking track {:?} as valid: {}",
+                                        track.path, err
+                                    );
+                                })
+                                .ok()
+                            })
+                            .collect();
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::UpdateTracks(updated_tracks)));
+                    }
+                    DatabaseCommand::InsertPlayHistory(play_history) => {
+                        if let Err(err) = PlayHistory::create(&conn, &play_history) {
+                            error!("Error when inserting play history: {}", err);
+                        }
+                    }
+                    DatabaseCommand::UpdatePlayHistory(play_history) => {
+                        if let Err(err) = PlayHistory::update(&conn, &play_history) {
+                            error!("Error when updating play history: {}", err);
+                        }
+                    }
+                    DatabaseCommand::QueryPlayHistory(limit) => {
+                        match PlayHistory::get_recent(&conn, limit) {
+                            Ok(play_history) => {
+                                let _ = event_tx
+                                    .send(Ok(DatabaseEvent::QueryPlayHistory(play_history)));
+                            }
+                            Err(err) => {
+                                error!("Something went wrong when querying play history: {}", err);
                             }
                         }
                     }
+                    DatabaseCommand::SetQueue(track_ids) => {
+                        if let Err(err) = QueueTrack::set_all(&mut conn, &track_ids) {
+                            error!("Error when storing the queue: {}", err);
+                        }
+                    }
+                    DatabaseCommand::QueryQueue => match QueueTrack::get_tracks(&conn) {
+                        Ok(tracks) => {
+                            let _ = event_tx.send(Ok(DatabaseEvent::QueryQueue(tracks)));
+                        }
+                        Err(err) => {
+                            error!("Something went wrong when querying the queue: {}", err);
+                        }
+                    },
                     DatabaseCommand::QueryTracks(playlist) => {
                         let result = if let Some(playlist) = playlist.as_ref() {
                             Playlist::get_tracks(&conn, playlist.id)
@@ -144,6 +421,44 @@ impl Database {
                             let _ = event_tx.send(Ok(query_tracks_event));
                         }
                     }
+                    DatabaseCommand::DeleteTracks(track_ids) => {
+                        if let Err(err) = Track::delete(&conn, &track_ids) {
+                            error!("Error when deleting tracks from the library: {}", err);
+                            continue;
+                        }
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::DeleteTracks(track_ids)));
+                    }
+                    DatabaseCommand::DeletePlaylistTracks(playlist, track_ids) => {
+                        let deleted: Vec<Uuid> = track_ids
+                            .into_iter()
+                            .filter(|track_id| {
+                                PlaylistTrack::delete(&conn, playlist.id, *track_id)
+                                    .inspect_err(|err| {
+                                        error!(
+                                            "Error when removing track from playlist {}: {}",
+                                            playlist.name, err
+                                        );
+                                    })
+                                    .is_ok()
+                            })
+                            .collect();
+
+                        let _ = event_tx
+                            .send(Ok(DatabaseEvent::DeletePlaylistTracks(playlist, deleted)));
+                    }
+                    DatabaseCommand::SearchTracks(full_text) => {
+                        match Track::search(&conn, &full_text) {
+                            Ok(track_ids) => {
+                                let search_tracks_event =
+                                    DatabaseEvent::SearchTracks(full_text, track_ids);
+                                let _ = event_tx.send(Ok(search_tracks_event));
+                            }
+                            Err(err) => {
+                                error!("Something went wrong when searching tracks: {}", err);
+                            }
+                        }
+                    }
                     DatabaseCommand::InsertPlaylist(playlist_name) => {
                         debug!("INSERT PLAYLIST CALLED");
                         let playlist_result = Playlist::create(&conn, playlist_name);
@@ -159,6 +474,74 @@ impl Database {
                             }
                         }
                     }
+                    DatabaseCommand::InsertSmartPlaylist(playlist_name, smart) => {
+                        match Playlist::create_smart(&conn, playlist_name.clone(), smart) {
+                            Ok(playlist) => {
+                                let _ = event_tx.send(Ok(DatabaseEvent::InsertPlaylist(playlist)));
+                            }
+                            Err(err) if is_unique_violation(&err) => {
+                                let _ = event_tx
+                                    .send(Err(DatabaseError::DuplicatePlaylist(playlist_name)));
+                            }
+                            Err(err) => {
+                                error!("Error when inserting smart playlist: {}", err);
+                            }
+                        }
+                    }
+                    DatabaseCommand::RenamePlaylist(playlist, new_name) => {
+                        match Playlist::rename(&conn, playlist.id, new_name.clone()) {
+                            Ok(renamed_playlist) => {
+                                let _ = event_tx
+                                    .send(Ok(DatabaseEvent::RenamePlaylist(renamed_playlist)));
+                            }
+                            Err(err) if is_unique_violation(&err) => {
+                                let _ =
+                                    event_tx.send(Err(DatabaseError::DuplicatePlaylist(new_name)));
+                            }
+                            Err(err) => {
+                                error!("Error when renaming playlist {}: {}", playlist.name, err);
+                            }
+                        }
+                    }
+                    DatabaseCommand::DuplicatePlaylist(playlist, name) => {
+                        let duplicate = match Playlist::duplicate(&conn, playlist.id, name.clone())
+                        {
+                            Ok(duplicate) => duplicate,
+                            Err(err) if is_unique_violation(&err) => {
+                                let _ = event_tx.send(Err(DatabaseError::DuplicatePlaylist(name)));
+                                continue;
+                            }
+                            Err(err) => {
+                                error!(
+                                    "Error when duplicating playlist {}: {}",
+                                    playlist.name, err
+                                );
+                                continue;
+                            }
+                        };
+
+                        // Smart playlists pick their own tracks
+                        let tracks = if duplicate.is_smart() {
+                            Vec::new()
+                        } else {
+                            Playlist::get_tracks(&conn, duplicate.id).unwrap_or_else(|err| {
+                                error!("Error when querying duplicated playlist tracks: {}", err);
+                                Vec::new()
+                            })
+                        };
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::DuplicatePlaylist(
+                            playlist, duplicate, tracks,
+                        )));
+                    }
+                    DatabaseCommand::DeletePlaylist(playlist) => {
+                        if let Err(err) = Playlist::delete(&conn, playlist.id) {
+                            error!("Error when deleting playlist {}: {}", playlist.name, err);
+                            continue;
+                        }
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::DeletePlaylist(playlist)));
+                    }
                     DatabaseCommand::QueryPlaylists => {
                         let result = Playlist::get_all(&conn);
 
@@ -170,10 +553,774 @@ impl Database {
                         let query_playlists_event = DatabaseEvent::QueryPlaylists(playlists);
                         let _ = event_tx.send(Ok(query_playlists_event));
                     }
+                    DatabaseCommand::InsertTag(tag_name) => {
+                        match Tag::create(&conn, tag_name.clone()) {
+                            Ok(tag) => {
+                                let _ = event_tx.send(Ok(DatabaseEvent::InsertTag(tag)));
+                            }
+                            Err(err) if is_unique_violation(&err) => {
+                                let _ = event_tx.send(Err(DatabaseError::DuplicateTag(tag_name)));
+                            }
+                            Err(err) => {
+                                error!("Error when inserting tag: {}", err);
+                            }
+                        }
+                    }
+                    DatabaseCommand::QueryTags => {
+                        let (Ok(tags), Ok(tag_tracks)) =
+                            (Tag::get_all(&conn), TagTrack::get_all(&conn))
+                        else {
+                            error!("Something went wrong when querying all tags");
+                            continue;
+                        };
+
+                        let mut tag_track_ids: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+                        for tag_track in tag_tracks {
+                            tag_track_ids
+                                .entry(tag_track.tag_id)
+                                .or_default()
+                                .push(tag_track.track_id);
+                        }
+
+                        let tags = tags
+                            .into_iter()
+                            .map(|tag| {
+                                let track_ids = tag_track_ids.remove(&tag.id).unwrap_or_default();
+                                (tag, track_ids)
+                            })
+                            .collect();
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::QueryTags(tags)));
+                    }
+                    DatabaseCommand::RenameTag(tag, new_name) => {
+                        match Tag::rename(&conn, tag.id, new_name.clone()) {
+                            Ok(renamed_tag) => {
+                                let _ = event_tx.send(Ok(DatabaseEvent::RenameTag(renamed_tag)));
+                            }
+                            Err(err) if is_unique_violation(&err) => {
+                                let _ = event_tx.send(Err(DatabaseError::DuplicateTag(new_name)));
+                            }
+                            Err(err) => {
+                                error!("Error when renaming tag {}: {}", tag.name, err);
+                            }
+                        }
+                    }
+                    DatabaseCommand::DeleteTag(tag) => {
+                        if let Err(err) = Tag::delete(&conn, tag.id) {
+                            error!("Error when deleting tag {}: {}", tag.name, err);
+                            continue;
+                        }
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::DeleteTag(tag)));
+                    }
+                    DatabaseCommand::InsertTagTracks(tag, track_ids) => {
+                        let inserted: Vec<Uuid> = track_ids
+                            .into_iter()
+                            .filter(|track_id| {
+                                TagTrack::create(&conn, tag.id, *track_id)
+                                    .inspect_err(|err| {
+                                        error!("Error when assigning tag {}: {}", tag.name, err);
+                                    })
+                                    .is_ok()
+                            })
+                            .collect();
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::InsertTagTracks(tag, inserted)));
+                    }
+                    DatabaseCommand::DeleteTagTracks(tag, track_ids) => {
+                        let deleted: Vec<Uuid> = track_ids
+                            .into_iter()
+                            .filter(|track_id| {
+                                TagTrack::delete(&conn, tag.id, *track_id)
+                                    .inspect_err(|err| {
+                                        error!("Error when unassigning tag {}: {}", tag.name, err);
+                                    })
+                                    .is_ok()
+                            })
+                            .collect();
+
+                        let _ = event_tx.send(Ok(DatabaseEvent::DeleteTagTracks(tag, deleted)));
+                    }
+                    DatabaseCommand::Shutdown => {
+                        info!("Database thread shutting down");
+                        break;
+                    }
+                }
+            }
+        });
+
+        (command_tx, event_rx, database_thread)
+    }
+
+    fn send_task_event(event_tx: &DatabaseEventSender, event: TaskEvent) {
+        let _ = event_tx.send(Ok(DatabaseEvent::Task(event)));
+    }
+
+    /// Starts hashing and probing the files of an import off the database thread, creating its playlist (if any)
+    /// first. The probed tracks come back as batches to be inserted with [`Database::insert_import_batch`].
+    fn start_import(
+        conn: &Connection,
+        command_tx: &Sender<DatabaseCommand>,
+        track_paths: Vec<PathBuf>,
+        playlist_name: Option<String>,
+        regex_extract: Option<(String, usize)>,
+        task: TaskHandle,
+    ) -> Result<Import> {
+        let regex_extract = if let Some((pattern, group_position)) = regex_extract {
+            RegexExtract::new(pattern, group_position).ok()
+        } else {
+            None
+        };
+
+        let playlist = if let Some(playlist_name) = playlist_name {
+            Playlist::create(conn, playlist_name)?
+        } else {
+            None
+        };
+
+        let command_tx = command_tx.clone();
+        let import = Import::start(track_paths, playlist, regex_extract, task, move |batch| {
+            let _ = command_tx.send(DatabaseCommand::InsertImportBatch(batch));
+        });
+
+        Ok(import)
+    }
+
+    /// Adds the probed tracks of a batch to the library (and the import's playlist, if any) in a single transaction,
+    /// unless the import has been cancelled. Files that cannot be added are reported as failures of the task,
+    /// instead of failing the batch.
+    fn insert_import_batch(
+        conn: &Connection,
+        event_tx: &DatabaseEventSender,
+        import: &mut Import,
+        probed_tracks: Vec<ProbedTrack>,
+    ) {
+        if import.task.is_cancelled() {
+            return;
+        }
+
+        let send_failure = |path: PathBuf, reason: String| {
+            Database::send_task_event(
+                event_tx,
+                TaskEvent::Failure {
+                    id: import.task.id(),
+                    failure: TaskFailure::new(path, reason),
+                },
+            );
+        };
+
+        let transaction = match conn.unchecked_transaction() {
+            Ok(transaction) => transaction,
+            Err(err) => {
+                error!("Error when starting to insert tracks: {}", err);
+                for ProbedTrack { path, .. } in probed_tracks {
+                    send_failure(path, err.to_string());
+                }
+                return;
+            }
+        };
+
+        let batch_size = probed_tracks.len();
+        let mut inserted_tracks = Vec::new();
+        let mut duplicates = Vec::new();
+
+        for ProbedTrack { path, track } in probed_tracks {
+            let track = match track {
+                Ok(track) => track,
+                Err(err) => {
+                    error!("Error when probing track {:?}: {}", path, err);
+                    send_failure(path, err);
+                    continue;
+                }
+            };
+
+            match Track::insert(&transaction, &track) {
+                Ok(Some(track)) => {
+                    if let Some(playlist) = import.playlist.as_ref() {
+                        match PlaylistTrack::create(&transaction, playlist.id, track.id) {
+                            Ok(()) => {}
+                            Err(err)
+                                if err
+                                    .downcast_ref::<rusqlite::Error>()
+                                    .is_some_and(is_unique_violation) =>
+                            {
+                                duplicates.push(DatabaseError::DuplicatePlaylistTrack(
+                                    path,
+                                    playlist.clone(),
+                                ));
+                            }
+                            Err(err) => {
+                                error!("Error when inserting track to playlist: {}", err);
+                                send_failure(path, err.to_string());
+                            }
+                        }
+                    }
+
+                    inserted_tracks.push(track);
+                }
+                Ok(None) => duplicates.push(DatabaseError::DuplicateTrack(path)),
+                Err(err) => {
+                    error!("Error when inserting track: {}", err);
+                    send_failure(path, err.to_string());
+                }
+            }
+        }
+
+        match transaction.commit() {
+            Ok(()) => {
+                if !inserted_tracks.is_empty() {
+                    let insert_tracks_event =
+                        DatabaseEvent::InsertTracks(inserted_tracks, import.playlist.clone());
+                    let _ = event_tx.send(Ok(insert_tracks_event));
+                }
+
+                for duplicate in duplicates {
+                    let _ = event_tx.send(Err(duplicate));
+                }
+            }
+            Err(err) => {
+                error!("Error when committing inserted tracks: {}", err);
+                for track in inserted_tracks {
+                    send_failure(track.path, err.to_string());
+                }
+            }
+        }
+
+        import.completed += batch_size;
+        if import.progress.is_due(import.completed, import.total) {
+            Database::send_task_event(
+                event_tx,
+                TaskEvent::Progress {
+                    id: import.task.id(),
+                    completed: import.completed,
+                    total: import.total,
+                },
+            );
+        }
+    }
+
+    /// Recalculates the hash of every track, sending back the ones that have changed.
+    fn rehash_tracks(
+        conn: &Connection,
+        event_tx: &DatabaseEventSender,
+        task: &TaskHandle,
+    ) -> TaskStatus {
+        let tracks = match Track::get_all(conn) {
+            Ok(tracks) => tracks,
+            Err(err) => {
+                error!("Something went wrong when querying all tracks: {}", err);
+                return TaskStatus::Failed(err.to_string());
+            }
+        };
+
+        let total = tracks.len();
+        let mut updated_tracks = Vec::new();
+        let mut status = TaskStatus::Completed;
+        let mut progress = ProgressThrottle::new();
+
+        for (index, track) in tracks.into_iter().enumerate() {
+            if task.is_cancelled() {
+                status = TaskStatus::Cancelled;
+                break;
+            }
+
+            match Track::rehash(conn, &track) {
+                Ok(Some(updated_track)) => updated_tracks.push(updated_track),
+                Ok(None) => {}
+                Err(err) => {
+                    error!("Error when rehashing track {:?}: {}", track.path, err);
+                    let failure = TaskFailure::new(track.path, err);
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Failure {
+                            id: task.id(),
+                            failure,
+                        },
+                    );
+                }
+            }
+
+            if progress.is_due(index + 1, total) {
+                Database::send_task_event(
+                    event_tx,
+                    TaskEvent::Progress {
+                        id: task.id(),
+                        completed: index + 1,
+                        total,
+                    },
+                );
+            }
+        }
+
+        // Tracks that were rehashed before cancelling have still been updated
+        if !updated_tracks.is_empty() {
+            let _ = event_tx.send(Ok(DatabaseEvent::UpdateTracks(updated_tracks)));
+        }
+
+        status
+    }
+
+    /// Hashes the audio files in the folders that aren't in the library yet, matching them to missing tracks,
+    /// and stopping early if the task is cancelled. Whatever was found is sent as a dry run to be applied later.
+    fn locate_missing_tracks(
+        conn: &Connection,
+        event_tx: &DatabaseEventSender,
+        roots: Vec<PathBuf>,
+        task: &TaskHandle,
+    ) -> TaskStatus {
+        let mut missing_tracks = match Track::get_all(conn) {
+            Ok(tracks) => MissingTracks::new(tracks),
+            Err(err) => {
+                error!("Something went wrong when querying all tracks: {}", err);
+                return TaskStatus::Failed(err.to_string());
+            }
+        };
+
+        let mut relocations = Vec::new();
+        let mut status = TaskStatus::Completed;
+
+        if !missing_tracks.is_empty() {
+            info!("Looking for {} missing tracks", missing_tracks.len());
+
+            let mut candidates: Vec<PathBuf> = roots
+                .iter()
+                .flat_map(|root| get_folder_tracks(root, true))
+                .filter(|path| !missing_tracks.is_known(path))
+                .collect();
+            candidates.sort();
+            candidates.dedup();
+
+            let total = candidates.len();
+            let mut progress = ProgressThrottle::new();
+
+            for (index, path) in candidates.into_iter().enumerate() {
+                if task.is_cancelled() {
+                    status = TaskStatus::Cancelled;
+                    break;
+                }
+
+                match hash_file(&path) {
+                    Ok(hash) => {
+                        if let Some(relocation) =
+                            missing_tracks.take_match(&path, &hash.to_string())
+                        {
+                            relocations.push(relocation);
+                        }
+                    }
+                    Err(err) => {
+                        error!("Error when hashing {:?}: {}", path, err);
+                        Database::send_task_event(
+                            event_tx,
+                            TaskEvent::Failure {
+                                id: task.id(),
+                                failure: TaskFailure::new(path, err),
+                            },
+                        );
+                    }
+                }
+
+                if progress.is_due(index + 1, total) {
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Progress {
+                            id: task.id(),
+                            completed: index + 1,
+                            total,
+                        },
+                    );
+                }
+
+                if missing_tracks.is_empty() {
+                    break;
+                }
+            }
+        }
+
+        // Whatever was found before cancelling can still be applied
+        let _ = event_tx.send(Ok(DatabaseEvent::LocateMissingTracks(relocations)));
+
+        status
+    }
+
+    /// Finds the audio files in the folders that aren't in the library, the tracks inside the folders
+    /// whose files have gone missing, and the ones whose files have changed since they were last read.
+    fn reconcile_library_folders(conn: &Connection, folders: &[PathBuf]) -> Result<LibraryChanges> {
+        let tracks = Track::get_all(conn)?;
+        let known_paths: HashSet<&PathBuf> = tracks.iter().map(|track| &track.path).collect();
+
+        let mut added: Vec<PathBuf> = folders
+            .iter()
+            .flat_map(|folder| get_folder_tracks(folder, true))
+            .filter(|path| !known_paths.contains(path))
+            .collect();
+        added.sort();
+        added.dedup();
+
+        let removed = tracks
+            .iter()
+            .filter(|track| {
+                track.valid
+                    && !track.path.exists()
+                    && folders.iter().any(|folder| track.path.starts_with(folder))
+            })
+            .map(|track| track.path.clone())
+            .collect();
+
+        let modified = tracks
+            .iter()
+            .filter(|track| {
+                track.valid
+                    && folders.iter().any(|folder| track.path.starts_with(folder))
+                    && track.file_stamp.is_some_and(|file_stamp| {
+                        FileStamp::read(&track.path).is_ok_and(|current| current != file_stamp)
+                    })
+            })
+            .map(|track| track.path.clone())
+            .collect();
+
+        Ok(LibraryChanges {
+            added,
+            removed,
+            renamed: Vec::new(),
+            modified,
+        })
+    }
+
+    /// Applies renames and removals, then goes through the modified and added files, stopping early if the task
+    /// is cancelled.
+    /// An added file with the same hash as a missing track is where that track moved to, so the track is relocated
+    /// instead of the file being imported as a new track.
+    fn sync_library_changes(
+        conn: &Connection,
+        event_tx: &DatabaseEventSender,
+        changes: LibraryChanges,
+        task: &TaskHandle,
+    ) -> TaskStatus {
+        let LibraryChanges {
+            added,
+            removed,
+            renamed,
+            modified,
+        } = changes;
+
+        let mut updated_tracks = Vec::new();
+        let mut inserted_tracks = Vec::new();
+
+        for (from, to) in renamed {
+            match Track::move_within(conn, &from, &to) {
+                Ok(moved_tracks) => updated_tracks.extend(moved_tracks),
+                Err(err) => {
+                    error!(
+                        "Error when moving tracks from {:?} to {:?}: {}",
+                        from, to, err
+                    );
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Failure {
+                            id: task.id(),
+                            failure: TaskFailure::new(from, err),
+                        },
+                    );
+                }
+            }
+        }
+
+        for path in removed {
+            match Track::invalidate_missing_within(conn, &path) {
+                Ok(invalidated_tracks) => updated_tracks.extend(invalidated_tracks),
+                Err(err) => {
+                    error!("Error when invalidating tracks in {:?}: {}", path, err);
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Failure {
+                            id: task.id(),
+                            failure: TaskFailure::new(path, err),
+                        },
+                    );
+                }
+            }
+        }
+
+        let mut missing_tracks = match Track::get_all(conn) {
+            Ok(tracks) => MissingTracks::new(tracks),
+            Err(err) => {
+                error!("Something went wrong when querying all tracks: {}", err);
+                return TaskStatus::Failed(err.to_string());
+            }
+        };
+
+        let added: Vec<PathBuf> = added
+            .into_iter()
+            .filter(|path| !missing_tracks.is_known(path))
+            .collect();
+        let total = modified.len() + added.len();
+        let mut completed = 0;
+        let mut progress = ProgressThrottle::new();
+        let mut status = TaskStatus::Completed;
+
+        for path in modified {
+            if task.is_cancelled() {
+                status = TaskStatus::Cancelled;
+                break;
+            }
+
+            match Database::sync_modified_file(conn, &path) {
+                Ok(refreshed_tracks) => updated_tracks.extend(refreshed_tracks),
+                Err(err) => {
+                    error!("Error when refreshing modified track {:?}: {}", path, err);
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Failure {
+                            id: task.id(),
+                            failure: TaskFailure::new(path, err),
+                        },
+                    );
+                }
+            }
+
+            completed += 1;
+            if progress.is_due(completed, total) {
+                Database::send_task_event(
+                    event_tx,
+                    TaskEvent::Progress {
+                        id: task.id(),
+                        completed,
+                        total,
+                    },
+                );
+            }
+        }
+
+        for path in added {
+            if task.is_cancelled() {
+                status = TaskStatus::Cancelled;
+                break;
+            }
+
+            match Database::sync_added_track(conn, &mut missing_tracks, path.clone()) {
+                Ok(Some(SyncedTrack::Relocated(track))) => updated_tracks.push(track),
+                Ok(Some(SyncedTrack::Inserted(track))) => inserted_tracks.push(track),
+                Ok(None) => {}
+                Err(err) => {
+                    error!("Error when syncing added track {:?}: {}", path, err);
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Failure {
+                            id: task.id(),
+                            failure: TaskFailure::new(path, err),
+                        },
+                    );
+                }
+            }
+
+            completed += 1;
+            if progress.is_due(completed, total) {
+                Database::send_task_event(
+                    event_tx,
+                    TaskEvent::Progress {
+                        id: task.id(),
+                        completed,
+                        total,
+                    },
+                );
+            }
+        }
+
+        if !updated_tracks.is_empty() {
+            let _ = event_tx.send(Ok(DatabaseEvent::UpdateTracks(updated_tracks)));
+        }
+        if !inserted_tracks.is_empty() {
+            let _ = event_tx.send(Ok(DatabaseEvent::InsertTracks(inserted_tracks, None)));
+        }
+
+        status
+    }
+
+    /// Reads the track for a modified file again, if the file is in the library and has actually changed.
+    fn sync_modified_file(conn: &Connection, path: &Path) -> Result<Vec<Track>> {
+        let mut refreshed_tracks = Vec::new();
+
+        for track in Track::get_within(conn, path)? {
+            if track.path == path
+                && let Some(refreshed_track) = Track::refresh(conn, &track)?
+            {
+                refreshed_tracks.push(refreshed_track);
+            }
+        }
+
+        Ok(refreshed_tracks)
+    }
+
+    /// Relocates the missing track the file belongs to, if any, otherwise imports it as a new track.
+    /// Files are only hashed up front when there are missing tracks they could belong to.
+    fn sync_added_track(
+        conn: &Connection,
+        missing_tracks: &mut MissingTracks,
+        path: PathBuf,
+    ) -> Result<Option<SyncedTrack>> {
+        if !missing_tracks.is_empty() {
+            let hash = hash_file(&path)?.to_string();
+
+            if let Some(TrackRelocation { track, path }) = missing_tracks.take_match(&path, &hash) {
+                let relocated_track = Track::relocate(conn, &track, &path)?;
+                return Ok(Some(SyncedTrack::Relocated(relocated_track)));
+            }
+        }
+
+        Ok(Track::create(conn, path, None)?.map(SyncedTrack::Inserted))
+    }
+
+    /// Validates the file of every track in the library, stopping early if the task is cancelled.
+    /// Each invalid track is reported as a failure of the task, and all updated tracks are sent at the end.
+    fn validate_tracks(
+        conn: &Connection,
+        event_tx: &DatabaseEventSender,
+        task: &TaskHandle,
+    ) -> TaskStatus {
+        let tracks = match Track::get_all(conn) {
+            Ok(tracks) => tracks,
+            Err(err) => {
+                error!("Something went wrong when querying all tracks: {}", err);
+                return TaskStatus::Failed(err.to_string());
+            }
+        };
+
+        let total = tracks.len();
+        let mut updated_tracks = Vec::new();
+        let mut status = TaskStatus::Completed;
+        let mut progress = ProgressThrottle::new();
+
+        for (index, track) in tracks.into_iter().enumerate() {
+            if task.is_cancelled() {
+                status = TaskStatus::Cancelled;
+                break;
+            }
+
+            match Track::validate(conn, &track) {
+                Ok(validation) => {
+                    if let Some(updated_track) = validation.updated_track {
+                        updated_tracks.push(updated_track);
+                    }
+
+                    if let Some(problem) = validation.problem {
+                        Database::send_task_event(
+                            event_tx,
+                            TaskEvent::Failure {
+                                id: task.id(),
+                                failure: TaskFailure::new(track.path, problem),
+                            },
+                        );
+                    }
+                }
+                Err(err) => {
+                    error!("Error when validating track {:?}: {}", track.path, err);
+                    Database::send_task_event(
+                        event_tx,
+                        TaskEvent::Failure {
+                            id: task.id(),
+                            failure: TaskFailure::new(track.path, err),
+                        },
+                    );
                 }
             }
+
+            if progress.is_due(index + 1, total) {
+                Database::send_task_event(
+                    event_tx,
+                    TaskEvent::Progress {
+                        id: task.id(),
+                        completed: index + 1,
+                        total,
+                    },
+                );
+            }
+        }
+
+        if !updated_tracks.is_empty() {
+            let _ = event_tx.send(Ok(DatabaseEvent::UpdateTracks(updated_tracks)));
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::{open_database, track};
+
+    fn probed_track(index: usize) -> ProbedTrack {
+        let track = Track {
+            hash: Some(index.to_string()),
+            ..track(&index.to_string())
+        };
+
+        ProbedTrack {
+            path: track.path.clone(),
+            track: Ok(track),
+        }
+    }
+
+    #[test]
+    fn test_import_batch_is_inserted_and_sent_at_once() {
+        let conn = open_database();
+
+        let playlist = Playlist::create(&conn, "Imported".to_string())
+            .unwrap()
+            .unwrap();
+        let task = TaskHandle::new();
+        let mut import = Import {
+            playlist: Some(playlist.clone()),
+            task: task.clone(),
+            total: 4,
+            completed: 0,
+            progress: ProgressThrottle::new(),
+        };
+
+        let mut probed_tracks: Vec<ProbedTrack> = (0..3).map(probed_track).collect();
+        probed_tracks.push(ProbedTrack {
+            path: PathBuf::from("/music/broken.mp3"),
+            track: Err("Unsupported format".to_string()),
         });
 
-        (command_tx, event_rx)
+        let (event_tx, event_rx) = unbounded();
+        Database::insert_import_batch(&conn, &event_tx, &mut import, probed_tracks);
+        drop(event_tx);
+
+        let mut track_paths: Vec<PathBuf> = Track::get_all(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|track| track.path)
+            .collect();
+        track_paths.sort();
+        assert_eq!(
+            track_paths,
+            vec![
+                PathBuf::from("/music/0.mp3"),
+                PathBuf::from("/music/1.mp3"),
+                PathBuf::from("/music/2.mp3"),
+            ]
+        );
+        assert_eq!(Playlist::get_tracks(&conn, playlist.id).unwrap().len(), 3);
+
+        let events: Vec<DatabaseEvent> = event_rx.iter().map(Result::unwrap).collect();
+        assert_eq!(events.len(), 3);
+
+        assert!(matches!(
+            &events[0],
+            DatabaseEvent::Task(TaskEvent::Failure { id, failure })
+                if *id == task.id() && failure.path == std::path::Path::new("/music/broken.mp3")
+        ));
+        assert!(matches!(
+            &events[1],
+            DatabaseEvent::InsertTracks(tracks, Some(event_playlist))
+                if tracks.len() == 3 && event_playlist.id == playlist.id
+        ));
+        assert!(matches!(
+            &events[2],
+            DatabaseEvent::Task(TaskEvent::Progress { id, completed: 4, total: 4 })
+                if *id == task.id()
+        ));
+        assert_eq!(import.completed, 4);
     }
 }
@@ -0,0 +1,193 @@
+use std::{
+    mem,
+    num::NonZeroUsize,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crossbeam::channel::{RecvTimeoutError, unbounded};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::{
+    database::models::{playlists::playlist::Playlist, tracks::Track},
+    tasks::{ProgressThrottle, TaskHandle},
+    utils::regex::RegexExtract,
+};
+
+/// Most probed tracks to insert in one transaction
+const IMPORT_BATCH_SIZE: usize = 256;
+/// Longest to hold on to probed tracks before inserting them, so progress keeps being shown for slow files
+const IMPORT_BATCH_INTERVAL: Duration = Duration::from_millis(250);
+/// How many files to probe at once when the number of CPUs isn't known
+const DEFAULT_IMPORT_WORKERS: usize = 4;
+
+/// A file that has been hashed and probed, ready to be inserted as a track
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ProbedTrack {
+    pub path: PathBuf,
+    /// The probed track, or why the file couldn't be probed
+    pub track: Result<Track, String>,
+}
+
+/// Probed tracks of an import, to be inserted together in a single transaction
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ImportBatch {
+    pub task_id: Uuid,
+    pub tracks: Vec<ProbedTrack>,
+    /// Whether every file has been probed, or the import was cancelled
+    pub last: bool,
+}
+
+/// An import whose files are being probed, kept by the database thread to insert its batches into
+#[derive(Debug)]
+pub struct Import {
+    pub playlist: Option<Playlist>,
+    pub task: TaskHandle,
+    pub total: usize,
+    pub completed: usize,
+    pub progress: ProgressThrottle,
+}
+
+impl Import {
+    /// Starts hashing and probing the files on a pool of worker threads, one per CPU.
+    /// The probed tracks are collected into batches by another thread and handed to `send_batch`,
+    /// finishing with a batch marked as the last, even if there were no files.
+    pub fn start(
+        paths: Vec<PathBuf>,
+        playlist: Option<Playlist>,
+        regex_extract: Option<RegexExtract>,
+        task: TaskHandle,
+        send_batch: impl Fn(ImportBatch) + Send + 'static,
+    ) -> Self {
+        let total = paths.len();
+
+        let (path_tx, path_rx) = unbounded();
+        for path in paths {
+            let _ = path_tx.send(path);
+        }
+        drop(path_tx);
+
+        let workers = thread::available_parallelism()
+            .map_or(DEFAULT_IMPORT_WORKERS, NonZeroUsize::get)
+            .min(total)
+            .max(1);
+        debug!("Importing {} files with {} workers", total, workers);
+
+        let (probed_tx, probed_rx) = unbounded();
+
+        for _ in 0..workers {
+            let path_rx = path_rx.clone();
+            let probed_tx = probed_tx.clone();
+            let regex_extract = regex_extract.clone();
+            let task = task.clone();
+
+            thread::spawn(move || {
+                for path in path_rx {
+                    if task.is_cancelled() {
+                        break;
+                    }
+
+                    let track =
+                        Track::probe(&path, regex_extract.clone()).map_err(|err| err.to_string());
+
+                    if probed_tx.send(ProbedTrack { path, track }).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // The batches end once every worker has finished and dropped its sender
+        drop(probed_tx);
+
+        let task_id = task.id();
+        thread::spawn(move || {
+            let mut tracks = Vec::new();
+            let mut last_sent = Instant::now();
+
+            loop {
+                match probed_rx.recv_timeout(IMPORT_BATCH_INTERVAL) {
+                    Ok(probed_track) => tracks.push(probed_track),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+
+                if tracks.len() >= IMPORT_BATCH_SIZE
+                    || (!tracks.is_empty() && last_sent.elapsed() >= IMPORT_BATCH_INTERVAL)
+                {
+                    send_batch(ImportBatch {
+                        task_id,
+                        tracks: mem::take(&mut tracks),
+                        last: false,
+                    });
+                    last_sent = Instant::now();
+                }
+            }
+
+            send_batch(ImportBatch {
+                task_id,
+                tracks,
+                last: true,
+            });
+        });
+
+        Self {
+            playlist,
+            task,
+            total,
+            completed: 0,
+            progress: ProgressThrottle::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_batches_every_file() {
+        let paths: Vec<PathBuf> = (0..10)
+            .map(|index| PathBuf::from(format!("/nonexistent/{index}.mp3")))
+            .collect();
+
+        let (batch_tx, batch_rx) = unbounded();
+        let task = TaskHandle::new();
+        let import = Import::start(paths.clone(), None, None, task.clone(), move |batch| {
+            let _ = batch_tx.send(batch);
+        });
+        assert_eq!(import.total, 10);
+
+        let mut probed_paths = Vec::new();
+        for batch in batch_rx.iter() {
+            assert_eq!(batch.task_id, task.id());
+
+            for probed_track in batch.tracks {
+                // The files don't exist, so they can't be probed
+                assert!(probed_track.track.is_err());
+                probed_paths.push(probed_track.path);
+            }
+
+            if batch.last {
+                break;
+            }
+        }
+
+        probed_paths.sort();
+        let mut expected_paths = paths;
+        expected_paths.sort();
+        assert_eq!(probed_paths, expected_paths);
+
+        // Even without any files, there's a last batch to finish the import with
+        let (batch_tx, batch_rx) = unbounded();
+        Import::start(Vec::new(), None, None, TaskHandle::new(), move |batch| {
+            let _ = batch_tx.send(batch);
+        });
+        let batch = batch_rx.recv().unwrap();
+        assert!(batch.last);
+        assert!(batch.tracks.is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::{fs, path::Path};
+
+use color_eyre::{
+    Result,
+    eyre::{Context, bail},
+};
+use rusqlite::Connection;
+use tracing::{debug, info};
+
+use super::{
+    connection::Database,
+    tables::{
+        PLAY_HISTORY_TABLES, QUEUE_TABLES, SMART_PLAYLIST_COLUMNS, TABLES,
+        TRACK_FILE_STAMP_COLUMNS, TRACK_SEARCH_TABLES, TRACK_TAG_COLUMNS,
+    },
+};
+
+/// A single, ordered upgrade step of the database schema.
+///
+/// Each migration is applied inside its own transaction, and the `user_version` pragma is
+/// bumped to [`Self::version`] within that same transaction so a failed step leaves the
+/// database at the previous version.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub statements: &'static [&'static str],
+}
+
+/// All schema migrations, in the order they must be applied.
+/// Versions are expected to be contiguous, starting from 1.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Initial tracks, playlists and tags tables",
+        statements: &TABLES,
+    },
+    Migration {
+        version: 2,
+        description: "Embedded tag metadata columns on tracks",
+        statements: &TRACK_TAG_COLUMNS,
+    },
+    Migration {
+        version: 3,
+        description: "Play history table",
+        statements: &PLAY_HISTORY_TABLES,
+    },
+    Migration {
+        version: 4,
+        description: "Play queue table",
+        statements: &QUEUE_TABLES,
+    },
+    Migration {
+        version: 5,
+        description: "Smart playlist query columns on playlists",
+        statements: &SMART_PLAYLIST_COLUMNS,
+    },
+    Migration {
+        version: 6,
+        description: "Full text search index over tracks and their tags",
+        statements: &TRACK_SEARCH_TABLES,
+    },
+    Migration {
+        version: 7,
+        description: "File size and modification time columns on tracks",
+        statements: &TRACK_FILE_STAMP_COLUMNS,
+    },
+];
+
+/// The schema version this build of the application expects.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+impl Database {
+    /// Reads the schema version stored in the `user_version` pragma.
+    pub(crate) fn schema_version(conn: &Connection) -> Result<u32> {
+        let version = conn
+            .pragma_query_value(None, "user_version", |row| row.get::<_, u32>(0))
+            .context("Failed to read database user version")?;
+
+        Ok(version)
+    }
+
+    /// Applies every pending migration to the database.
+    ///
+    /// If the database was created by a newer version of the application, nothing is changed and
+    /// an error is returned. When there are pending migrations for a database that already has
+    /// tables, a copy of it is written next to `database_path` before anything is applied.
+    pub(crate) fn migrate(conn: &mut Connection, database_path: Option<&Path>) -> Result<()> {
+        let current_version = Self::schema_version(conn)?;
+        let latest_version = latest_version();
+
+        if current_version > latest_version {
+            bail!(
+                "Database schema version {current_version} is newer than the latest supported version {latest_version}, refusing to open it"
+            );
+        }
+
+        let pending: Vec<&Migration> = MIGRATIONS
+            .iter()
+            .filter(|migration| migration.version > current_version)
+            .collect();
+
+        if pending.is_empty() {
+            debug!("Database schema is up to date at version {current_version}");
+            return Ok(());
+        }
+
+        if let Some(database_path) = database_path
+            && Self::has_tables(conn)?
+        {
+            Self::backup(conn, database_path, current_version)?;
+        }
+
+        for migration in pending {
+            info!(
+                "Applying database migration {}: {}",
+                migration.version, migration.description
+            );
+
+            let tx = conn.transaction()?;
+            for statement in migration.statements {
+                tx.execute_batch(statement).context(format!(
+                    "Failed to apply database migration {}",
+                    migration.version
+                ))?;
+            }
+            tx.pragma_update(None, "user_version", migration.version)?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    fn has_tables(conn: &Connection) -> Result<bool> {
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'",
+            [],
+            |row| row.get::<_, u32>(0),
+        )?;
+
+        Ok(count > 0)
+    }
+
+    /// Writes a consistent copy of the database to `<database>.v<version>.bak`,
+    /// replacing any previous backup made from the same version.
+    fn backup(conn: &Connection, database_path: &Path, version: u32) -> Result<()> {
+        let mut backup_path = database_path.as_os_str().to_owned();
+        backup_path.push(format!(".v{version}.bak"));
+        let backup_path = Path::new(&backup_path);
+
+        if backup_path.exists() {
+            fs::remove_file(backup_path).context("Failed to remove previous database backup")?;
+        }
+
+        conn.execute(
+            "VACUUM INTO ?1",
+            [backup_path.to_string_lossy().into_owned()],
+        )
+        .context(format!("Failed to back up database to {backup_path:?}"))?;
+
+        info!("Backed up database to {:?} before migrating", backup_path);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils::temp_path;
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        Database::migrate(&mut conn, None).unwrap();
+
+        assert_eq!(Database::schema_version(&conn).unwrap(), latest_version());
+        assert!(Database::has_tables(&conn).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+
+        Database::migrate(&mut conn, None).unwrap();
+        Database::migrate(&mut conn, None).unwrap();
+
+        assert_eq!(Database::schema_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn test_migrate_refuses_newer_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        assert!(Database::migrate(&mut conn, None).is_err());
+        assert!(!Database::has_tables(&conn).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_backs_up_existing_database() {
+        let database_path = temp_path("migrate").with_extension("sqlite");
+        let backup_path = database_path.with_extension("sqlite.v0.bak");
+
+        let mut conn = Connection::open(&database_path).unwrap();
+        conn.execute("CREATE TABLE legacy (id TEXT PRIMARY KEY)", [])
+            .unwrap();
+
+        Database::migrate(&mut conn, Some(&database_path)).unwrap();
+
+        let backup_exists = backup_path.exists();
+
+        let _ = fs::remove_file(&database_path);
+        let _ = fs::remove_file(&backup_path);
+
+        assert!(backup_exists);
+    }
+}
@@ -1,5 +1,10 @@
 pub mod connection;
 pub mod hash;
+pub mod import;
 pub mod local;
+pub mod migrations;
 pub mod models;
 pub mod tables;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
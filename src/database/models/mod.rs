@@ -1,4 +1,7 @@
+pub mod play_history;
 pub mod playlists;
+pub mod queue_tracks;
+pub mod sort;
 pub mod tags;
 pub mod tracks;
-mod utils;
+pub(crate) mod utils;
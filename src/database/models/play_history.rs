@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use color_eyre::{Result, eyre::Context};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::database::models::{
+    tracks::Track,
+    utils::parse::{parse_date, parse_uuid},
+};
+
+/// A single time a track was played, and how it ended.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct PlayHistory {
+    pub id: Uuid,
+    pub track_id: Uuid,
+    /// The playlist the track was autoplaying from, if any
+    pub playlist_id: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub listened_secs: f64,
+    /// If another track was started before this one finished
+    pub skipped: bool,
+}
+
+impl PlayHistory {
+    pub fn new(track_id: Uuid, playlist_id: Option<Uuid>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            track_id,
+            playlist_id,
+            started_at: Utc::now(),
+            listened_secs: 0.0,
+            skipped: false,
+        }
+    }
+
+    pub fn create(conn: &Connection, play_history: &PlayHistory) -> rusqlite::Result<()> {
+        let sql = "
+            INSERT INTO play_history (id, track_id, playlist_id, started_at, listened_secs, skipped)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+        ";
+
+        conn.execute(
+            sql,
+            params![
+                play_history.id.to_string(),
+                play_history.track_id.to_string(),
+                play_history.playlist_id.map(|id| id.to_string()),
+                play_history.started_at,
+                play_history.listened_secs,
+                play_history.skipped,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Updates how long the track was listened to, and if it was skipped.
+    pub fn update(conn: &Connection, play_history: &PlayHistory) -> rusqlite::Result<()> {
+        let sql = "
+            UPDATE play_history
+            SET listened_secs = ?2, skipped = ?3
+            WHERE id = ?1
+        ";
+
+        conn.execute(
+            sql,
+            params![
+                play_history.id.to_string(),
+                play_history.listened_secs,
+                play_history.skipped,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Gets the most recent plays along with their tracks, newest first.
+    pub fn get_recent(conn: &Connection, limit: usize) -> Result<Vec<(PlayHistory, Track)>> {
+        // History columns are aliased, as they would otherwise clash with the track columns
+        let sql = "
+            SELECT h.id AS history_id, h.playlist_id, h.started_at, h.listened_secs, h.skipped,
+                t.id, t.path, t.name, t.hash, t.duration_secs, t.valid,
+                t.title, t.artist, t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
+                t.file_size, t.file_modified_at, t.created_at, t.updated_at
+            FROM play_history h
+            JOIN tracks t ON t.id = h.track_id
+            ORDER BY h.started_at DESC
+            LIMIT ?1
+        ";
+
+        let mut stmt = conn
+            .prepare(sql)
+            .context("Failed to prepare query for select recent play history")?;
+
+        let play_history: Vec<(PlayHistory, Track)> = stmt
+            .query_map(params![limit], |row| {
+                let track = Track::try_from(row)?;
+
+                let play_history = PlayHistory {
+                    id: parse_uuid(row.get::<_, String>("history_id")?)?,
+                    track_id: track.id,
+                    playlist_id: row
+                        .get::<_, Option<String>>("playlist_id")?
+                        .map(parse_uuid)
+                        .transpose()?,
+                    started_at: parse_date(row.get::<_, String>("started_at")?)?,
+                    listened_secs: row.get::<_, f64>("listened_secs")?,
+                    skipped: row.get("skipped")?,
+                };
+
+                Ok((play_history, track))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        debug!(
+            "Found {} entries from play history query",
+            play_history.len()
+        );
+
+        Ok(play_history)
+    }
+}
@@ -1,2 +1,3 @@
 pub mod playlist;
 pub mod playlist_tracks;
+pub mod smart_playlist;
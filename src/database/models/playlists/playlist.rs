@@ -8,6 +8,7 @@ use tracing::{debug, error};
 use uuid::Uuid;
 
 use crate::database::models::{
+    playlists::smart_playlist::SmartPlaylist,
     tracks::Track,
     utils::parse::{parse_date, parse_uuid},
 };
@@ -16,6 +17,8 @@ use crate::database::models::{
 pub struct Playlist {
     pub id: Uuid,
     pub name: String,
+    /// What tracks belong to this playlist, if they're picked by a query instead of being added to it
+    pub smart: Option<SmartPlaylist>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -25,6 +28,7 @@ impl Default for Playlist {
         Self {
             id: Uuid::new_v4(),
             name: String::default(),
+            smart: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -43,12 +47,14 @@ impl TryFrom<&Row<'_>> for Playlist {
     fn try_from(row: &Row) -> Result<Self, Self::Error> {
         let id = parse_uuid(row.get::<_, String>("id")?)?;
         let name = row.get::<_, String>("name")?;
+        let smart = SmartPlaylist::from_row(row)?;
         let created_at = parse_date(row.get::<_, String>("created_at")?)?;
         let updated_at = parse_date(row.get::<_, String>("updated_at")?)?;
 
         let playlist = Playlist {
             id,
             name,
+            smart,
             created_at,
             updated_at,
         };
@@ -62,7 +68,7 @@ impl Playlist {
     // TODO: Return an enum that says if the playlist already exists or not
     pub fn create(conn: &Connection, name: String) -> Result<Option<Playlist>> {
         let sql = "
-            INSERT INTO playlists
+            INSERT INTO playlists (id, name, created_at, updated_at)
             VALUES (?1, ?2, ?3, ?4)
             ON CONFLICT(name) DO UPDATE SET
                 name = excluded.name
@@ -95,6 +101,50 @@ impl Playlist {
         Ok(Some(returned))
     }
 
+    /// Creates a new smart playlist, failing with a unique constraint violation if the name is already taken.
+    pub fn create_smart(
+        conn: &Connection,
+        name: String,
+        smart: SmartPlaylist,
+    ) -> rusqlite::Result<Playlist> {
+        let sql = "
+            INSERT INTO playlists (
+                id, name, smart_query, smart_sort_column, smart_sort_order, smart_limit,
+                created_at, updated_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            RETURNING *
+        ";
+
+        let playlist = Playlist {
+            name,
+            ..Default::default()
+        };
+
+        let returned = conn.query_row(
+            sql,
+            params![
+                playlist.id.to_string(),
+                playlist.name,
+                smart.query,
+                smart.sort.column.key(),
+                smart.sort.order.key(),
+                smart.limit.map(|limit| limit as i64),
+                playlist.created_at,
+                playlist.updated_at,
+            ],
+            |row| Playlist::try_from(row),
+        )?;
+
+        debug!("Inserted smart playlist in database: {}", returned.name);
+
+        Ok(returned)
+    }
+
+    pub fn is_smart(&self) -> bool {
+        self.smart.is_some()
+    }
+
     pub fn get_all(conn: &Connection) -> Result<Vec<Self>> {
         let query = "SELECT * FROM playlists";
 
@@ -114,6 +164,61 @@ impl Playlist {
         Ok(playlists)
     }
 
+    /// Renames a playlist, failing with a unique constraint violation if the name is already taken.
+    pub fn rename(conn: &Connection, id: Uuid, name: String) -> rusqlite::Result<Playlist> {
+        let sql = "
+            UPDATE playlists
+            SET name = ?2, updated_at = ?3
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        conn.query_row(sql, params![id.to_string(), name, Utc::now()], |row| {
+            Playlist::try_from(row)
+        })
+    }
+
+    /// Copies a playlist under a new name, along with its tracks (or the query picking them),
+    /// failing with a unique constraint violation if the name is already taken.
+    pub fn duplicate(conn: &Connection, id: Uuid, name: String) -> rusqlite::Result<Playlist> {
+        let insert_playlist_sql = "
+            INSERT INTO playlists (
+                id, name, smart_query, smart_sort_column, smart_sort_order, smart_limit,
+                created_at, updated_at
+            )
+            SELECT ?2, ?3, smart_query, smart_sort_column, smart_sort_order, smart_limit, ?4, ?4
+            FROM playlists
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        let insert_tracks_sql = "
+            INSERT INTO playlist_tracks (playlist_id, track_id, created_at)
+            SELECT ?2, track_id, created_at
+            FROM playlist_tracks
+            WHERE playlist_id = ?1
+        ";
+
+        let tx = conn.unchecked_transaction()?;
+
+        let duplicate = tx.query_row(
+            insert_playlist_sql,
+            params![id.to_string(), Uuid::new_v4().to_string(), name, Utc::now()],
+            |row| Playlist::try_from(row),
+        )?;
+
+        tx.execute(
+            insert_tracks_sql,
+            params![id.to_string(), duplicate.id.to_string()],
+        )?;
+
+        tx.commit()?;
+
+        debug!("Duplicated playlist into {}", duplicate.name);
+
+        Ok(duplicate)
+    }
+
     pub fn delete(conn: &Connection, id: Uuid) -> rusqlite::Result<()> {
         let sql = "
             DELETE FROM playlists
@@ -127,7 +232,9 @@ impl Playlist {
 
     pub fn get_tracks(conn: &Connection, id: Uuid) -> Result<Vec<Track>> {
         let sql = "
-            SELECT t.id, t.path, t.name, t.hash, t.duration_secs, t.valid, t.created_at, t.updated_at
+            SELECT t.id, t.path, t.name, t.hash, t.duration_secs, t.valid,
+                t.title, t.artist, t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
+                t.file_size, t.file_modified_at, t.created_at, t.updated_at
             FROM tracks t
             JOIN playlist_tracks pt ON t.id = pt.track_id
             WHERE pt.playlist_id = ?1;
@@ -149,3 +256,86 @@ impl Playlist {
         Ok(playlist_tracks)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        models::{
+            playlists::playlist_tracks::PlaylistTrack,
+            sort::{SortOrder, TrackColumn, TrackSort},
+            utils::errors::is_unique_violation,
+        },
+        test_utils::{insert_track, open_database, track},
+    };
+
+    #[test]
+    fn test_create_smart_playlist() {
+        let conn = open_database();
+
+        let smart = SmartPlaylist::new(
+            "artist:\"daft punk\"".to_string(),
+            TrackSort {
+                column: TrackColumn::DateAdded,
+                order: SortOrder::Descending,
+            },
+            Some(50),
+        );
+
+        Playlist::create(&conn, "Regular".to_string()).unwrap();
+        let created = Playlist::create_smart(&conn, "Recent".to_string(), smart.clone()).unwrap();
+        assert_eq!(created.smart, Some(smart));
+
+        assert!(
+            Playlist::create_smart(&conn, "Recent".to_string(), created.smart.clone().unwrap())
+                .is_err()
+        );
+
+        let playlists = Playlist::get_all(&conn).unwrap();
+        assert_eq!(
+            playlists
+                .iter()
+                .filter(|playlist| playlist.is_smart())
+                .count(),
+            1
+        );
+        assert!(playlists.contains(&created));
+    }
+
+    #[test]
+    fn test_rename_duplicate_and_delete_playlist() {
+        let conn = open_database();
+
+        let track = track("a");
+        insert_track(&conn, &track);
+
+        let playlist = Playlist::create(&conn, "Mix".to_string()).unwrap().unwrap();
+        PlaylistTrack::create(&conn, playlist.id, track.id).unwrap();
+        Playlist::create(&conn, "Taken".to_string()).unwrap();
+
+        let renamed = Playlist::rename(&conn, playlist.id, "Road trip".to_string()).unwrap();
+        assert_eq!(renamed.id, playlist.id);
+        assert_eq!(renamed.name, "Road trip");
+        assert!(is_unique_violation(
+            &Playlist::rename(&conn, playlist.id, "Taken".to_string()).unwrap_err()
+        ));
+
+        let duplicate =
+            Playlist::duplicate(&conn, playlist.id, "Road trip (copy)".to_string()).unwrap();
+        assert_ne!(duplicate.id, playlist.id);
+        assert_eq!(Playlist::get_tracks(&conn, duplicate.id).unwrap().len(), 1);
+        assert!(is_unique_violation(
+            &Playlist::duplicate(&conn, playlist.id, "Taken".to_string()).unwrap_err()
+        ));
+
+        Playlist::delete(&conn, playlist.id).unwrap();
+        let mut names: Vec<String> = Playlist::get_all(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|playlist| playlist.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Road trip (copy)", "Taken"]);
+        assert_eq!(Playlist::get_tracks(&conn, duplicate.id).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    sync::OnceLock,
+};
+
+use rusqlite::{Row, types::Type};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    database::models::{
+        sort::{SortOrder, TrackColumn, TrackSort},
+        tracks::Track,
+    },
+    utils::query::{Query, QueryEnv},
+};
+
+/// The filter a smart playlist's tracks are picked with, instead of having tracks added to it.
+/// Its tracks are every track in the library matching the query, sorted and then cut down to the limit (if any).
+/// Compared and hashed by its query, sort and limit only, as the parsed query follows from them.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SmartPlaylist {
+    /// Search query the tracks have to match, in the same syntax as searching the track table
+    pub query: String,
+    pub sort: TrackSort,
+    /// How many tracks to keep after sorting, such as the 50 most recently added
+    pub limit: Option<usize>,
+    /// The query once it has been parsed, or [`None`] if it can't be
+    #[serde(skip)]
+    parsed_query: OnceLock<Option<Query>>,
+}
+
+impl PartialEq for SmartPlaylist {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for SmartPlaylist {}
+
+impl Hash for SmartPlaylist {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for SmartPlaylist {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmartPlaylist {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl SmartPlaylist {
+    pub fn new(query: String, sort: TrackSort, limit: Option<usize>) -> Self {
+        Self {
+            query,
+            sort,
+            limit,
+            parsed_query: OnceLock::new(),
+        }
+    }
+
+    fn key(&self) -> (&str, TrackSort, Option<usize>) {
+        (&self.query, self.sort, self.limit)
+    }
+
+    /// The parsed query, which is only parsed the first time the playlist is evaluated.
+    fn parsed_query(&self) -> Option<&Query> {
+        self.parsed_query
+            .get_or_init(|| {
+                Query::parse(&self.query)
+                    .inspect_err(|err| {
+                        warn!("Smart playlist query {:?} is invalid: {}", self.query, err);
+                    })
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// Reads the smart playlist columns of a playlist row, which are all null for regular playlists.
+    pub fn from_row(row: &Row) -> rusqlite::Result<Option<Self>> {
+        let Some(query) = row.get::<_, Option<String>>("smart_query")? else {
+            return Ok(None);
+        };
+
+        let invalid_key = |column: &str, key: String| {
+            rusqlite::Error::FromSqlConversionFailure(
+                0,
+                Type::Text,
+                format!("Unknown {column} {key}").into(),
+            )
+        };
+
+        let sort_column = row.get::<_, String>("smart_sort_column")?;
+        let column = TrackColumn::from_key(&sort_column)
+            .ok_or_else(|| invalid_key("sort column", sort_column))?;
+
+        let sort_order = row.get::<_, String>("smart_sort_order")?;
+        let order = SortOrder::from_key(&sort_order)
+            .ok_or_else(|| invalid_key("sort order", sort_order))?;
+
+        let limit = row
+            .get::<_, Option<i64>>("smart_limit")?
+            .map(|limit| limit.max(0) as usize);
+
+        Ok(Some(Self::new(query, TrackSort { column, order }, limit)))
+    }
+
+    /// Picks the tracks of this playlist out of all the passed through tracks.
+    /// Nothing is picked if the query can't be parsed.
+    pub fn evaluate(&self, tracks: &[Track], env: &QueryEnv) -> Vec<Track> {
+        let Some(query) = self.parsed_query() else {
+            return Vec::new();
+        };
+
+        let mut tracks: Vec<Track> = tracks
+            .iter()
+            .filter(|track| query.matches(track, env))
+            .cloned()
+            .collect();
+
+        self.sort.sort(&mut tracks);
+
+        if let Some(limit) = self.limit {
+            tracks.truncate(limit);
+        }
+
+        tracks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::config::search::MatcherFn;
+
+    #[test]
+    fn test_query_is_parsed_once() {
+        let matcher: MatcherFn = Box::new(|_, _| true);
+        let env = QueryEnv {
+            matcher: &matcher,
+            track_tags: HashMap::new(),
+            now: Utc::now(),
+        };
+        let tracks = vec![Track {
+            duration_secs: 90.0,
+            ..Default::default()
+        }];
+
+        let smart = SmartPlaylist::new("duration>1:00".to_string(), TrackSort::default(), None);
+        assert_eq!(smart.evaluate(&tracks, &env).len(), 1);
+        assert!(smart.parsed_query.get().is_some_and(Option::is_some));
+
+        // The parsed query doesn't make it a different playlist
+        assert_eq!(
+            smart,
+            SmartPlaylist::new("duration>1:00".to_string(), TrackSort::default(), None)
+        );
+
+        let invalid = SmartPlaylist::new("duration>soon".to_string(), TrackSort::default(), None);
+        assert!(invalid.evaluate(&tracks, &env).is_empty());
+        assert_eq!(invalid.parsed_query.get(), Some(&None));
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::Utc;
+use color_eyre::{Result, eyre::Context};
+use rusqlite::{Connection, params};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::database::models::tracks::Track;
+
+/// The persisted play queue, where each track is stored along with its position in the queue.
+pub struct QueueTrack;
+
+impl QueueTrack {
+    /// Replaces the whole stored queue with the passed through track IDs, in order.
+    pub fn set_all(conn: &mut Connection, track_ids: &[Uuid]) -> Result<()> {
+        let sql = "
+            INSERT INTO queue_tracks (position, track_id, created_at)
+            VALUES (?1, ?2, ?3)
+        ";
+
+        let tx = conn.transaction()?;
+
+        tx.execute("DELETE FROM queue_tracks", [])?;
+
+        {
+            let mut stmt = tx.prepare(sql)?;
+            let created_at = Utc::now();
+
+            for (position, track_id) in track_ids.iter().enumerate() {
+                stmt.execute(params![position, track_id.to_string(), created_at])?;
+            }
+        }
+
+        tx.commit()?;
+
+        debug!("Stored {} track(s) in the queue", track_ids.len());
+
+        Ok(())
+    }
+
+    /// Gets the tracks in the stored queue, in order.
+    pub fn get_tracks(conn: &Connection) -> Result<Vec<Track>> {
+        let sql = "
+            SELECT t.id, t.path, t.name, t.hash, t.duration_secs, t.valid,
+                t.title, t.artist, t.album_artist, t.album, t.track_number, t.disc_number, t.year, t.genre,
+                t.file_size, t.file_modified_at, t.created_at, t.updated_at
+            FROM tracks t
+            JOIN queue_tracks qt ON t.id = qt.track_id
+            ORDER BY qt.position;
+        ";
+
+        let mut stmt = conn
+            .prepare(sql)
+            .context("Failed to prepare query for select all tracks from queue")?;
+
+        let queue_tracks: Vec<Track> = stmt
+            .query_map([], |row| Track::try_from(row))?
+            .collect::<Result<_, _>>()?;
+
+        debug!(
+            "Found {} track(s) from queue/tracks query",
+            queue_tracks.len()
+        );
+
+        Ok(queue_tracks)
+    }
+}
@@ -0,0 +1,282 @@
+use std::{cmp::Ordering, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::database::models::tracks::Track;
+
+/// A column that can be shown in the track table.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackColumn {
+    /// Position of the track in the table, which can't be sorted by
+    Index,
+    Name,
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    TrackNumber,
+    DiscNumber,
+    Year,
+    Genre,
+    Duration,
+    DateAdded,
+    Path,
+    Hash,
+    Valid,
+}
+
+impl TrackColumn {
+    /// Every column, in the order they're listed in the column chooser.
+    pub const ALL: [TrackColumn; 15] = [
+        TrackColumn::Index,
+        TrackColumn::Name,
+        TrackColumn::Title,
+        TrackColumn::Artist,
+        TrackColumn::AlbumArtist,
+        TrackColumn::Album,
+        TrackColumn::TrackNumber,
+        TrackColumn::DiscNumber,
+        TrackColumn::Year,
+        TrackColumn::Genre,
+        TrackColumn::Duration,
+        TrackColumn::DateAdded,
+        TrackColumn::Path,
+        TrackColumn::Hash,
+        TrackColumn::Valid,
+    ];
+
+    pub fn sortable(&self) -> bool {
+        *self != TrackColumn::Index
+    }
+
+    /// Name of the column when stored in the database.
+    pub fn key(&self) -> &'static str {
+        match self {
+            TrackColumn::Index => "index",
+            TrackColumn::Name => "name",
+            TrackColumn::Title => "title",
+            TrackColumn::Artist => "artist",
+            TrackColumn::AlbumArtist => "album_artist",
+            TrackColumn::Album => "album",
+            TrackColumn::TrackNumber => "track_number",
+            TrackColumn::DiscNumber => "disc_number",
+            TrackColumn::Year => "year",
+            TrackColumn::Genre => "genre",
+            TrackColumn::Duration => "duration",
+            TrackColumn::DateAdded => "date_added",
+            TrackColumn::Path => "path",
+            TrackColumn::Hash => "hash",
+            TrackColumn::Valid => "valid",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|column| column.key() == key)
+    }
+}
+
+impl fmt::Display for TrackColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrackColumn::Index => write!(f, "Index"),
+            TrackColumn::Name => write!(f, "Track"),
+            TrackColumn::Title => write!(f, "Title"),
+            TrackColumn::Artist => write!(f, "Artist"),
+            TrackColumn::AlbumArtist => write!(f, "Album artist"),
+            TrackColumn::Album => write!(f, "Album"),
+            TrackColumn::TrackNumber => write!(f, "Track number"),
+            TrackColumn::DiscNumber => write!(f, "Disc number"),
+            TrackColumn::Year => write!(f, "Year"),
+            TrackColumn::Genre => write!(f, "Genre"),
+            TrackColumn::Duration => write!(f, "Duration"),
+            TrackColumn::DateAdded => write!(f, "Date added"),
+            TrackColumn::Path => write!(f, "Path"),
+            TrackColumn::Hash => write!(f, "Hash"),
+            TrackColumn::Valid => write!(f, "Valid"),
+        }
+    }
+}
+
+#[derive(
+    Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn reverse(&self) -> Self {
+        match self {
+            SortOrder::Ascending => SortOrder::Descending,
+            SortOrder::Descending => SortOrder::Ascending,
+        }
+    }
+
+    /// Name of the order when stored in the database.
+    pub fn key(&self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "ascending",
+            SortOrder::Descending => "descending",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        [SortOrder::Ascending, SortOrder::Descending]
+            .into_iter()
+            .find(|order| order.key() == key)
+    }
+}
+
+impl fmt::Display for SortOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SortOrder::Ascending => write!(f, "Ascending"),
+            SortOrder::Descending => write!(f, "Descending"),
+        }
+    }
+}
+
+/// How the tracks of a playlist are ordered, both in the track table and during autoplay.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackSort {
+    pub column: TrackColumn,
+    pub order: SortOrder,
+}
+
+impl Default for TrackSort {
+    fn default() -> Self {
+        Self {
+            column: TrackColumn::Path,
+            order: SortOrder::Ascending,
+        }
+    }
+}
+
+impl TrackSort {
+    /// The sort after a column's header has been clicked, flipping the order if the tracks are
+    /// already sorted by that column.
+    pub fn toggled(&self, column: TrackColumn) -> Self {
+        if self.column == column {
+            Self {
+                column,
+                order: self.order.reverse(),
+            }
+        } else {
+            Self {
+                column,
+                order: SortOrder::Ascending,
+            }
+        }
+    }
+
+    /// Compares two tracks by the sorted column, falling back to their paths so that tracks with
+    /// equal values always end up in the same order. Tracks without a tag come before the ones with it.
+    pub fn compare(&self, a: &Track, b: &Track) -> Ordering {
+        let ordering = match self.column {
+            TrackColumn::Name => compare_text(&a.name, &b.name),
+            TrackColumn::Title => compare_tag(a.title.as_deref(), b.title.as_deref()),
+            TrackColumn::Artist => compare_tag(a.artist.as_deref(), b.artist.as_deref()),
+            TrackColumn::AlbumArtist => {
+                compare_tag(a.album_artist.as_deref(), b.album_artist.as_deref())
+            }
+            TrackColumn::Album => compare_tag(a.album.as_deref(), b.album.as_deref()),
+            TrackColumn::TrackNumber => a.track_number.cmp(&b.track_number),
+            TrackColumn::DiscNumber => a.disc_number.cmp(&b.disc_number),
+            TrackColumn::Year => a.year.cmp(&b.year),
+            TrackColumn::Genre => compare_tag(a.genre.as_deref(), b.genre.as_deref()),
+            TrackColumn::Duration => a.duration_secs.total_cmp(&b.duration_secs),
+            TrackColumn::DateAdded => a.created_at.cmp(&b.created_at),
+            TrackColumn::Hash => a.hash.cmp(&b.hash),
+            TrackColumn::Valid => a.valid.cmp(&b.valid),
+            TrackColumn::Index | TrackColumn::Path => Ordering::Equal,
+        }
+        .then_with(|| a.path.cmp(&b.path));
+
+        match self.order {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    }
+
+    pub fn sort(&self, tracks: &mut [Track]) {
+        tracks.sort_by(|a, b| self.compare(a, b));
+    }
+}
+
+/// Compares text regardless of case.
+fn compare_text(a: &str, b: &str) -> Ordering {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .cmp(b.chars().flat_map(char::to_lowercase))
+}
+
+fn compare_tag(a: Option<&str>, b: Option<&str>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare_text(a, b),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_utils;
+
+    fn track(name: &str, duration_secs: f64) -> Track {
+        Track {
+            duration_secs,
+            ..test_utils::track(name)
+        }
+    }
+
+    fn names(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|track| track.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_toggles_order() {
+        let mut tracks = vec![track("b", 30.0), track("C", 10.0), track("a", 20.0)];
+
+        let sort = TrackSort::default().toggled(TrackColumn::Name);
+        sort.sort(&mut tracks);
+        assert_eq!(names(&tracks), ["a", "b", "C"]);
+
+        let sort = sort.toggled(TrackColumn::Name);
+        assert_eq!(sort.order, SortOrder::Descending);
+        sort.sort(&mut tracks);
+        assert_eq!(names(&tracks), ["C", "b", "a"]);
+
+        let sort = sort.toggled(TrackColumn::Duration);
+        assert_eq!(sort.order, SortOrder::Ascending);
+        sort.sort(&mut tracks);
+        assert_eq!(names(&tracks), ["C", "a", "b"]);
+    }
+
+    #[test]
+    fn test_sort_by_tags() {
+        let tagged = |name: &str, artist: Option<&str>, track_number: Option<u32>| Track {
+            artist: artist.map(str::to_string),
+            track_number,
+            ..track(name, 0.0)
+        };
+        let mut tracks = vec![
+            tagged("a", Some("beta"), Some(2)),
+            tagged("b", None, None),
+            tagged("c", Some("Alpha"), Some(1)),
+        ];
+
+        let sort = TrackSort::default().toggled(TrackColumn::Artist);
+        sort.sort(&mut tracks);
+        assert_eq!(names(&tracks), ["b", "c", "a"]);
+
+        let sort = sort
+            .toggled(TrackColumn::TrackNumber)
+            .toggled(TrackColumn::TrackNumber);
+        sort.sort(&mut tracks);
+        assert_eq!(names(&tracks), ["a", "c", "b"]);
+    }
+}
@@ -1 +1,147 @@
+use std::fmt;
 
+use chrono::{DateTime, Utc};
+use color_eyre::{Result, eyre::Context};
+use rusqlite::{Connection, Row, params};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::database::models::utils::parse::{parse_date, parse_uuid};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub struct Tag {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Default for Tag {
+    fn default() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: String::default(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl TryFrom<&Row<'_>> for Tag {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        let id = parse_uuid(row.get::<_, String>("id")?)?;
+        let name = row.get::<_, String>("name")?;
+        let created_at = parse_date(row.get::<_, String>("created_at")?)?;
+        let updated_at = parse_date(row.get::<_, String>("updated_at")?)?;
+
+        let tag = Tag {
+            id,
+            name,
+            created_at,
+            updated_at,
+        };
+
+        Ok(tag)
+    }
+}
+
+impl Tag {
+    /// Creates a new tag, failing with a unique constraint violation if the name is already taken.
+    pub fn create(conn: &Connection, name: String) -> rusqlite::Result<Tag> {
+        let sql = "
+            INSERT INTO tags
+            VALUES (?1, ?2, ?3, ?4)
+            RETURNING *
+        ";
+
+        let tag = Tag {
+            name,
+            ..Default::default()
+        };
+
+        let returned = conn.query_row(
+            sql,
+            params![tag.id.to_string(), tag.name, tag.created_at, tag.updated_at],
+            |row| Tag::try_from(row),
+        )?;
+
+        debug!("Inserted tag in database: {}", returned.name);
+
+        Ok(returned)
+    }
+
+    pub fn get_all(conn: &Connection) -> Result<Vec<Self>> {
+        let query = "SELECT * FROM tags";
+
+        let mut stmt = conn
+            .prepare(query)
+            .context("Failed to prepare query for select all from tags")?;
+
+        let tags: Vec<Tag> = stmt
+            .query_map([], |row| Tag::try_from(row))?
+            .collect::<Result<_, _>>()?;
+
+        debug!("Found {} tag(s) from tags table query", tags.len());
+
+        Ok(tags)
+    }
+
+    /// Renames a tag, failing with a unique constraint violation if the name is already taken.
+    pub fn rename(conn: &Connection, id: Uuid, name: String) -> rusqlite::Result<Tag> {
+        let sql = "
+            UPDATE tags
+            SET name = ?2, updated_at = ?3
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        conn.query_row(sql, params![id.to_string(), name, Utc::now()], |row| {
+            Tag::try_from(row)
+        })
+    }
+
+    pub fn delete(conn: &Connection, id: Uuid) -> rusqlite::Result<()> {
+        let sql = "
+            DELETE FROM tags
+            WHERE id = ?1
+        ";
+
+        conn.execute(sql, params![id.to_string()])?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{models::utils::errors::is_unique_violation, test_utils::open_database};
+
+    #[test]
+    fn test_tag_names_are_unique() {
+        let conn = open_database();
+
+        let live = Tag::create(&conn, "live".to_string()).unwrap();
+        let remix = Tag::create(&conn, "remix".to_string()).unwrap();
+
+        assert!(is_unique_violation(
+            &Tag::create(&conn, "live".to_string()).unwrap_err()
+        ));
+        assert!(is_unique_violation(
+            &Tag::rename(&conn, remix.id, "live".to_string()).unwrap_err()
+        ));
+
+        let renamed = Tag::rename(&conn, live.id, "concert".to_string()).unwrap();
+        assert_eq!(renamed.id, live.id);
+        assert_eq!(Tag::get_all(&conn).unwrap().len(), 2);
+    }
+}
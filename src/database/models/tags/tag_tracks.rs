@@ -1 +1,76 @@
+use chrono::{DateTime, Utc};
+use color_eyre::Result;
+use rusqlite::{Connection, Row, params};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
+use crate::database::models::utils::parse::{parse_date, parse_uuid};
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TagTrack {
+    pub tag_id: Uuid,
+    pub track_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<&Row<'_>> for TagTrack {
+    type Error = rusqlite::Error;
+
+    fn try_from(row: &Row) -> Result<Self, Self::Error> {
+        let tag_id = parse_uuid(row.get("tag_id")?)?;
+        let track_id = parse_uuid(row.get("track_id")?)?;
+        let created_at = parse_date(row.get::<_, String>("created_at")?)?;
+
+        let tag_track = TagTrack {
+            tag_id,
+            track_id,
+            created_at,
+        };
+
+        Ok(tag_track)
+    }
+}
+
+impl TagTrack {
+    /// Assigns a tag to a track, doing nothing if the track already has the tag.
+    pub fn create(conn: &Connection, tag_id: Uuid, track_id: Uuid) -> Result<()> {
+        let sql = "
+            INSERT INTO track_tags (tag_id, track_id, created_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT (tag_id, track_id) DO NOTHING
+        ";
+
+        conn.execute(
+            sql,
+            params![tag_id.to_string(), track_id.to_string(), Utc::now()],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_all(conn: &Connection) -> Result<Vec<TagTrack>> {
+        let sql = "
+            SELECT tag_id, track_id, created_at
+            FROM track_tags
+        ";
+
+        let mut stmt = conn.prepare(sql)?;
+
+        let tag_tracks = stmt
+            .query_map([], |row| TagTrack::try_from(row))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(tag_tracks)
+    }
+
+    pub fn delete(conn: &Connection, tag_id: Uuid, track_id: Uuid) -> Result<()> {
+        let sql = "
+            DELETE FROM track_tags
+            WHERE tag_id = ?1 AND track_id = ?2
+        ";
+
+        conn.execute(sql, params![tag_id.to_string(), track_id.to_string()])?;
+
+        Ok(())
+    }
+}
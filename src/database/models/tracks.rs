@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{MAIN_SEPARATOR, Path, PathBuf},
+};
 
 use chrono::{DateTime, Utc};
 use color_eyre::{
@@ -10,11 +14,14 @@ use serde::{Deserialize, Serialize};
 use tracing::debug;
 use uuid::Uuid;
 
-use super::utils::parse::{parse_date, parse_uuid};
+use super::utils::{
+    errors::is_unique_violation,
+    parse::{parse_date, parse_uuid},
+};
 use crate::{
     database::hash::hash_file,
     files::open::get_file_name,
-    playback::track_metadata::{extract_track_duration, extract_track_metadata},
+    playback::track_metadata::{TrackMetadata, extract_track_duration, extract_track_metadata},
     utils::regex::RegexExtract,
 };
 
@@ -26,10 +33,114 @@ pub struct Track {
     pub hash: Option<String>,
     pub duration_secs: f64,
     pub valid: bool,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+    /// The size and modification time of the file when it was last hashed
+    pub file_stamp: Option<FileStamp>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+/// The size and last modification time of a file, which tell if it has changed since its hash
+/// was calculated without having to hash it again.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct FileStamp {
+    pub size: u64,
+    pub modified_at: DateTime<Utc>,
+}
+
+impl FileStamp {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+
+        Ok(Self {
+            size: metadata.len(),
+            modified_at: metadata.modified()?.into(),
+        })
+    }
+}
+
+/// What was found when validating a track's file
+#[derive(Debug)]
+pub struct TrackValidation {
+    /// The track, if its validity, hash or file stamp had to be updated
+    pub updated_track: Option<Track>,
+    /// Why the track is invalid, if it is
+    pub problem: Option<String>,
+}
+
+/// A track, and the file it should point at instead
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TrackRelocation {
+    pub track: Track,
+    pub path: PathBuf,
+}
+
+/// The tracks whose files are missing, waiting to be matched by hash to the files they were moved to
+#[derive(Debug, Default)]
+pub struct MissingTracks {
+    by_hash: HashMap<String, Vec<Track>>,
+    /// Files already in the library, which can't be where a missing track moved to
+    known_paths: HashSet<PathBuf>,
+}
+
+impl MissingTracks {
+    /// Picks out the tracks of the library whose files don't exist anymore.
+    /// Tracks without a hash can't be recognized, so they're left out.
+    pub fn new(tracks: Vec<Track>) -> Self {
+        let mut missing_tracks = MissingTracks::default();
+
+        for track in tracks {
+            missing_tracks.known_paths.insert(track.path.clone());
+
+            if track.path.exists() {
+                continue;
+            }
+
+            if let Some(hash) = track.hash.clone() {
+                missing_tracks.by_hash.entry(hash).or_default().push(track);
+            }
+        }
+
+        missing_tracks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_hash.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_hash.values().map(Vec::len).sum()
+    }
+
+    /// Whether the file already belongs to a track, so doesn't need hashing.
+    pub fn is_known(&self, path: &Path) -> bool {
+        self.known_paths.contains(path)
+    }
+
+    /// Matches the file to a missing track with the same hash, if any are left.
+    /// Each file is only matched once, so copies of a track are spread between copies of its file.
+    pub fn take_match(&mut self, path: &Path, hash: &str) -> Option<TrackRelocation> {
+        let tracks = self.by_hash.get_mut(hash)?;
+        let track = tracks.pop()?;
+
+        if tracks.is_empty() {
+            self.by_hash.remove(hash);
+        }
+
+        Some(TrackRelocation {
+            track,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
 impl Default for Track {
     fn default() -> Self {
         Self {
@@ -39,6 +150,15 @@ impl Default for Track {
             hash: None,
             duration_secs: 0.0,
             valid: true,
+            title: None,
+            artist: None,
+            album_artist: None,
+            album: None,
+            track_number: None,
+            disc_number: None,
+            year: None,
+            genre: None,
+            file_stamp: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -55,6 +175,22 @@ impl TryFrom<&Row<'_>> for Track {
         let hash = row.get("hash")?;
         let duration_secs = row.get::<_, f64>("duration_secs")?;
         let valid = row.get("valid")?;
+        let title = row.get("title")?;
+        let artist = row.get("artist")?;
+        let album_artist = row.get("album_artist")?;
+        let album = row.get("album")?;
+        let track_number = row.get("track_number")?;
+        let disc_number = row.get("disc_number")?;
+        let year = row.get("year")?;
+        let genre = row.get("genre")?;
+        let file_size = row.get::<_, Option<u64>>("file_size")?;
+        let file_modified_at = row
+            .get::<_, Option<String>>("file_modified_at")?
+            .map(parse_date)
+            .transpose()?;
+        let file_stamp = file_size
+            .zip(file_modified_at)
+            .map(|(size, modified_at)| FileStamp { size, modified_at });
         let created_at = parse_date(row.get::<_, String>("created_at")?)?;
         let updated_at = parse_date(row.get::<_, String>("updated_at")?)?;
 
@@ -65,6 +201,15 @@ impl TryFrom<&Row<'_>> for Track {
             hash,
             duration_secs,
             valid,
+            title,
+            artist,
+            album_artist,
+            album,
+            track_number,
+            disc_number,
+            year,
+            genre,
+            file_stamp,
             created_at,
             updated_at,
         };
@@ -74,46 +219,105 @@ impl TryFrom<&Row<'_>> for Track {
 }
 
 impl Track {
+    /// If this track directly follows the previous one on the same album and disc,
+    /// which is usually meant to be heard without any gap or crossfade in between.
+    pub fn continues_album(&self, previous: &Track) -> bool {
+        let (Some(album), Some(track_number), Some(previous_track_number)) =
+            (&self.album, self.track_number, previous.track_number)
+        else {
+            return false;
+        };
+
+        previous.album.as_ref() == Some(album)
+            && previous.album_artist == self.album_artist
+            && previous.disc_number == self.disc_number
+            && previous_track_number + 1 == track_number
+    }
+
     /// Creates a track, or returns the one it conflicts with on hash and path.
-    /// When creating a new track, the hash of the file is generated, along with a new UUID.
-    /// All other attributes of the track are generated with defaults.
+    /// See [`Track::probe`] for how the file is read, and [`Track::insert`] for how it's inserted.
     // TODO: Return an enum to tell if a new track has been created, or the old one was returned
     pub fn create(
         conn: &Connection,
         path: PathBuf,
         regex_extract: Option<RegexExtract>,
     ) -> Result<Option<Track>> {
-        let sql = "
-            INSERT INTO Tracks
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
-            ON CONFLICT (hash, path) DO UPDATE SET
-                hash = excluded.hash
-            RETURNING *
-        ";
+        let track = Track::probe(&path, regex_extract)?;
 
-        let hash = hash_file(&path)?.to_string();
+        Ok(Track::insert(conn, &track)?)
+    }
 
-        let track_metadata = extract_track_metadata(&path)?;
-        let duration_secs = extract_track_duration(track_metadata)
+    /// Reads a file into a track without touching the database, so files can be probed in parallel.
+    /// The hash of the file is generated, along with a new UUID.
+    /// Tags embedded in the file are read into the track.
+    /// The name of the track is its title tag, falling back to the file name
+    /// (optionally narrowed down by a regex extract) when the file isn't tagged.
+    /// All other attributes of the track are generated with defaults.
+    pub fn probe(path: &Path, regex_extract: Option<RegexExtract>) -> Result<Track> {
+        // Read before hashing, so a file modified in the meantime is hashed again when validated
+        let file_stamp = FileStamp::read(path)
+            .context(format!("Failed to read file metadata of track {path:?}"))?;
+        let hash = hash_file(path)?.to_string();
+
+        let TrackMetadata { codec_params, tags } = extract_track_metadata(path)?;
+        let duration_secs = extract_track_duration(codec_params)
             .context(format!("Failed to get duration from track {path:?}"))?
             .as_secs_f64();
 
-        let file_name = get_file_name(path.clone())
+        let file_name = get_file_name(path.to_path_buf())
             .context(format!("Failed to get track file name from {path:?}"))?;
 
-        let name = regex_extract
-            .and_then(|extract| extract.extract_group(&file_name))
-            .unwrap_or(file_name);
+        let name = tags.title.clone().unwrap_or_else(|| {
+            regex_extract
+                .and_then(|extract| extract.extract_group(&file_name))
+                .unwrap_or(file_name)
+        });
 
-        let track = Track {
-            path: path.clone(),
+        Ok(Track {
+            path: path.to_path_buf(),
             name,
             hash: Some(hash),
             duration_secs,
+            title: tags.title,
+            artist: tags.artist,
+            album_artist: tags.album_artist,
+            album: tags.album,
+            track_number: tags.track_number,
+            disc_number: tags.disc_number,
+            year: tags.year,
+            genre: tags.genre,
+            file_stamp: Some(file_stamp),
             ..Default::default()
-        };
+        })
+    }
 
-        let mut stmt = conn.prepare(sql)?;
+    /// Inserts a probed track, or returns the one it conflicts with on hash and path,
+    /// refreshing its tags from the probed track.
+    pub fn insert(conn: &Connection, track: &Track) -> rusqlite::Result<Option<Track>> {
+        let sql = "
+            INSERT INTO tracks (
+                id, path, name, hash, duration_secs, valid,
+                title, artist, album_artist, album, track_number, disc_number, year, genre,
+                file_size, file_modified_at, created_at, updated_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
+            ON CONFLICT (hash, path) DO UPDATE SET
+                hash = excluded.hash,
+                file_size = excluded.file_size,
+                file_modified_at = excluded.file_modified_at,
+                title = excluded.title,
+                artist = excluded.artist,
+                album_artist = excluded.album_artist,
+                album = excluded.album,
+                track_number = excluded.track_number,
+                disc_number = excluded.disc_number,
+                year = excluded.year,
+                genre = excluded.genre
+            RETURNING *
+        ";
+
+        // Cached, as tracks are inserted many at a time when importing
+        let mut stmt = conn.prepare_cached(sql)?;
 
         let mut rows = stmt.query(params![
             track.id.to_string(),
@@ -122,6 +326,16 @@ impl Track {
             track.hash,
             track.duration_secs,
             track.valid,
+            track.title,
+            track.artist,
+            track.album_artist,
+            track.album,
+            track.track_number,
+            track.disc_number,
+            track.year,
+            track.genre,
+            track.file_stamp.map(|file_stamp| file_stamp.size),
+            track.file_stamp.map(|file_stamp| file_stamp.modified_at),
             track.created_at,
             track.updated_at,
         ])?;
@@ -134,14 +348,318 @@ impl Track {
             );
             Ok(Some(returned_track))
         } else {
-            debug!("No track returned for path: {:?}", path);
+            debug!("No track returned for path: {:?}", track.path);
             Ok(None)
         }
     }
 
+    /// Recalculates the hash of a track's file, only updating the track if it or the file stamp has changed.
+    /// Returns the updated track, or `None` if the stored hash was already up to date.
+    pub fn rehash(conn: &Connection, track: &Track) -> Result<Option<Track>> {
+        let sql = "
+            UPDATE tracks
+            SET hash = ?2, file_size = ?3, file_modified_at = ?4, updated_at = ?5
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        let file_stamp = FileStamp::read(&track.path).context(format!(
+            "Failed to read file metadata of track {:?}",
+            track.path
+        ))?;
+        let hash = hash_file(&track.path)?.to_string();
+
+        if track.hash.as_ref() == Some(&hash) && track.file_stamp == Some(file_stamp) {
+            return Ok(None);
+        }
+
+        let updated_track = conn.query_row(
+            sql,
+            params![
+                track.id.to_string(),
+                hash,
+                file_stamp.size,
+                file_stamp.modified_at,
+                Utc::now()
+            ],
+            |row| Track::try_from(row),
+        )?;
+
+        debug!("Updated hash of track {:?}", updated_track.path);
+
+        Ok(Some(updated_track))
+    }
+
+    /// Reads a track's file again after it has been modified, updating its hash, embedded tags and duration.
+    /// The name is only updated if the file has a title tag. Returns `None` if the file hasn't changed since
+    /// it was last read, going by its size and modification time.
+    pub fn refresh(conn: &Connection, track: &Track) -> Result<Option<Track>> {
+        let sql = "
+            UPDATE tracks
+            SET name = ?2, hash = ?3, duration_secs = ?4, valid = 1,
+                title = ?5, artist = ?6, album_artist = ?7, album = ?8,
+                track_number = ?9, disc_number = ?10, year = ?11, genre = ?12,
+                file_size = ?13, file_modified_at = ?14, updated_at = ?15
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        let path = &track.path;
+
+        let file_stamp = FileStamp::read(path)
+            .context(format!("Failed to read file metadata of track {path:?}"))?;
+        if track.valid && track.file_stamp == Some(file_stamp) {
+            return Ok(None);
+        }
+
+        let hash = hash_file(path)?.to_string();
+
+        let TrackMetadata { codec_params, tags } = extract_track_metadata(path)?;
+        let duration_secs = extract_track_duration(codec_params)
+            .context(format!("Failed to get duration from track {path:?}"))?
+            .as_secs_f64();
+
+        let name = tags.title.clone().unwrap_or_else(|| track.name.clone());
+
+        let refreshed_track = conn.query_row(
+            sql,
+            params![
+                track.id.to_string(),
+                name,
+                hash,
+                duration_secs,
+                tags.title,
+                tags.artist,
+                tags.album_artist,
+                tags.album,
+                tags.track_number,
+                tags.disc_number,
+                tags.year,
+                tags.genre,
+                file_stamp.size,
+                file_stamp.modified_at,
+                Utc::now()
+            ],
+            |row| Track::try_from(row),
+        )?;
+
+        debug!("Refreshed modified track {:?}", refreshed_track.path);
+
+        Ok(Some(refreshed_track))
+    }
+
+    /// Checks that a track's file still exists and can be read, rehashing it if it has been modified
+    /// since it was last hashed, going by its size and modification time.
+    /// The track is only updated if its validity, hash or file stamp has changed.
+    pub fn validate(conn: &Connection, track: &Track) -> rusqlite::Result<TrackValidation> {
+        let (valid, hash, file_stamp, problem) = match FileStamp::read(&track.path) {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (
+                false,
+                track.hash.clone(),
+                track.file_stamp,
+                Some("File is missing".to_string()),
+            ),
+            Err(err) => (
+                false,
+                track.hash.clone(),
+                track.file_stamp,
+                Some(format!("File couldn't be read: {err}")),
+            ),
+            Ok(file_stamp) if track.valid && track.file_stamp == Some(file_stamp) => {
+                return Ok(TrackValidation {
+                    updated_track: None,
+                    problem: None,
+                });
+            }
+            Ok(file_stamp) => match hash_file(&track.path) {
+                Ok(hash) => (true, Some(hash.to_string()), Some(file_stamp), None),
+                Err(err) => (
+                    false,
+                    track.hash.clone(),
+                    track.file_stamp,
+                    Some(format!("File couldn't be read: {err}")),
+                ),
+            },
+        };
+
+        let updated_track =
+            if valid != track.valid || hash != track.hash || file_stamp != track.file_stamp {
+                Some(Track::set_validation(
+                    conn,
+                    track.id,
+                    valid,
+                    hash.as_deref(),
+                    file_stamp,
+                )?)
+            } else {
+                None
+            };
+
+        Ok(TrackValidation {
+            updated_track,
+            problem,
+        })
+    }
+
+    /// Stores what validation found out about a track's file, returning the updated track.
+    pub fn set_validation(
+        conn: &Connection,
+        id: Uuid,
+        valid: bool,
+        hash: Option<&str>,
+        file_stamp: Option<FileStamp>,
+    ) -> rusqlite::Result<Track> {
+        let sql = "
+            UPDATE tracks
+            SET valid = ?2, hash = ?3, file_size = ?4, file_modified_at = ?5, updated_at = ?6
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        conn.query_row(
+            sql,
+            params![
+                id.to_string(),
+                valid,
+                hash,
+                file_stamp.map(|stamp| stamp.size),
+                file_stamp.map(|stamp| stamp.modified_at),
+                Utc::now()
+            ],
+            |row| Track::try_from(row),
+        )
+    }
+
+    /// Points a track at a file in another location, such as after it's been moved.
+    /// The new file is hashed, and the track is marked as valid again.
+    pub fn relocate(conn: &Connection, track: &Track, path: &Path) -> Result<Track> {
+        let sql = "
+            UPDATE tracks
+            SET path = ?2, hash = ?3, file_size = ?4, file_modified_at = ?5, valid = 1, updated_at = ?6
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        let file_stamp = FileStamp::read(path)
+            .context(format!("Failed to read file metadata of track {path:?}"))?;
+        let hash = hash_file(path)?.to_string();
+
+        let relocated_track = conn.query_row(
+            sql,
+            params![
+                track.id.to_string(),
+                path.to_str(),
+                hash,
+                file_stamp.size,
+                file_stamp.modified_at,
+                Utc::now()
+            ],
+            |row| Track::try_from(row),
+        )?;
+
+        debug!(
+            "Relocated track from {:?} to {:?}",
+            track.path, relocated_track.path
+        );
+
+        Ok(relocated_track)
+    }
+
+    /// Gets the track at the path, or every track inside it if it's a folder.
+    pub fn get_within(conn: &Connection, path: &Path) -> rusqlite::Result<Vec<Track>> {
+        let sql = "
+            SELECT * FROM tracks
+            WHERE path = ?1 OR substr(path, 1, length(?2)) = ?2
+        ";
+
+        let path = path.to_string_lossy();
+        let folder = format!(
+            "{}{}",
+            path.trim_end_matches(MAIN_SEPARATOR),
+            MAIN_SEPARATOR
+        );
+
+        let mut stmt = conn.prepare(sql)?;
+        stmt.query_map(params![path, folder], |row| Track::try_from(row))?
+            .collect()
+    }
+
+    /// Points the tracks at or inside a path that has been renamed or moved to their new locations,
+    /// returning the moved tracks. Tracks that would clash with one already at the new location are left alone.
+    pub fn move_within(conn: &Connection, from: &Path, to: &Path) -> rusqlite::Result<Vec<Track>> {
+        let sql = "
+            UPDATE tracks
+            SET path = ?2, valid = ?3, updated_at = ?4
+            WHERE id = ?1
+            RETURNING *
+        ";
+
+        let mut moved_tracks = Vec::new();
+
+        for track in Track::get_within(conn, from)? {
+            let Ok(relative_path) = track.path.strip_prefix(from) else {
+                continue;
+            };
+            let path = if relative_path.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(relative_path)
+            };
+
+            match conn.query_row(
+                sql,
+                params![
+                    track.id.to_string(),
+                    path.to_string_lossy(),
+                    path.exists(),
+                    Utc::now()
+                ],
+                |row| Track::try_from(row),
+            ) {
+                Ok(moved_track) => moved_tracks.push(moved_track),
+                Err(err) if is_unique_violation(&err) => {
+                    debug!("Track {:?} is already in the library", path);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        debug!(
+            "Moved {} tracks from {:?} to {:?}",
+            moved_tracks.len(),
+            from,
+            to
+        );
+
+        Ok(moved_tracks)
+    }
+
+    /// Marks the tracks at or inside a path as invalid if their files don't exist anymore,
+    /// returning the tracks that were marked.
+    pub fn invalidate_missing_within(
+        conn: &Connection,
+        path: &Path,
+    ) -> rusqlite::Result<Vec<Track>> {
+        Track::get_within(conn, path)?
+            .into_iter()
+            .filter(|track| track.valid && !track.path.exists())
+            .map(|track| {
+                Track::set_validation(
+                    conn,
+                    track.id,
+                    false,
+                    track.hash.as_deref(),
+                    track.file_stamp,
+                )
+            })
+            .collect()
+    }
+
     pub fn get_all(conn: &Connection) -> Result<Vec<Track>> {
         let sql = "
-            SELECT id, path, name, hash, duration_secs, valid, created_at, updated_at
+            SELECT id, path, name, hash, duration_secs, valid,
+                title, artist, album_artist, album, track_number, disc_number, year, genre,
+                file_size, file_modified_at, created_at, updated_at
             FROM tracks
         ";
 
@@ -157,4 +675,294 @@ impl Track {
 
         Ok(tracks)
     }
+
+    /// Deletes tracks from the library by their IDs, which removes them from every playlist and tag
+    /// (and the play queue and history) through cascading deletes. The files themselves are left alone.
+    /// Returns how many tracks were deleted.
+    pub fn delete(conn: &Connection, track_ids: &[Uuid]) -> Result<usize> {
+        let sql = "
+            DELETE FROM tracks
+            WHERE id = ?1
+        ";
+
+        let tx = conn.unchecked_transaction()?;
+
+        let mut deleted = 0;
+        {
+            let mut stmt = tx.prepare(sql)?;
+            for track_id in track_ids {
+                deleted += stmt.execute([track_id.to_string()])?;
+            }
+        }
+
+        tx.commit()?;
+
+        debug!("Deleted {} track(s) from the library", deleted);
+
+        Ok(deleted)
+    }
+
+    /// Searches the full text index of tracks and their tags with an FTS5 match expression,
+    /// such as one made by [`Query::split_full_text`](crate::utils::query::Query::split_full_text).
+    /// Returns the IDs of the matching tracks, best matches first.
+    pub fn search(conn: &Connection, full_text: &str) -> Result<Vec<Uuid>> {
+        let sql = "
+            SELECT track_search_ids.track_id
+            FROM tracks_fts
+            JOIN track_search_ids ON track_search_ids.search_id = tracks_fts.rowid
+            WHERE tracks_fts MATCH ?1
+            ORDER BY rank
+        ";
+
+        let mut stmt = conn
+            .prepare_cached(sql)
+            .context("Failed to prepare full text search of tracks")?;
+
+        let track_ids: Vec<Uuid> = stmt
+            .query_map([full_text], |row| parse_uuid(row.get::<_, String>(0)?))?
+            .collect::<Result<_, _>>()?;
+
+        debug!(
+            "Found {} track(s) matching full text search {:?}",
+            track_ids.len(),
+            full_text
+        );
+
+        Ok(track_ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn album_track(album: &str, disc_number: u32, track_number: u32) -> Track {
+        Track {
+            album: Some(album.to_string()),
+            disc_number: Some(disc_number),
+            track_number: Some(track_number),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_missing_tracks_are_matched_by_hash() {
+        let present_path = std::env::current_exe().unwrap();
+
+        let present = Track {
+            path: present_path.clone(),
+            hash: Some("present".to_string()),
+            ..Default::default()
+        };
+        let moved = Track {
+            path: PathBuf::from("/nonexistent/moved.mp3"),
+            hash: Some("moved".to_string()),
+            ..Default::default()
+        };
+        let unhashed = Track {
+            path: PathBuf::from("/nonexistent/unhashed.mp3"),
+            hash: None,
+            ..Default::default()
+        };
+
+        let mut missing = MissingTracks::new(vec![present.clone(), moved.clone(), unhashed]);
+        assert_eq!(missing.len(), 1);
+        assert!(missing.is_known(&present_path));
+        assert!(!missing.is_known(Path::new("/music/moved.mp3")));
+
+        // A file that's still where it was isn't a missing track
+        assert!(missing.take_match(&present_path, "present").is_none());
+        assert!(
+            missing
+                .take_match(Path::new("/music/other.mp3"), "other")
+                .is_none()
+        );
+
+        let relocation = missing
+            .take_match(Path::new("/music/moved.mp3"), "moved")
+            .unwrap();
+        assert_eq!(relocation.track, moved);
+        assert_eq!(relocation.path, PathBuf::from("/music/moved.mp3"));
+
+        // Once matched, the track can't be matched again
+        assert!(
+            missing
+                .take_match(Path::new("/music/copy of moved.mp3"), "moved")
+                .is_none()
+        );
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_tracks_follow_moved_and_removed_folders() {
+        use crate::database::test_utils::{insert_track, open_database, temp_path};
+
+        let conn = open_database();
+
+        let folder = temp_path("watch");
+        let renamed_folder = folder.with_extension("renamed");
+        fs::create_dir_all(folder.join("album")).unwrap();
+
+        let inside = Track {
+            path: folder.join("album").join("track.mp3"),
+            hash: Some("inside".to_string()),
+            ..Default::default()
+        };
+        let similar = Track {
+            path: folder.with_extension("mp3"),
+            hash: Some("similar".to_string()),
+            ..Default::default()
+        };
+        fs::write(&inside.path, b"inside").unwrap();
+        insert_track(&conn, &inside);
+        insert_track(&conn, &similar);
+
+        // Only tracks inside the folder are matched, not ones whose path merely starts the same
+        let within = Track::get_within(&conn, &folder).unwrap();
+        assert_eq!(within.len(), 1);
+        assert_eq!(within[0].id, inside.id);
+
+        fs::rename(&folder, &renamed_folder).unwrap();
+
+        // Nothing is missing until the removal is noticed, and the move is repointed
+        let moved = Track::move_within(&conn, &folder, &renamed_folder).unwrap();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(
+            moved[0].path,
+            renamed_folder.join("album").join("track.mp3")
+        );
+        assert!(moved[0].valid);
+
+        fs::remove_dir_all(&renamed_folder).unwrap();
+
+        let invalidated = Track::invalidate_missing_within(&conn, &renamed_folder).unwrap();
+        assert_eq!(invalidated.len(), 1);
+        assert!(!invalidated[0].valid);
+
+        // Already invalid tracks aren't marked again
+        assert!(
+            Track::invalidate_missing_within(&conn, &renamed_folder)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_validate_only_rehashes_changed_files() {
+        use crate::database::test_utils::{insert_track, open_database, temp_path};
+
+        let conn = open_database();
+
+        let path = temp_path("validate").with_extension("mp3");
+        fs::write(&path, b"original").unwrap();
+
+        // A stale hash shows the file isn't hashed again while its size and modification time are unchanged
+        let track = Track {
+            path: path.clone(),
+            hash: Some("stale".to_string()),
+            file_stamp: Some(FileStamp::read(&path).unwrap()),
+            ..Default::default()
+        };
+        insert_track(&conn, &track);
+
+        let validation = Track::validate(&conn, &track).unwrap();
+        assert!(validation.updated_track.is_none());
+        assert!(validation.problem.is_none());
+
+        fs::write(&path, b"modified, and longer").unwrap();
+        let validation = Track::validate(&conn, &track).unwrap();
+        let modified_track = validation.updated_track.unwrap();
+        assert!(modified_track.valid);
+        assert_eq!(
+            modified_track.hash,
+            Some(hash_file(&path).unwrap().to_string())
+        );
+        assert_eq!(
+            modified_track.file_stamp,
+            Some(FileStamp::read(&path).unwrap())
+        );
+        assert!(validation.problem.is_none());
+
+        fs::remove_file(&path).unwrap();
+        let validation = Track::validate(&conn, &modified_track).unwrap();
+        let missing_track = validation.updated_track.unwrap();
+        assert!(!missing_track.valid);
+        assert_eq!(missing_track.hash, modified_track.hash);
+        assert!(validation.problem.is_some());
+
+        // Nothing changes when validating a track that's already known to be missing
+        let validation = Track::validate(&conn, &missing_track).unwrap();
+        assert!(validation.updated_track.is_none());
+        assert!(validation.problem.is_some());
+    }
+
+    #[test]
+    fn test_search_follows_tracks_and_tags() {
+        use crate::database::{
+            models::tags::{tag::Tag, tag_tracks::TagTrack},
+            test_utils::{insert_track, open_database},
+        };
+
+        let conn = open_database();
+
+        let daft = Track {
+            name: "One More Time".to_string(),
+            path: "/music/one_more_time.flac".into(),
+            artist: Some("Daft Punk".to_string()),
+            ..Default::default()
+        };
+        let aphex = Track {
+            name: "Windowlicker".to_string(),
+            path: "/music/windowlicker.mp3".into(),
+            artist: Some("Aphex Twin".to_string()),
+            album: Some("Windowlicker".to_string()),
+            ..Default::default()
+        };
+        insert_track(&conn, &daft);
+        insert_track(&conn, &aphex);
+
+        let search = |query: &str| Track::search(&conn, query).unwrap();
+
+        assert_eq!(search("\"daft\"*"), [daft.id]);
+        assert_eq!(search("\"windowlicker\""), [aphex.id]);
+        assert_eq!(search("\"music\"").len(), 2);
+        assert_eq!(search("\"more time\""), [daft.id]);
+        assert!(search("\"time more\"").is_empty());
+
+        conn.execute(
+            "UPDATE tracks SET artist = 'Thomas Bangalter' WHERE id = ?1",
+            [daft.id.to_string()],
+        )
+        .unwrap();
+        assert!(search("\"daft\"").is_empty());
+        assert_eq!(search("\"bangalter\""), [daft.id]);
+
+        let tag = Tag::create(&conn, "Favourite".to_string()).unwrap();
+        TagTrack::create(&conn, tag.id, aphex.id).unwrap();
+        assert_eq!(search("\"favourite\""), [aphex.id]);
+
+        Tag::rename(&conn, tag.id, "Classic".to_string()).unwrap();
+        assert!(search("\"favourite\"").is_empty());
+        assert_eq!(search("\"classic\""), [aphex.id]);
+
+        TagTrack::delete(&conn, tag.id, aphex.id).unwrap();
+        assert!(search("\"classic\"").is_empty());
+
+        assert_eq!(
+            Track::delete(&conn, &[aphex.id, Uuid::new_v4()]).unwrap(),
+            1
+        );
+        assert_eq!(search("\"music\""), [daft.id]);
+    }
+
+    #[test]
+    fn test_continues_album() {
+        let first = album_track("Live", 1, 1);
+
+        assert!(album_track("Live", 1, 2).continues_album(&first));
+        assert!(!album_track("Live", 1, 3).continues_album(&first));
+        assert!(!album_track("Live", 2, 2).continues_album(&first));
+        assert!(!album_track("Studio", 1, 2).continues_album(&first));
+        assert!(!Track::default().continues_album(&Track::default()));
+    }
 }
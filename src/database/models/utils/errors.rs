@@ -0,0 +1,11 @@
+use rusqlite::{Error, ffi};
+
+/// Checks if an error came from violating a `UNIQUE` (or primary key) constraint.
+pub fn is_unique_violation(err: &Error) -> bool {
+    matches!(
+        err,
+        Error::SqliteFailure(failure, _)
+            if failure.extended_code == ffi::SQLITE_CONSTRAINT_UNIQUE
+                || failure.extended_code == ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+    )
+}
@@ -1,8 +1,3 @@
-use color_eyre::Result;
-use rusqlite::Connection;
-
-use super::connection::Database;
-
 const TRACKS_TABLE: &str = "
 CREATE TABLE IF NOT EXISTS tracks (
     id TEXT PRIMARY KEY,
@@ -65,7 +60,7 @@ CREATE TABLE IF NOT EXISTS track_tags (
 );
 ";
 
-const TABLES: [&str; 5] = [
+pub(crate) const TABLES: [&str; 5] = [
     TRACKS_TABLE,
     PLAYLISTS_TABLE,
     PLAYLIST_TRACKS_TABLE,
@@ -73,12 +68,176 @@ const TABLES: [&str; 5] = [
     TAG_TRACKS_TABLE,
 ];
 
-impl Database {
-    pub(crate) fn create_tables(conn: &Connection) -> Result<()> {
-        for table in TABLES {
-            conn.execute(table, ())?;
-        }
+pub(crate) const TRACK_TAG_COLUMNS: [&str; 8] = [
+    "ALTER TABLE tracks ADD COLUMN title TEXT;",
+    "ALTER TABLE tracks ADD COLUMN artist TEXT;",
+    "ALTER TABLE tracks ADD COLUMN album_artist TEXT;",
+    "ALTER TABLE tracks ADD COLUMN album TEXT;",
+    "ALTER TABLE tracks ADD COLUMN track_number INTEGER;",
+    "ALTER TABLE tracks ADD COLUMN disc_number INTEGER;",
+    "ALTER TABLE tracks ADD COLUMN year INTEGER;",
+    "ALTER TABLE tracks ADD COLUMN genre TEXT;",
+];
+
+const PLAY_HISTORY_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS play_history (
+    id TEXT PRIMARY KEY,
+    track_id TEXT NOT NULL,
+    playlist_id TEXT,
+    started_at DATETIME NOT NULL,
+    listened_secs REAL NOT NULL DEFAULT 0,
+    skipped BOOLEAN NOT NULL DEFAULT 0,
+
+    FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE,
+    FOREIGN KEY (playlist_id) REFERENCES playlists(id) ON DELETE SET NULL
+);
+";
+
+const PLAY_HISTORY_STARTED_AT_INDEX: &str = "
+CREATE INDEX IF NOT EXISTS play_history_started_at ON play_history (started_at);
+";
+
+pub(crate) const PLAY_HISTORY_TABLES: [&str; 2] =
+    [PLAY_HISTORY_TABLE, PLAY_HISTORY_STARTED_AT_INDEX];
+
+const QUEUE_TRACKS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS queue_tracks (
+    position INTEGER PRIMARY KEY,
+    track_id TEXT NOT NULL,
+
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+
+    FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+);
+";
+
+pub(crate) const QUEUE_TABLES: [&str; 1] = [QUEUE_TRACKS_TABLE];
+
+pub(crate) const SMART_PLAYLIST_COLUMNS: [&str; 4] = [
+    "ALTER TABLE playlists ADD COLUMN smart_query TEXT;",
+    "ALTER TABLE playlists ADD COLUMN smart_sort_column TEXT;",
+    "ALTER TABLE playlists ADD COLUMN smart_sort_order TEXT;",
+    "ALTER TABLE playlists ADD COLUMN smart_limit INTEGER;",
+];
+
+/// Lets validation tell if a track's file has changed without hashing it again.
+pub(crate) const TRACK_FILE_STAMP_COLUMNS: [&str; 2] = [
+    "ALTER TABLE tracks ADD COLUMN file_size INTEGER;",
+    "ALTER TABLE tracks ADD COLUMN file_modified_at TEXT;",
+];
+
+/// Gives every track a stable integer id for its row in `tracks_fts`,
+/// since the implicit rowids of `tracks` may be renumbered when the database is vacuumed.
+const TRACK_SEARCH_IDS_TABLE: &str = "
+CREATE TABLE IF NOT EXISTS track_search_ids (
+    search_id INTEGER PRIMARY KEY,
+    track_id TEXT NOT NULL UNIQUE,
+
+    FOREIGN KEY (track_id) REFERENCES tracks(id) ON DELETE CASCADE
+);
+";
+
+const TRACKS_FTS_TABLE: &str = "
+CREATE VIRTUAL TABLE IF NOT EXISTS tracks_fts USING fts5(
+    name, title, artist, album_artist, album, genre, path, tags,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+";
+
+const TRACKS_FTS_TRIGGERS: &str = "
+CREATE TRIGGER IF NOT EXISTS tracks_fts_insert AFTER INSERT ON tracks BEGIN
+    INSERT INTO track_search_ids (track_id) VALUES (new.id);
+    INSERT INTO tracks_fts (rowid, name, title, artist, album_artist, album, genre, path, tags)
+    VALUES (
+        (SELECT search_id FROM track_search_ids WHERE track_id = new.id),
+        new.name, new.title, new.artist, new.album_artist, new.album, new.genre, new.path, ''
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS tracks_fts_update AFTER UPDATE ON tracks BEGIN
+    UPDATE tracks_fts SET
+        name = new.name,
+        title = new.title,
+        artist = new.artist,
+        album_artist = new.album_artist,
+        album = new.album,
+        genre = new.genre,
+        path = new.path
+    WHERE rowid = (SELECT search_id FROM track_search_ids WHERE track_id = new.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS tracks_fts_delete BEFORE DELETE ON tracks BEGIN
+    DELETE FROM tracks_fts
+    WHERE rowid = (SELECT search_id FROM track_search_ids WHERE track_id = old.id);
+    DELETE FROM track_search_ids WHERE track_id = old.id;
+END;
+";
+
+const TRACK_TAGS_FTS_TRIGGERS: &str = "
+CREATE TRIGGER IF NOT EXISTS track_tags_fts_insert AFTER INSERT ON track_tags BEGIN
+    UPDATE tracks_fts SET tags = coalesce((
+        SELECT group_concat(tags.name, ' ')
+        FROM track_search_ids
+        JOIN track_tags ON track_tags.track_id = track_search_ids.track_id
+        JOIN tags ON tags.id = track_tags.tag_id
+        WHERE track_search_ids.search_id = tracks_fts.rowid
+    ), '')
+    WHERE rowid = (SELECT search_id FROM track_search_ids WHERE track_id = new.track_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS track_tags_fts_delete AFTER DELETE ON track_tags BEGIN
+    UPDATE tracks_fts SET tags = coalesce((
+        SELECT group_concat(tags.name, ' ')
+        FROM track_search_ids
+        JOIN track_tags ON track_tags.track_id = track_search_ids.track_id
+        JOIN tags ON tags.id = track_tags.tag_id
+        WHERE track_search_ids.search_id = tracks_fts.rowid
+    ), '')
+    WHERE rowid = (SELECT search_id FROM track_search_ids WHERE track_id = old.track_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS tags_fts_update AFTER UPDATE OF name ON tags BEGIN
+    UPDATE tracks_fts SET tags = coalesce((
+        SELECT group_concat(tags.name, ' ')
+        FROM track_search_ids
+        JOIN track_tags ON track_tags.track_id = track_search_ids.track_id
+        JOIN tags ON tags.id = track_tags.tag_id
+        WHERE track_search_ids.search_id = tracks_fts.rowid
+    ), '')
+    WHERE rowid IN (
+        SELECT track_search_ids.search_id
+        FROM track_search_ids
+        JOIN track_tags ON track_tags.track_id = track_search_ids.track_id
+        WHERE track_tags.tag_id = new.id
+    );
+END;
+";
 
-        Ok(())
-    }
-}
+/// Indexes the tracks that were already in the library when the search index was created.
+const TRACKS_FTS_BACKFILL: &str = "
+INSERT INTO track_search_ids (track_id) SELECT id FROM tracks;
+
+INSERT INTO tracks_fts (rowid, name, title, artist, album_artist, album, genre, path, tags)
+SELECT track_search_ids.search_id,
+    tracks.name, tracks.title, tracks.artist, tracks.album_artist, tracks.album, tracks.genre,
+    tracks.path, ''
+FROM tracks
+JOIN track_search_ids ON track_search_ids.track_id = tracks.id;
+
+UPDATE tracks_fts SET tags = coalesce((
+        SELECT group_concat(tags.name, ' ')
+        FROM track_search_ids
+        JOIN track_tags ON track_tags.track_id = track_search_ids.track_id
+        JOIN tags ON tags.id = track_tags.tag_id
+        WHERE track_search_ids.search_id = tracks_fts.rowid
+    ), '');
+";
+
+pub(crate) const TRACK_SEARCH_TABLES: [&str; 5] = [
+    TRACK_SEARCH_IDS_TABLE,
+    TRACKS_FTS_TABLE,
+    TRACKS_FTS_TRIGGERS,
+    TRACK_TAGS_FTS_TRIGGERS,
+    TRACKS_FTS_BACKFILL,
+];
@@ -0,0 +1,64 @@
+//! Fixtures shared by the tests of the database and what's loaded from it.
+
+use std::path::PathBuf;
+
+use rusqlite::{Connection, params};
+use uuid::Uuid;
+
+use crate::database::{connection::Database, models::tracks::Track};
+
+/// An empty database in memory, set up the same way as the one the app opens.
+pub fn open_database() -> Connection {
+    let mut conn = Connection::open_in_memory().unwrap();
+    conn.pragma_update(None, "foreign_keys", true).unwrap();
+    Database::migrate(&mut conn, None).unwrap();
+
+    conn
+}
+
+/// Stores a track as is, without the file it points to having to exist.
+pub fn insert_track(conn: &Connection, track: &Track) {
+    conn.execute(
+        "INSERT INTO tracks (
+            id, path, name, hash, duration_secs, valid,
+            title, artist, album_artist, album, track_number, disc_number, year, genre,
+            file_size, file_modified_at, created_at, updated_at
+        )
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+        params![
+            track.id.to_string(),
+            track.path.to_str(),
+            track.name,
+            track.hash,
+            track.duration_secs,
+            track.valid,
+            track.title,
+            track.artist,
+            track.album_artist,
+            track.album,
+            track.track_number,
+            track.disc_number,
+            track.year,
+            track.genre,
+            track.file_stamp.map(|stamp| stamp.size),
+            track.file_stamp.map(|stamp| stamp.modified_at),
+            track.created_at,
+            track.updated_at,
+        ],
+    )
+    .unwrap();
+}
+
+/// A track that's only told apart by its name, with a file named after it.
+pub fn track(name: &str) -> Track {
+    Track {
+        name: name.to_string(),
+        path: format!("/music/{name}.mp3").into(),
+        ..Default::default()
+    }
+}
+
+/// A path in the temp folder that no other test uses, such as for a database or a track's file.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("daemos-{name}-{}", Uuid::new_v4()))
+}
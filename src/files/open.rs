@@ -1,8 +1,9 @@
 use std::{
-    env, fs,
+    env,
     path::{Path, PathBuf},
 };
 
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use rfd::FileDialog;
 use walkdir::WalkDir;
 
@@ -24,34 +25,102 @@ pub fn select_folders_dialog() -> Option<Vec<PathBuf>> {
         .pick_folders()
 }
 
+/// Whether the path has the extension of an audio format that can be played, whether or not it exists.
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| ALLOWED_AUDIO_FORMATS.contains(&extension))
+}
+
 /// Returns a list of audio track paths from the given directory.
 /// If `recursive` is true, subdirectories will also be searched.
 pub fn get_folder_tracks<P: AsRef<Path>>(dir: &P, recursive: bool) -> Vec<PathBuf> {
-    let mut tracks = Vec::new();
-
-    if recursive {
-        for entry in WalkDir::new(dir).into_iter().filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_file()
-                && let Some(extension) = path.extension().and_then(|e| e.to_str())
-                && ALLOWED_AUDIO_FORMATS.contains(&extension)
-            {
-                tracks.push(path.to_path_buf());
+    let options = ScanOptions {
+        recursive,
+        ..Default::default()
+    };
+
+    // Without any patterns, there's nothing that could fail to parse
+    options.scan(dir.as_ref()).unwrap_or_default()
+}
+
+/// How to look through a folder for audio files to import.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanOptions {
+    /// Whether to look inside subfolders
+    pub recursive: bool,
+    /// How many levels of subfolders to look inside when recursive, or `None` for all of them
+    pub max_depth: Option<usize>,
+    /// Whether to look inside symlinked folders, which might lead outside the folder
+    pub follow_symlinks: bool,
+    /// Glob patterns that a file has to match at least one of, unless there are none
+    pub include: Vec<String>,
+    /// Glob patterns of files and folders to leave out
+    pub exclude: Vec<String>,
+}
+
+impl ScanOptions {
+    /// Finds the audio files in the folder, failing if any of the patterns can't be parsed.
+    ///
+    /// Patterns are matched against paths relative to the folder. Patterns without a `/` match at any depth,
+    /// such as `*.demo.mp3`, and a trailing `/` is ignored, so `Samples/` leaves out every folder named `Samples`.
+    pub fn scan(&self, dir: &Path) -> Result<Vec<PathBuf>, globset::Error> {
+        let include = Self::glob_set(&self.include)?;
+        let exclude = Self::glob_set(&self.exclude)?;
+
+        let max_depth = match (self.recursive, self.max_depth) {
+            (false, _) => 1,
+            (true, Some(max_depth)) => max_depth + 1,
+            (true, None) => usize::MAX,
+        };
+
+        let walker = WalkDir::new(dir)
+            .follow_links(self.follow_symlinks)
+            .max_depth(max_depth)
+            .sort_by_file_name()
+            .into_iter()
+            // Excluded folders aren't looked inside at all
+            .filter_entry(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(dir)
+                    .is_ok_and(|relative_path| !exclude.is_match(relative_path))
+            });
+
+        let tracks = walker
+            .filter_map(Result::ok)
+            .map(walkdir::DirEntry::into_path)
+            .filter(|path| {
+                path.is_file()
+                    && is_audio_file(path)
+                    && (include.is_empty()
+                        || path
+                            .strip_prefix(dir)
+                            .is_ok_and(|relative_path| include.is_match(relative_path)))
+            })
+            .collect();
+
+        Ok(tracks)
+    }
+
+    fn glob_set(patterns: &[String]) -> Result<GlobSet, globset::Error> {
+        let mut builder = GlobSetBuilder::new();
+
+        for pattern in patterns {
+            let pattern = pattern.trim().trim_end_matches('/');
+            if pattern.is_empty() {
+                continue;
             }
-        }
-    } else if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.filter_map(Result::ok) {
-            let path = entry.path();
-            if path.is_file()
-                && let Some(extension) = path.extension().and_then(|e| e.to_str())
-                && ALLOWED_AUDIO_FORMATS.contains(&extension)
-            {
-                tracks.push(path);
+
+            if pattern.contains('/') {
+                builder.add(Glob::new(pattern)?);
+            } else {
+                builder.add(Glob::new(&format!("**/{pattern}"))?);
             }
         }
-    }
 
-    tracks
+        builder.build()
+    }
 }
 
 pub fn get_file_name(track_file_path: PathBuf) -> Option<String> {
@@ -69,3 +138,74 @@ pub fn get_file_name(track_file_path: PathBuf) -> Option<String> {
             .to_string()
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::database::test_utils::temp_path;
+
+    #[test]
+    fn test_scan_options_filter_files() {
+        let dir = temp_path("scan");
+        for path in [
+            "top.mp3",
+            "cover.jpg",
+            "album/track.flac",
+            "album/track.demo.mp3",
+            "album/disc 2/deep.mp3",
+            "Samples/kick.wav",
+        ] {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"").unwrap();
+        }
+
+        let relative_paths = |options: &ScanOptions| -> Vec<String> {
+            options
+                .scan(&dir)
+                .unwrap()
+                .iter()
+                .map(|path| path.strip_prefix(&dir).unwrap().display().to_string())
+                .collect()
+        };
+
+        assert_eq!(relative_paths(&ScanOptions::default()), ["top.mp3"]);
+
+        let mut options = ScanOptions {
+            recursive: true,
+            ..Default::default()
+        };
+        assert_eq!(relative_paths(&options).len(), 5);
+
+        options.max_depth = Some(1);
+        assert_eq!(
+            relative_paths(&options),
+            [
+                "Samples/kick.wav",
+                "album/track.demo.mp3",
+                "album/track.flac",
+                "top.mp3"
+            ]
+        );
+
+        options.max_depth = None;
+        options.exclude = vec!["Samples/".to_string(), "*.demo.mp3".to_string()];
+        assert_eq!(
+            relative_paths(&options),
+            ["album/disc 2/deep.mp3", "album/track.flac", "top.mp3"]
+        );
+
+        options.include = vec!["album/**".to_string()];
+        assert_eq!(
+            relative_paths(&options),
+            ["album/disc 2/deep.mp3", "album/track.flac"]
+        );
+
+        options.include = vec!["[".to_string()];
+        assert!(options.scan(&dir).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+use std::{path::PathBuf, time::Duration};
+
+use crossbeam::channel::{Receiver, Sender, unbounded};
+use notify::{
+    Event, EventKind, RecommendedWatcher, RecursiveMode,
+    event::{ModifyKind, RenameMode},
+};
+use notify_debouncer_full::{DebounceEventResult, Debouncer, RecommendedCache, new_debouncer};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info};
+
+use crate::files::open::{get_folder_tracks, is_audio_file};
+
+/// What happened to the files in the watched folders, to be synced with the library
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq)]
+pub struct LibraryChanges {
+    /// Audio files that have appeared, and may be new tracks or where missing tracks moved to
+    pub added: Vec<PathBuf>,
+    /// Files or folders that have gone, whose tracks are now missing
+    pub removed: Vec<PathBuf>,
+    /// Files or folders that have been renamed or moved within the watched folders, from and to
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Audio files whose contents or metadata have changed, such as after being retagged
+    pub modified: Vec<PathBuf>,
+}
+
+impl LibraryChanges {
+    /// Sorts out the filesystem events into what needs to change in the library.
+    /// Folders that appear are searched for audio files, as only the folder itself is reported.
+    pub fn from_events(events: impl IntoIterator<Item = Event>) -> Self {
+        let mut changes = LibraryChanges::default();
+
+        for event in events {
+            match event.kind {
+                EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                    for path in event.paths {
+                        changes.add(path);
+                    }
+                }
+                EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                    changes.removed.extend(event.paths);
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                    if let [from, to] = event.paths.as_slice() {
+                        changes.renamed.push((from.clone(), to.clone()));
+                    }
+                }
+                // Some platforms don't report what kind of modification it was
+                EventKind::Modify(
+                    ModifyKind::Data(_) | ModifyKind::Metadata(_) | ModifyKind::Any,
+                ) => {
+                    for path in event.paths {
+                        changes.modify(path);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Files that were added are read in full anyway
+        let added = &changes.added;
+        changes.modified.retain(|path| !added.contains(path));
+
+        changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.renamed.is_empty()
+            && self.modified.is_empty()
+    }
+
+    fn add(&mut self, path: PathBuf) {
+        if path.is_dir() {
+            self.added.extend(get_folder_tracks(&path, true));
+        } else if is_audio_file(&path) && !self.added.contains(&path) {
+            self.added.push(path);
+        }
+    }
+
+    fn modify(&mut self, path: PathBuf) {
+        if is_audio_file(&path) && !self.modified.contains(&path) {
+            self.modified.push(path);
+        }
+    }
+}
+
+/// Watches the library folders for changes, which are batched up until the folders have settled,
+/// so copying many files at once doesn't import them one at a time.
+pub struct LibraryWatcher {
+    /// Stops watching once dropped
+    _debouncer: Debouncer<RecommendedWatcher, RecommendedCache>,
+    changes_rx: Receiver<LibraryChanges>,
+}
+
+impl LibraryWatcher {
+    /// Starts watching each folder and everything inside it.
+    /// Folders that can't be watched, such as ones that don't exist, are skipped.
+    pub fn new(folders: &[PathBuf], debounce: Duration) -> notify::Result<Self> {
+        let (changes_tx, changes_rx) = unbounded();
+
+        let mut debouncer = new_debouncer(debounce, None, move |result: DebounceEventResult| {
+            Self::handle_events(&changes_tx, result);
+        })?;
+
+        for folder in folders {
+            match debouncer.watch(folder, RecursiveMode::Recursive) {
+                Ok(()) => info!("Watching library folder {:?}", folder),
+                Err(err) => error!("Failed to watch library folder {:?}: {}", folder, err),
+            }
+        }
+
+        Ok(Self {
+            _debouncer: debouncer,
+            changes_rx,
+        })
+    }
+
+    fn handle_events(changes_tx: &Sender<LibraryChanges>, result: DebounceEventResult) {
+        match result {
+            Ok(events) => {
+                let changes =
+                    LibraryChanges::from_events(events.into_iter().map(|event| event.event));
+
+                if !changes.is_empty() {
+                    debug!("Library folders changed: {:?}", changes);
+                    let _ = changes_tx.send(changes);
+                }
+            }
+            Err(errors) => {
+                for err in errors {
+                    error!("Error when watching library folders: {}", err);
+                }
+            }
+        }
+    }
+
+    /// The changes that have settled since this was last called, if any.
+    pub fn try_changes(&self) -> Option<LibraryChanges> {
+        self.changes_rx.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use notify::event::{CreateKind, DataChange, MetadataKind, RemoveKind};
+
+    use super::*;
+
+    #[test]
+    fn test_events_are_sorted_into_changes() {
+        let events = vec![
+            Event::new(EventKind::Create(CreateKind::File)).add_path("/music/new.mp3".into()),
+            Event::new(EventKind::Create(CreateKind::File)).add_path("/music/cover.jpg".into()),
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::To)))
+                .add_path("/music/moved in.flac".into()),
+            Event::new(EventKind::Remove(RemoveKind::File)).add_path("/music/gone.mp3".into()),
+            Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+                .add_path("/music/old".into())
+                .add_path("/music/new".into()),
+            Event::new(EventKind::Modify(ModifyKind::Any)).add_path("/music/edited.mp3".into()),
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+                .add_path("/music/retagged.flac".into()),
+            Event::new(EventKind::Modify(ModifyKind::Metadata(
+                MetadataKind::WriteTime,
+            )))
+            .add_path("/music/retagged.flac".into()),
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+                .add_path("/music/new.mp3".into()),
+            Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Size)))
+                .add_path("/music/cover.jpg".into()),
+        ];
+
+        let changes = LibraryChanges::from_events(events);
+
+        assert_eq!(
+            changes,
+            LibraryChanges {
+                added: vec!["/music/new.mp3".into(), "/music/moved in.flac".into()],
+                removed: vec!["/music/gone.mp3".into()],
+                renamed: vec![("/music/old".into(), "/music/new".into())],
+                modified: vec!["/music/edited.mp3".into(), "/music/retagged.flac".into()],
+            }
+        );
+        assert!(LibraryChanges::from_events(Vec::new()).is_empty());
+    }
+}
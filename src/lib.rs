@@ -8,6 +8,8 @@ pub mod files;
 pub mod fonts;
 pub mod logging;
 pub mod playback;
+pub mod session;
+pub mod tasks;
 pub mod themes;
 pub mod utils;
 
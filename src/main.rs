@@ -9,8 +9,13 @@ fn main() -> eframe::Result {
 
     use crossbeam::channel;
     use daemos::{
-        app::App, channels::Channels, config::load_config, database::connection::Database,
-        fonts::set_fonts, logging::initialize_logging, playback::state::Player,
+        app::App,
+        channels::Channels,
+        config::load_config,
+        database::connection::{Database, DatabaseCommand},
+        fonts::set_fonts,
+        logging::initialize_logging,
+        playback::state::Player,
     };
     use egui_extras::install_image_loaders;
     use tracing::{error, info};
@@ -36,7 +41,9 @@ fn main() -> eframe::Result {
         ..Default::default()
     };
 
-    let (database_command_tx, database_event_rx) = Database::start();
+    let (database_command_tx, database_event_rx, database_thread) = Database::start();
+    // Kept to stop the database thread with, once the app has exited
+    let database_shutdown_tx = database_command_tx.clone();
 
     let (player_command_tx, player_cmd_rx) = channel::unbounded();
     let (player_event_tx, player_event_rx) = channel::unbounded();
@@ -46,7 +53,7 @@ fn main() -> eframe::Result {
     thread::spawn(move || {
         info!("Spawned player thread");
 
-        let player = match Player::new(player_event_tx, player_cmd_rx) {
+        let mut player = match Player::new(player_event_tx, player_cmd_rx) {
             Err(err) => {
                 let _ = err_tx.send(Some(err));
                 return;
@@ -72,7 +79,7 @@ fn main() -> eframe::Result {
         player_event_rx,
     ));
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "Daemos",
         options,
         Box::new(|cc| {
@@ -83,7 +90,17 @@ fn main() -> eframe::Result {
 
             Ok(Box::new(app))
         }),
-    )
+    );
+
+    // Wait for whatever the app sent on exit, such as removing tracks whose undo window was still open,
+    // to be written before the process ends
+    if database_shutdown_tx.send(DatabaseCommand::Shutdown).is_ok()
+        && database_thread.join().is_err()
+    {
+        error!("Database thread panicked before shutting down");
+    }
+
+    result
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -1,32 +1,54 @@
 use std::{
+    collections::VecDeque,
+    f32::consts::FRAC_PI_2,
     fs::File,
     io::BufReader,
-    sync::Arc,
     time::{Duration, Instant},
 };
 
 use color_eyre::{Result, eyre::bail};
 use crossbeam::{
-    channel::{Receiver, Sender},
+    channel::{Receiver, RecvTimeoutError, Sender},
     utils::Backoff,
 };
-use rodio::{Decoder, OutputStream, Sink};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info};
 
 use crate::{database::models::tracks::Track, playback::notifications::now_playing};
 
+/// How long the player waits for a command before checking on the state of the sink.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How often volumes are adjusted while crossfading.
+const CROSSFADE_INTERVAL: Duration = Duration::from_millis(20);
+/// How often the position of the playing track is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+type TrackDecoder = Decoder<BufReader<File>>;
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum PlayerEvent {
     TrackChanged(Track),
+    /// A track was loaded paused, such as when resuming a previous session, so it hasn't started playing yet
+    TrackLoaded(Track),
+    /// The track played through to the end of its audio, and nothing else is playing
+    TrackFinished(Track),
+    /// Position of the playing track, reported by the player after seeking and periodically while playing
     TrackProgress(Duration),
     TrackPlayingStatus(bool),
     CurrentVolume(f32),
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum PlayerCommand {
     Create(Track, f32),
+    /// Load a track paused at a position, for resuming where a previous session left off
+    Restore(Track, f32, Duration),
+    /// Append a track to play directly after the current one, without a gap in between.
+    /// If a crossfade duration is given, the track is faded in over the end of the current one instead.
+    Enqueue(Track, Option<Duration>),
     Play,
     Pause,
     Toggle,
@@ -36,16 +58,60 @@ pub enum PlayerCommand {
     SetVolume(f32),
     Position,
     SetPosition(Duration),
+    /// Seek ahead of the current position by a step, stopping at the end of the track
+    SeekForward(Duration),
+    /// Seek back from the current position by a step, stopping at the start of the track
+    SeekBackward(Duration),
+}
+
+/// An enqueued track waiting for the current one to get close enough to its end to be faded into.
+struct PendingCrossfade {
+    track: Track,
+    decoder: TrackDecoder,
+    duration: Duration,
+}
+
+/// The previous track fading out, while the track in the main sink fades in.
+struct Crossfade {
+    outgoing: Sink,
+    duration: Duration,
 }
 
 pub struct Player {
     _stream: OutputStream,
-    sink: Arc<Sink>,
+    stream_handle: OutputStreamHandle,
+    sink: Sink,
+    /// Volume set by the user, which the volumes of crossfading tracks are scaled by
+    volume: f32,
+
+    /// The track that was last appended to the sink, until it finishes or the sink is cleared
+    current_track: Option<Track>,
+    /// Length of the current track's audio, for knowing when to start crossfading
+    current_duration: Duration,
+    /// Tracks appended to the sink after the current one, in the order they'll be played
+    enqueued_tracks: VecDeque<(Track, Duration)>,
+    pending_crossfade: Option<PendingCrossfade>,
+    crossfade: Option<Crossfade>,
+    last_progress_report: Instant,
 
     player_event_tx: Sender<PlayerEvent>,
     player_cmd_rx: Receiver<PlayerCommand>,
 }
 
+/// The length of a track's audio, falling back to its stored duration if the decoder doesn't know.
+fn track_duration(track: &Track, decoder: &TrackDecoder) -> Duration {
+    decoder
+        .total_duration()
+        .unwrap_or_else(|| Duration::from_secs_f64(track.duration_secs.max(0.0)))
+}
+
+fn open_decoder(track: &Track) -> Result<TrackDecoder> {
+    let file = File::open(&track.path)?;
+    let decoder = Decoder::new(BufReader::new(file))?;
+
+    Ok(decoder)
+}
+
 impl Player {
     pub fn new(
         player_event_tx: Sender<PlayerEvent>,
@@ -54,10 +120,10 @@ impl Player {
         let backoff = Backoff::new();
         let timeout = Instant::now() + Duration::from_secs(10);
 
-        let (stream, sink) = loop {
+        let (stream, stream_handle, sink) = loop {
             match OutputStream::try_default() {
                 Ok((stream, handle)) => match Sink::try_new(&handle) {
-                    Ok(sink) => break (stream, sink),
+                    Ok(sink) => break (stream, handle, sink),
                     Err(err) => {
                         error!("Sink creation failed: {}", err);
                     }
@@ -76,92 +142,370 @@ impl Player {
 
         info!("Audio device found!");
 
+        let volume = sink.volume();
+
         Ok(Self {
             _stream: stream,
-            sink: Arc::new(sink),
+            stream_handle,
+            sink,
+            volume,
+            current_track: None,
+            current_duration: Duration::ZERO,
+            enqueued_tracks: VecDeque::new(),
+            pending_crossfade: None,
+            crossfade: None,
+            last_progress_report: Instant::now(),
             player_event_tx,
             player_cmd_rx,
         })
     }
 
-    pub fn create(&self) {
-        while let Ok(command) = self.player_cmd_rx.recv() {
-            if let Err(err) = self.handle_command(&command) {
-                error!(
-                    "Processing player command {:?} failed with error {}",
-                    command, err
-                );
+    pub fn create(&mut self) {
+        loop {
+            let timeout = if self.crossfade.is_some() {
+                CROSSFADE_INTERVAL
+            } else {
+                POLL_INTERVAL
+            };
+
+            match self.player_cmd_rx.recv_timeout(timeout) {
+                Ok(command) => {
+                    if let Err(err) = self.handle_command(&command) {
+                        error!(
+                            "Processing player command {:?} failed with error {}",
+                            command, err
+                        );
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
             }
+
+            self.update_crossfade();
+
+            if let Err(err) = self.report_playback_state() {
+                error!("Reporting playback state failed with error {}", err);
+            }
+        }
+    }
+
+    /// Lets the UI know when an enqueued track starts, once the current track has run out of audio to play,
+    /// and where playback is at every [`PROGRESS_INTERVAL`] while it's playing.
+    fn report_playback_state(&mut self) -> Result<()> {
+        if self.current_track.is_none() {
+            return Ok(());
+        }
+
+        self.start_pending_crossfade()?;
+
+        // Finished sources are dropped from the sink, so an enqueued track has started once the sink
+        // doesn't hold more than the enqueued tracks anymore
+        while !self.enqueued_tracks.is_empty() && self.sink.len() <= self.enqueued_tracks.len() {
+            if let Some((track, duration)) = self.enqueued_tracks.pop_front() {
+                debug!("Started enqueued track {:?}", track.path);
+
+                self.start_track(track, duration, true)?;
+            }
+        }
+
+        if self.sink.empty() {
+            if let Some(track) = self.current_track.take() {
+                debug!("Finished playing track {:?}", track.path);
+
+                self.player_event_tx
+                    .send(PlayerEvent::TrackFinished(track))?;
+            }
+        } else if !self.sink.is_paused() && self.last_progress_report.elapsed() >= PROGRESS_INTERVAL
+        {
+            self.last_progress_report = Instant::now();
+
+            self.player_event_tx
+                .send(PlayerEvent::TrackProgress(self.sink.get_pos()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks a track as the one being played, and lets the UI know about it.
+    fn start_track(&mut self, track: Track, duration: Duration, playing: bool) -> Result<()> {
+        self.current_track = Some(track.clone());
+        self.current_duration = duration;
+        self.last_progress_report = Instant::now();
+
+        let event = if playing {
+            PlayerEvent::TrackChanged(track.clone())
+        } else {
+            PlayerEvent::TrackLoaded(track.clone())
+        };
+        self.player_event_tx.send(event)?;
+
+        now_playing(&track.name);
+
+        Ok(())
+    }
+
+    /// Moves the current track into its own sink to fade out, and fades the pending track in with the main sink,
+    /// once the current track is within the crossfade duration of its end.
+    fn start_pending_crossfade(&mut self) -> Result<()> {
+        let Some(pending) = &self.pending_crossfade else {
+            return Ok(());
+        };
+
+        if self.sink.is_paused() {
+            return Ok(());
+        }
+
+        let remaining = self.current_duration.saturating_sub(self.sink.get_pos());
+        if !self.sink.empty() && remaining > pending.duration {
+            return Ok(());
+        }
+
+        let Some(pending) = self.pending_crossfade.take() else {
+            return Ok(());
+        };
+
+        let track_duration = track_duration(&pending.track, &pending.decoder);
+
+        // The current track ended earlier than expected, so there's nothing left to fade out
+        if self.sink.empty() {
+            self.sink.append(pending.decoder);
+            self.sink.set_volume(self.volume);
+            self.sink.play();
+
+            return self.start_track(pending.track, track_duration, true);
+        }
+
+        debug!(
+            "Crossfading into track {:?} over {:?}",
+            pending.track.path,
+            remaining.min(pending.duration)
+        );
+
+        let incoming = Sink::try_new(&self.stream_handle)?;
+        incoming.set_volume(0.0);
+        incoming.append(pending.decoder);
+
+        let outgoing = std::mem::replace(&mut self.sink, incoming);
+
+        self.crossfade = Some(Crossfade {
+            outgoing,
+            duration: remaining.min(pending.duration),
+        });
+
+        self.start_track(pending.track, track_duration, true)
+    }
+
+    /// Adjusts the volumes of both tracks with an equal-power curve, following the position of the incoming track
+    /// so that pausing also pauses the fade.
+    fn update_crossfade(&mut self) {
+        let Some(crossfade) = &self.crossfade else {
+            return;
+        };
+
+        let progress = if crossfade.duration.is_zero() {
+            1.0
+        } else {
+            self.sink.get_pos().as_secs_f32() / crossfade.duration.as_secs_f32()
+        };
+
+        if progress >= 1.0 || crossfade.outgoing.empty() {
+            self.finish_crossfade();
+
+            return;
+        }
+
+        let angle = progress * FRAC_PI_2;
+        crossfade.outgoing.set_volume(self.volume * angle.cos());
+        self.sink.set_volume(self.volume * angle.sin());
+    }
+
+    /// Stops the outgoing track of a crossfade straight away, leaving the incoming track at full volume.
+    fn finish_crossfade(&mut self) {
+        if let Some(crossfade) = self.crossfade.take() {
+            crossfade.outgoing.stop();
+        }
+
+        self.sink.set_volume(self.volume);
+    }
+
+    fn play(&self) {
+        self.sink.play();
+
+        if let Some(crossfade) = &self.crossfade {
+            crossfade.outgoing.play();
+        }
+    }
+
+    fn pause(&self) {
+        self.sink.pause();
+
+        if let Some(crossfade) = &self.crossfade {
+            crossfade.outgoing.pause();
         }
     }
 
-    fn create_player_track(&self, track: &Track, volume: &f32) -> Result<()> {
+    fn create_player_track(&mut self, track: &Track, volume: &f32, play: bool) -> Result<()> {
         if !self.sink.empty() {
             self.sink.clear();
         }
+        self.current_track = None;
+        self.enqueued_tracks.clear();
+        self.pending_crossfade = None;
+        self.volume = *volume;
+        self.finish_crossfade();
 
-        let track_file_path = track.path.clone();
+        debug!("Appended file {:?} to sink, playing: {}", track.path, play);
 
-        debug!("Appended file {:?} to sink, and playing", track.path);
+        let decoder = open_decoder(track)?;
+        let duration = track_duration(track, &decoder);
 
-        let file = File::open(track_file_path)?;
-        let decoder = Decoder::new(BufReader::new(file))?;
+        // Pausing before appending makes sure none of the track is heard when it shouldn't play yet
+        if !play {
+            self.sink.pause();
+        }
 
         self.sink.append(decoder);
         self.sink.set_volume(*volume);
-        self.sink.play();
 
-        self.player_event_tx
-            .send(PlayerEvent::TrackChanged(track.clone()))?;
+        if play {
+            self.sink.play();
+        }
 
-        now_playing(&track.name);
+        self.start_track(track.clone(), duration, play)
+    }
+
+    /// Appends a track after everything in the sink, so it starts as soon as the sink runs out of audio
+    /// from the tracks before it. With a crossfade, the track is held back until the current one is close
+    /// enough to its end instead. If nothing is playing, the track is played straight away.
+    fn enqueue_player_track(&mut self, track: &Track, crossfade: Option<Duration>) -> Result<()> {
+        if self.current_track.is_none() {
+            let volume = self.volume;
+
+            return self.create_player_track(track, &volume, true);
+        }
+
+        let decoder = open_decoder(track)?;
+
+        if let Some(duration) = crossfade.filter(|duration| !duration.is_zero()) {
+            debug!("Enqueued file {:?} to crossfade into", track.path);
+
+            self.pending_crossfade = Some(PendingCrossfade {
+                track: track.clone(),
+                decoder,
+                duration,
+            });
+
+            return Ok(());
+        }
+
+        debug!("Enqueued file {:?} in sink", track.path);
+
+        let duration = track_duration(track, &decoder);
+
+        self.sink.append(decoder);
+        self.enqueued_tracks.push_back((track.clone(), duration));
 
         Ok(())
     }
 
-    fn handle_command(&self, command: &PlayerCommand) -> Result<()> {
+    /// Seeking skips any crossfade, so the track being seeked through is the only one playing,
+    /// and a track waiting to be crossfaded into follows on without a gap instead.
+    fn skip_crossfade(&mut self) {
+        self.finish_crossfade();
+
+        if let Some(pending) = self.pending_crossfade.take() {
+            let duration = track_duration(&pending.track, &pending.decoder);
+
+            self.sink.append(pending.decoder);
+            self.enqueued_tracks.push_back((pending.track, duration));
+        }
+    }
+
+    /// Seeks the current track to a position worked out from where it's at, reporting back where it ended up.
+    fn seek_relative(&mut self, position: Duration) -> Result<()> {
+        if self.current_track.is_none() {
+            debug!("No track to seek through");
+
+            return Ok(());
+        }
+
+        self.skip_crossfade();
+
+        if let Err(err) = self.sink.try_seek(position) {
+            bail!("Failed to seek to {:?}: {:?}", position, err);
+        };
+
+        self.player_event_tx
+            .send(PlayerEvent::TrackProgress(self.sink.get_pos()))?;
+
+        Ok(())
+    }
+
+    fn handle_command(&mut self, command: &PlayerCommand) -> Result<()> {
         debug!("Player received command: {:?}", command);
 
         match command {
             PlayerCommand::Create(track, volume) => {
-                self.create_player_track(track, volume)?;
+                self.create_player_track(track, volume, true)?;
+            }
+            PlayerCommand::Restore(track, volume, position) => {
+                self.create_player_track(track, volume, false)?;
+
+                if let Err(err) = self.sink.try_seek(*position) {
+                    bail!("Failed to restore position {:?}: {:?}", position, err);
+                };
+
+                self.player_event_tx
+                    .send(PlayerEvent::TrackPlayingStatus(false))?;
+                self.player_event_tx
+                    .send(PlayerEvent::TrackProgress(*position))?;
+            }
+            PlayerCommand::Enqueue(track, crossfade) => {
+                self.enqueue_player_track(track, *crossfade)?;
             }
             PlayerCommand::Play => {
-                self.sink.play();
+                self.play();
             }
             PlayerCommand::Toggle => {
                 let is_paused = self.sink.is_paused();
 
                 if is_paused {
-                    self.sink.play();
+                    self.play();
                 } else {
-                    self.sink.pause();
+                    self.pause();
                 }
 
                 self.player_event_tx
                     .send(PlayerEvent::TrackPlayingStatus(is_paused))?;
             }
             PlayerCommand::Pause => {
-                self.sink.pause();
+                self.pause();
             }
             PlayerCommand::Resume => {
                 if self.sink.is_paused() {
-                    self.sink.play();
+                    self.play();
                 } else {
                     debug!("No track to resume");
                 }
             }
             PlayerCommand::Clear => {
                 self.sink.clear();
+                self.current_track = None;
+                self.enqueued_tracks.clear();
+                self.pending_crossfade = None;
+                self.finish_crossfade();
             }
             PlayerCommand::Volume => {
-                let volume = self.sink.volume();
-
                 self.player_event_tx
-                    .send(PlayerEvent::CurrentVolume(volume))?;
+                    .send(PlayerEvent::CurrentVolume(self.volume))?;
             }
             PlayerCommand::SetVolume(volume_value) => {
-                self.sink.set_volume(*volume_value);
+                self.volume = *volume_value;
+
+                // While crossfading, the new volume is picked up by the next fade step
+                if self.crossfade.is_none() {
+                    self.sink.set_volume(*volume_value);
+                }
 
                 self.player_event_tx
                     .send(PlayerEvent::CurrentVolume(*volume_value))?;
@@ -173,6 +517,8 @@ impl Player {
                     .send(PlayerEvent::TrackProgress(position))?;
             }
             PlayerCommand::SetPosition(duration) => {
+                self.skip_crossfade();
+
                 if let Err(err) = self.sink.try_seek(*duration) {
                     bail!("Failed to set duration: {:?}", err);
                 };
@@ -182,6 +528,16 @@ impl Player {
                 self.player_event_tx
                     .send(PlayerEvent::TrackProgress(position))?;
             }
+            PlayerCommand::SeekForward(step) => {
+                let position = (self.sink.get_pos() + *step).min(self.current_duration);
+
+                self.seek_relative(position)?;
+            }
+            PlayerCommand::SeekBackward(step) => {
+                let position = self.sink.get_pos().saturating_sub(*step);
+
+                self.seek_relative(position)?;
+            }
         }
 
         Ok(())
@@ -1,37 +1,144 @@
-use std::{fs::File, path::PathBuf, time::Duration};
+use std::{fs::File, path::Path, time::Duration};
 
-use color_eyre::{Result, eyre::ContextCompat};
+use color_eyre::{
+    Result,
+    eyre::{Context, ContextCompat},
+};
 use symphonia::{
     core::{
         codecs::{CODEC_TYPE_NULL, CodecParameters},
         formats::FormatOptions,
         io::MediaSourceStream,
-        meta::MetadataOptions,
+        meta::{MetadataOptions, MetadataRevision, StandardTagKey},
     },
     default::get_probe,
 };
 
-pub fn extract_track_metadata(file_path: &PathBuf) -> Result<CodecParameters> {
-    let file = File::open(file_path).expect("Failed to open file");
+/// Descriptive tags embedded in a track's container (ID3v2, Vorbis comments, RIFF INFO, etc).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub year: Option<i32>,
+    pub genre: Option<String>,
+}
+
+impl TrackTags {
+    /// Fills any tags that are still missing from a metadata revision.
+    /// Tags that have already been found are kept, so the first revision applied takes priority.
+    fn apply_revision(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let Some(key) = tag.std_key else {
+                continue;
+            };
+
+            // Some containers (such as RIFF INFO) pad their values with NUL bytes
+            let value = tag
+                .value
+                .to_string()
+                .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+                .to_string();
+            if value.is_empty() {
+                continue;
+            }
+
+            match key {
+                StandardTagKey::TrackTitle => {
+                    self.title.get_or_insert(value);
+                }
+                StandardTagKey::Artist => {
+                    self.artist.get_or_insert(value);
+                }
+                StandardTagKey::AlbumArtist => {
+                    self.album_artist.get_or_insert(value);
+                }
+                StandardTagKey::Album => {
+                    self.album.get_or_insert(value);
+                }
+                StandardTagKey::Genre => {
+                    self.genre.get_or_insert(value);
+                }
+                StandardTagKey::TrackNumber if self.track_number.is_none() => {
+                    self.track_number = parse_position(&value);
+                }
+                StandardTagKey::DiscNumber if self.disc_number.is_none() => {
+                    self.disc_number = parse_position(&value);
+                }
+                StandardTagKey::Date
+                | StandardTagKey::ReleaseDate
+                | StandardTagKey::OriginalDate
+                    if self.year.is_none() =>
+                {
+                    self.year = parse_year(&value);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Codec parameters of the first playable track in a file, along with its embedded tags.
+#[derive(Debug, Clone)]
+pub struct TrackMetadata {
+    pub codec_params: CodecParameters,
+    pub tags: TrackTags,
+}
+
+/// Parses positions such as `3` or `3/12` (track 3 of 12) into the leading number.
+fn parse_position(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Parses the year out of dates such as `1997`, `1997-05-21` or `1997-05-21T00:00:00`.
+fn parse_year(value: &str) -> Option<i32> {
+    value.get(..4)?.parse().ok()
+}
+
+pub fn extract_track_metadata(file_path: &Path) -> Result<TrackMetadata> {
+    let file = File::open(file_path).context(format!("Failed to open track {file_path:?}"))?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
 
-    let probed = get_probe()
+    let mut probed = get_probe()
         .format(
             &Default::default(),
             mss,
             &FormatOptions::default(),
             &MetadataOptions::default(),
         )
-        .expect("Unsupported format or read error");
+        .context(format!(
+            "Unsupported format or read error for {file_path:?}"
+        ))?;
 
-    let format = probed.format;
-    let track = format
+    let mut tags = TrackTags::default();
+
+    // Tags inside the container take priority over the ones found while probing (such as ID3v2)
+    if let Some(revision) = probed.format.metadata().current() {
+        tags.apply_revision(revision);
+    }
+    if let Some(revision) = probed
+        .metadata
+        .get()
+        .as_ref()
+        .and_then(|metadata| metadata.current())
+    {
+        tags.apply_revision(revision);
+    }
+
+    let track = probed
+        .format
         .tracks()
         .iter()
         .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
         .context(format!("No supported audio for track {file_path:?}"))?;
 
-    Ok(track.codec_params.clone())
+    Ok(TrackMetadata {
+        codec_params: track.codec_params.clone(),
+        tags,
+    })
 }
 
 pub fn extract_track_duration(codec_params: CodecParameters) -> Option<Duration> {
@@ -43,3 +150,69 @@ pub fn extract_track_duration(codec_params: CodecParameters) -> Option<Duration>
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use symphonia::core::meta::{MetadataBuilder, Tag, Value};
+
+    use super::*;
+
+    fn revision(tags: &[(StandardTagKey, &str)]) -> MetadataRevision {
+        let mut builder = MetadataBuilder::new();
+        for (key, value) in tags {
+            builder.add_tag(Tag::new(Some(*key), "", Value::from(*value)));
+        }
+        builder.metadata()
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position("3"), Some(3));
+        assert_eq!(parse_position("3/12"), Some(3));
+        assert_eq!(parse_position(" 7 /12"), Some(7));
+        assert_eq!(parse_position("/12"), None);
+        assert_eq!(parse_position("A1"), None);
+    }
+
+    #[test]
+    fn test_parse_year() {
+        assert_eq!(parse_year("1997"), Some(1997));
+        assert_eq!(parse_year("2001-05-01"), Some(2001));
+        assert_eq!(parse_year("2001-05-01T00:00:00"), Some(2001));
+        assert_eq!(parse_year(""), None);
+        assert_eq!(parse_year("   "), None);
+        assert_eq!(parse_year("soon"), None);
+        assert_eq!(parse_year("May 2001"), None);
+    }
+
+    #[test]
+    fn test_first_revision_wins() {
+        let mut tags = TrackTags::default();
+
+        tags.apply_revision(&revision(&[
+            (StandardTagKey::TrackTitle, "Container title"),
+            (StandardTagKey::TrackNumber, "3/12"),
+            (StandardTagKey::Artist, "\0\0"),
+            (StandardTagKey::Date, "unknown"),
+        ]));
+        tags.apply_revision(&revision(&[
+            (StandardTagKey::TrackTitle, "ID3 title"),
+            (StandardTagKey::TrackNumber, "4"),
+            (StandardTagKey::Artist, "ID3 artist\0"),
+            (StandardTagKey::ReleaseDate, "2001-05-01"),
+            (StandardTagKey::DiscNumber, "1/2"),
+        ]));
+
+        assert_eq!(
+            tags,
+            TrackTags {
+                title: Some("Container title".to_string()),
+                artist: Some("ID3 artist".to_string()),
+                track_number: Some(3),
+                disc_number: Some(1),
+                year: Some(2001),
+                ..Default::default()
+            }
+        );
+    }
+}
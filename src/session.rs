@@ -0,0 +1,74 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::context::Context;
+
+/// Key the session is stored under in eframe's app storage.
+pub const SESSION_STORAGE_KEY: &str = "session";
+
+/// A snapshot of what was playing when the app was last closed, so it can be picked back up on the next launch.
+///
+/// The play queue is stored in the database whenever it changes, and the layout of tabs is stored separately,
+/// so neither are part of the snapshot.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Session {
+    /// The playlist that tracks were being autoplayed from, if any
+    pub playlist_id: Option<Uuid>,
+    pub track_id: Option<Uuid>,
+    pub position_secs: f64,
+    /// Indices of the tracks that have already been picked by the pseudo random shuffler
+    pub played_tracks: BTreeSet<usize>,
+}
+
+impl Session {
+    /// Takes a snapshot of the selected playlist and track, along with how far into the track playback is.
+    pub fn capture(context: &Context) -> Self {
+        let playback = &context.playback;
+
+        Self {
+            playlist_id: context.ui.playlist.autoplay().map(|playlist| playlist.id),
+            track_id: playback
+                .selected_track
+                .as_ref()
+                .map(|track_context| track_context.track.id),
+            position_secs: playback
+                .control
+                .current_progress()
+                .map_or(0.0, |progress| progress.as_secs_f64()),
+            played_tracks: playback.selected_playlist.played_tracks(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{context::playback::SelectedTrackContext, database::models::tracks::Track};
+
+    #[test]
+    fn test_capture_selected_track_and_position() {
+        let mut context = Context::default();
+        let track = Track::default();
+
+        context
+            .playback
+            .select_track(Some(SelectedTrackContext::new(track.clone(), 3, false)));
+        context
+            .playback
+            .control
+            .set_progress(Some(Duration::from_secs(42)), None);
+        context.playback.selected_playlist.add_played_track(3);
+
+        let session = Session::capture(&context);
+
+        assert_eq!(session.track_id, Some(track.id));
+        assert_eq!(session.playlist_id, None);
+        assert_eq!(session.position_secs, 42.0);
+        assert!(session.played_tracks.contains(&3));
+    }
+}
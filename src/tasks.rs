@@ -0,0 +1,185 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum TaskKind {
+    /// Adding files to the library (and optionally a playlist)
+    Import,
+    /// Recalculating the hash of every track in the library
+    Rehash,
+    /// Checking that the file of every track in the library still exists and can be read
+    Validation,
+    /// Looking for the files of missing tracks by their hash
+    Relocation,
+    /// Bringing the library up to date with the watched folders
+    Sync,
+}
+
+impl fmt::Display for TaskKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            TaskKind::Import => "Import",
+            TaskKind::Rehash => "Rehash",
+            TaskKind::Validation => "Validation",
+            TaskKind::Relocation => "Relocation",
+            TaskKind::Sync => "Sync",
+        };
+
+        write!(f, "{label}")
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub enum TaskStatus {
+    Running,
+    Completed,
+    Cancelled,
+    /// The task could not continue at all, as opposed to individual files failing
+    Failed(String),
+}
+
+impl TaskStatus {
+    pub fn is_running(&self) -> bool {
+        matches!(self, TaskStatus::Running)
+    }
+}
+
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskStatus::Running => write!(f, "Running"),
+            TaskStatus::Completed => write!(f, "Completed"),
+            TaskStatus::Cancelled => write!(f, "Cancelled"),
+            TaskStatus::Failed(reason) => write!(f, "Failed: {reason}"),
+        }
+    }
+}
+
+/// A single file that a task was unable to process.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct TaskFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl TaskFailure {
+    pub fn new(path: PathBuf, reason: impl fmt::Display) -> Self {
+        Self {
+            path,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Identifies a running task, and allows it to be cancelled from another thread.
+///
+/// The handle is cloned into whatever is doing the work, which is expected to check
+/// [`Self::is_cancelled`] between each unit of work.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TaskHandle {
+    id: Uuid,
+    #[serde(skip)]
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    pub fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            cancelled: Arc::default(),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for TaskHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Least time between progress updates of a task, as the UI only needs a few per second
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Limits how often a task reports its progress, so a task going through many small files
+/// doesn't flood the UI with events. The first and last update always get through.
+#[derive(Debug, Default)]
+pub struct ProgressThrottle {
+    last_sent: Option<Instant>,
+}
+
+impl ProgressThrottle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the progress should be sent now, in which case the next one is held back again.
+    pub fn is_due(&mut self, completed: usize, total: usize) -> bool {
+        let is_due = completed >= total
+            || self
+                .last_sent
+                .is_none_or(|last_sent| last_sent.elapsed() >= PROGRESS_INTERVAL);
+
+        if is_due {
+            self.last_sent = Some(Instant::now());
+        }
+
+        is_due
+    }
+}
+
+/// Updates sent by whatever is doing the work of a task.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub enum TaskEvent {
+    Progress {
+        id: Uuid,
+        completed: usize,
+        total: usize,
+    },
+    Failure {
+        id: Uuid,
+        failure: TaskFailure,
+    },
+    Finished {
+        id: Uuid,
+        status: TaskStatus,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_is_throttled() {
+        let mut progress = ProgressThrottle::new();
+
+        assert!(progress.is_due(1, 10));
+        assert!(!progress.is_due(2, 10));
+        assert!(progress.is_due(10, 10));
+
+        progress.last_sent = Instant::now().checked_sub(PROGRESS_INTERVAL);
+        assert!(progress.is_due(3, 10));
+    }
+}
@@ -1,4 +1,5 @@
 pub mod formatting;
+pub mod query;
 pub mod random;
 pub mod regex;
 pub mod search;
@@ -0,0 +1,950 @@
+//! A small query language for searching tracks.
+//!
+//! Terms are separated by whitespace and all have to match, unless joined with `OR`.
+//! A term can be negated with a leading `-` or `NOT`, and grouped with parentheses.
+//! Free text is matched against track names with the configured [`MatcherFn`], while
+//! `field:value` terms (or `field<value`, `field>=value`, etc.) match a specific field:
+//!
+//! ```text
+//! artist:"daft punk" duration>4:00 -tag:live added:<30d path:/mnt/vinyl
+//! ```
+//!
+//! The free text of a query is searched for in the full text index of the database with
+//! [`Query::split_full_text`], leaving the rest of the query to be matched against the results.
+
+use std::{cmp::Ordering, collections::HashMap, ops::Range};
+
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{config::search::MatcherFn, database::models::tracks::Track};
+
+/// Names of every field that can be searched, for error messages.
+const FIELD_NAMES: &str = "name, title, artist, album_artist, album, genre, path, hash, tag, year, track, disc, duration, added, valid";
+
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum QueryErrorKind {
+    #[error("Missing a closing quote")]
+    UnclosedQuote,
+    #[error("Missing a closing parenthesis")]
+    UnclosedParen,
+    #[error("Unexpected closing parenthesis")]
+    UnmatchedParen,
+    #[error("Nothing between parentheses")]
+    EmptyGroup,
+    #[error("Expected a search term")]
+    MissingTerm,
+    #[error(
+        "Unknown field `{0}` (quote the term to search for it as text), expected one of {FIELD_NAMES}"
+    )]
+    UnknownField(String),
+    #[error("Missing a value for `{0}`")]
+    MissingValue(String),
+    #[error("Invalid value for `{field}`, expected {expected}")]
+    InvalidValue {
+        field: String,
+        expected: &'static str,
+    },
+    #[error("`{field}` can't be compared with `{operator}`")]
+    UnsupportedComparison { field: String, operator: String },
+}
+
+/// Why a query couldn't be parsed, along with the byte range of the input it's about.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{kind}")]
+pub struct QueryError {
+    pub kind: QueryErrorKind,
+    pub span: Range<usize>,
+}
+
+impl QueryError {
+    fn new(kind: QueryErrorKind, span: Range<usize>) -> Self {
+        Self { kind, span }
+    }
+
+    /// The error along with the part of the query it's about, for showing next to the query.
+    pub fn describe(&self, input: &str) -> String {
+        match input.get(self.span.clone()) {
+            Some(snippet) if !snippet.trim().is_empty() => format!("{self}: {}", snippet.trim()),
+            _ => self.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Name,
+    Title,
+    Artist,
+    AlbumArtist,
+    Album,
+    Genre,
+    Path,
+    Hash,
+    Tag,
+    Year,
+    TrackNumber,
+    DiscNumber,
+    Duration,
+    Added,
+    Valid,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        let field = match name {
+            "name" => Field::Name,
+            "title" => Field::Title,
+            "artist" => Field::Artist,
+            "album_artist" | "albumartist" => Field::AlbumArtist,
+            "album" => Field::Album,
+            "genre" => Field::Genre,
+            "path" => Field::Path,
+            "hash" => Field::Hash,
+            "tag" => Field::Tag,
+            "year" => Field::Year,
+            "track" => Field::TrackNumber,
+            "disc" => Field::DiscNumber,
+            "duration" | "length" => Field::Duration,
+            "added" => Field::Added,
+            "valid" => Field::Valid,
+            _ => return None,
+        };
+
+        Some(field)
+    }
+}
+
+/// How a field is compared against a value. `field:value` is a [`Comparison::Match`],
+/// which checks if text contains the value, and is the same as [`Comparison::Equal`] otherwise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Match,
+    Equal,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Comparison {
+    /// Reads the comparison operator at the start of the passed through text, and how many bytes it takes up.
+    fn parse_prefix(text: &str) -> (Self, usize) {
+        const OPERATORS: [(&str, Comparison); 11] = [
+            (":<=", Comparison::LessEqual),
+            (":>=", Comparison::GreaterEqual),
+            (":<", Comparison::Less),
+            (":>", Comparison::Greater),
+            (":=", Comparison::Equal),
+            (":", Comparison::Match),
+            ("<=", Comparison::LessEqual),
+            (">=", Comparison::GreaterEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ];
+
+        OPERATORS
+            .into_iter()
+            .find(|(operator, _)| text.starts_with(operator))
+            .map_or((Comparison::Match, 0), |(operator, comparison)| {
+                (comparison, operator.len())
+            })
+    }
+
+    fn ordered(&self) -> bool {
+        !matches!(self, Comparison::Match | Comparison::Equal)
+    }
+
+    /// If the ordering of a field's value to the query's value satisfies this comparison.
+    fn holds(&self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Match | Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterEqual => ordering != Ordering::Less,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum FieldValue {
+    /// Lowercased, since text is compared case-insensitively
+    Text(String),
+    Number(i64),
+    Seconds(u64),
+    /// How long ago a track was added, such as `30d`
+    Age(TimeDelta),
+    Date(NaiveDate),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// Free text, matched against the track's name
+    Text(String),
+    /// Quoted free text, which is searched for as a phrase in the full text index
+    Phrase(String),
+    Field {
+        field: Field,
+        comparison: Comparison,
+        value: FieldValue,
+    },
+}
+
+/// What a query is matched against, besides the track itself.
+pub struct QueryEnv<'a> {
+    /// Matches free text against track names, using the configured matching strategy
+    pub matcher: &'a MatcherFn,
+    /// Names of the tags assigned to each track
+    pub track_tags: HashMap<Uuid, Vec<String>>,
+    /// What relative dates, such as `added:<30d`, are relative to
+    pub now: DateTime<Utc>,
+}
+
+/// A parsed search query, which an empty input parses to one that matches every track.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    expr: Option<Expr>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        let tokens = tokenize(input)?;
+
+        if tokens.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            end: input.len(),
+        };
+
+        let expr = parser.parse_or()?;
+
+        // Anything left over can only be a closing parenthesis that was never opened
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(QueryError::new(
+                QueryErrorKind::UnmatchedParen,
+                token.span.clone(),
+            ));
+        }
+
+        Ok(Self { expr: Some(expr) })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expr.is_none()
+    }
+
+    pub fn matches(&self, track: &Track, env: &QueryEnv) -> bool {
+        self.expr
+            .as_ref()
+            .is_none_or(|expr| expr.matches(track, env))
+    }
+
+    /// Splits the query into an SQLite FTS5 match expression for the full text index of tracks,
+    /// and the rest of the query, which the tracks found in the index still have to match.
+    ///
+    /// Every term that has to match and is made of free text is searched for in the index. As FTS5
+    /// can't negate a term on its own, negated free text is only searched for if there's also
+    /// free text that isn't. Each word of free text matches words starting with it in any of the
+    /// indexed columns, while quoted text has to match as a phrase.
+    /// Without any free text to search for, the whole query is returned as the rest.
+    pub fn split_full_text(&self) -> (Option<String>, Query) {
+        let Some(expr) = &self.expr else {
+            return (None, Self::default());
+        };
+
+        let mut terms = Vec::new();
+        expr.collect_terms(&mut terms);
+
+        let (searched, rest): (Vec<_>, Vec<_>) = terms
+            .into_iter()
+            .partition(|term| !matches!(term, Expr::Not(_)) && term.full_text().is_some());
+
+        let Some(mut full_text) = searched
+            .iter()
+            .filter_map(|term| term.full_text())
+            .reduce(|lhs, rhs| format!("({lhs}) AND ({rhs})"))
+        else {
+            return (None, self.clone());
+        };
+
+        let mut filters = Vec::new();
+        for term in rest {
+            match term {
+                Expr::Not(negated) if let Some(negated) = negated.full_text() => {
+                    full_text = format!("({full_text}) NOT ({negated})");
+                }
+                _ => filters.push(term.clone()),
+            }
+        }
+
+        let expr = filters
+            .into_iter()
+            .reduce(|lhs, rhs| Expr::And(Box::new(lhs), Box::new(rhs)));
+
+        (Some(full_text), Self { expr })
+    }
+}
+
+impl Expr {
+    fn matches(&self, track: &Track, env: &QueryEnv) -> bool {
+        match self {
+            Expr::And(lhs, rhs) => lhs.matches(track, env) && rhs.matches(track, env),
+            Expr::Or(lhs, rhs) => lhs.matches(track, env) || rhs.matches(track, env),
+            Expr::Not(expr) => !expr.matches(track, env),
+            Expr::Text(text) | Expr::Phrase(text) => (env.matcher)(text, &track.name),
+            Expr::Field {
+                field,
+                comparison,
+                value,
+            } => field_matches(track, env, *field, *comparison, value),
+        }
+    }
+
+    /// Collects the terms that all have to match for this expression to match.
+    fn collect_terms<'a>(&'a self, terms: &mut Vec<&'a Expr>) {
+        if let Expr::And(lhs, rhs) = self {
+            lhs.collect_terms(terms);
+            rhs.collect_terms(terms);
+        } else {
+            terms.push(self);
+        }
+    }
+
+    fn full_text(&self) -> Option<String> {
+        match self {
+            Expr::And(lhs, rhs) => match (lhs.as_ref(), rhs.as_ref()) {
+                (Expr::Not(_), Expr::Not(_)) => None,
+                (expr, Expr::Not(negated)) | (Expr::Not(negated), expr) => Some(format!(
+                    "({}) NOT ({})",
+                    expr.full_text()?,
+                    negated.full_text()?
+                )),
+                (lhs, rhs) => Some(format!("({}) AND ({})", lhs.full_text()?, rhs.full_text()?)),
+            },
+            Expr::Or(lhs, rhs) => Some(format!("({}) OR ({})", lhs.full_text()?, rhs.full_text()?)),
+            Expr::Not(_) | Expr::Field { .. } => None,
+            Expr::Text(text) => {
+                let words: Vec<String> = full_text_words(text)
+                    .map(|word| format!("\"{word}\"*"))
+                    .collect();
+
+                (!words.is_empty()).then(|| words.join(" "))
+            }
+            Expr::Phrase(text) => {
+                let words: Vec<&str> = full_text_words(text).collect();
+
+                (!words.is_empty()).then(|| format!("\"{}\"", words.join(" ")))
+            }
+        }
+    }
+}
+
+/// The words of free text, as they're split up by the tokenizer of the full text index.
+fn full_text_words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+fn field_matches(
+    track: &Track,
+    env: &QueryEnv,
+    field: Field,
+    comparison: Comparison,
+    value: &FieldValue,
+) -> bool {
+    let text_matches = |text: &str, value: &str| {
+        let text = text.to_lowercase();
+
+        match comparison {
+            Comparison::Match => text.contains(value),
+            _ => text == value,
+        }
+    };
+
+    let number_matches = |number: Option<i64>, value: i64| {
+        number.is_some_and(|number| comparison.holds(number.cmp(&value)))
+    };
+
+    match (field, value) {
+        (Field::Name, FieldValue::Text(value)) => text_matches(&track.name, value),
+        (Field::Path, FieldValue::Text(value)) => {
+            text_matches(&track.path.to_string_lossy(), value)
+        }
+        (Field::Tag, FieldValue::Text(value)) => env
+            .track_tags
+            .get(&track.id)
+            .is_some_and(|tags| tags.iter().any(|tag| text_matches(tag, value))),
+        (
+            Field::Title
+            | Field::Artist
+            | Field::AlbumArtist
+            | Field::Album
+            | Field::Genre
+            | Field::Hash,
+            FieldValue::Text(value),
+        ) => {
+            let text = match field {
+                Field::Title => &track.title,
+                Field::Artist => &track.artist,
+                Field::AlbumArtist => &track.album_artist,
+                Field::Album => &track.album,
+                Field::Genre => &track.genre,
+                _ => &track.hash,
+            };
+
+            text.as_deref()
+                .is_some_and(|text| text_matches(text, value))
+        }
+        (Field::Year, FieldValue::Number(value)) => {
+            number_matches(track.year.map(i64::from), *value)
+        }
+        (Field::TrackNumber, FieldValue::Number(value)) => {
+            number_matches(track.track_number.map(i64::from), *value)
+        }
+        (Field::DiscNumber, FieldValue::Number(value)) => {
+            number_matches(track.disc_number.map(i64::from), *value)
+        }
+        (Field::Duration, FieldValue::Seconds(value)) => {
+            comparison.holds((track.duration_secs.max(0.0) as u64).cmp(value))
+        }
+        (Field::Added, FieldValue::Age(age)) => {
+            // Ages are checked to be in range when parsed, but the query may be matched much later
+            let since = env
+                .now
+                .checked_sub_signed(*age)
+                .unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+            // Being added less than some time ago is the same as being added after that time
+            match comparison {
+                Comparison::Greater | Comparison::GreaterEqual => track.created_at < since,
+                _ => track.created_at >= since,
+            }
+        }
+        (Field::Added, FieldValue::Date(date)) => {
+            let added = track.created_at.with_timezone(&Local).date_naive();
+
+            comparison.holds(added.cmp(date))
+        }
+        (Field::Valid, FieldValue::Bool(valid)) => track.valid == *valid,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word {
+        text: String,
+        /// Where in the text the first quote started, if any part of it was quoted
+        quoted_at: Option<usize>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Range<usize>,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        let single = |kind| Token {
+            kind,
+            span: start..start + c.len_utf8(),
+        };
+
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(single(TokenKind::LParen));
+            }
+            ')' => {
+                chars.next();
+                tokens.push(single(TokenKind::RParen));
+            }
+            // A dash on its own, like in "artist - title", is searched for as text
+            '-' if input[start + 1..]
+                .chars()
+                .next()
+                .is_some_and(|next| !next.is_whitespace() && next != ')') =>
+            {
+                chars.next();
+                tokens.push(single(TokenKind::Not));
+            }
+            _ => {
+                let mut text = String::new();
+                let mut quoted_at = None;
+                let mut end = start;
+
+                while let Some(&(index, c)) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+
+                    chars.next();
+                    end = index + c.len_utf8();
+
+                    if c != '"' {
+                        text.push(c);
+                        continue;
+                    }
+
+                    quoted_at.get_or_insert(text.len());
+
+                    let mut closed = false;
+                    for (index, c) in chars.by_ref() {
+                        end = index + c.len_utf8();
+
+                        if c == '"' {
+                            closed = true;
+                            break;
+                        }
+
+                        text.push(c);
+                    }
+
+                    if !closed {
+                        return Err(QueryError::new(
+                            QueryErrorKind::UnclosedQuote,
+                            index..input.len(),
+                        ));
+                    }
+                }
+
+                let kind = match (text.as_str(), quoted_at) {
+                    ("AND", None) => TokenKind::And,
+                    ("OR", None) => TokenKind::Or,
+                    ("NOT", None) => TokenKind::Not,
+                    _ => TokenKind::Word { text, quoted_at },
+                };
+
+                tokens.push(Token {
+                    kind,
+                    span: start..end,
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    /// Length of the input, for errors about something missing at the end
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<&Token> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+
+        Some(token)
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_and()?;
+
+        while self.peek() == Some(&TokenKind::Or) {
+            self.position += 1;
+
+            let rhs = self.parse_and()?;
+            expr = Expr::Or(Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    /// Terms next to each other have to all match, with or without an `AND` between them.
+    fn parse_and(&mut self) -> Result<Expr, QueryError> {
+        let mut expr = self.parse_unary()?;
+
+        loop {
+            match self.peek() {
+                None | Some(TokenKind::Or | TokenKind::RParen) => break,
+                Some(TokenKind::And) => self.position += 1,
+                _ => {}
+            }
+
+            let rhs = self.parse_unary()?;
+            expr = Expr::And(Box::new(expr), Box::new(rhs));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, QueryError> {
+        if self.peek() == Some(&TokenKind::Not) {
+            self.position += 1;
+
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, QueryError> {
+        let end = self.end;
+        let Some(token) = self.next().cloned() else {
+            return Err(QueryError::new(QueryErrorKind::MissingTerm, end..end));
+        };
+
+        match token.kind {
+            TokenKind::LParen => {
+                if self.peek() == Some(&TokenKind::RParen) {
+                    let span = token.span.start..self.tokens[self.position].span.end;
+                    return Err(QueryError::new(QueryErrorKind::EmptyGroup, span));
+                }
+
+                let expr = self.parse_or()?;
+
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        ..
+                    }) => Ok(expr),
+                    _ => Err(QueryError::new(QueryErrorKind::UnclosedParen, token.span)),
+                }
+            }
+            TokenKind::RParen => Err(QueryError::new(QueryErrorKind::UnmatchedParen, token.span)),
+            TokenKind::And | TokenKind::Or | TokenKind::Not => {
+                Err(QueryError::new(QueryErrorKind::MissingTerm, token.span))
+            }
+            TokenKind::Word { text, quoted_at } => parse_term(text, quoted_at, token.span),
+        }
+    }
+}
+
+/// Parses a single term, which is either free text or a `field:value` comparison.
+fn parse_term(
+    text: String,
+    quoted_at: Option<usize>,
+    span: Range<usize>,
+) -> Result<Expr, QueryError> {
+    // Anything quoted is searched for as is, so a field is only looked for before the first quote
+    let unquoted = &text[..quoted_at.unwrap_or(text.len())];
+
+    let free_text = |text| match quoted_at {
+        Some(_) => Expr::Phrase(text),
+        None => Expr::Text(text),
+    };
+
+    let Some(operator_start) = unquoted.find([':', '<', '>', '=']) else {
+        return Ok(free_text(text));
+    };
+
+    let name = &unquoted[..operator_start];
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic() || c == '_') {
+        return Ok(free_text(text));
+    }
+
+    let name = name.to_ascii_lowercase();
+    let Some(field) = Field::from_name(&name) else {
+        return Err(QueryError::new(QueryErrorKind::UnknownField(name), span));
+    };
+
+    let (comparison, operator_len) = Comparison::parse_prefix(&text[operator_start..]);
+    let operator = &text[operator_start..operator_start + operator_len];
+    let value = &text[operator_start + operator_len..];
+
+    if value.is_empty() {
+        return Err(QueryError::new(QueryErrorKind::MissingValue(name), span));
+    }
+
+    let invalid_value = |expected| {
+        QueryError::new(
+            QueryErrorKind::InvalidValue {
+                field: name.clone(),
+                expected,
+            },
+            span.clone(),
+        )
+    };
+
+    let value = match field {
+        Field::Name
+        | Field::Title
+        | Field::Artist
+        | Field::AlbumArtist
+        | Field::Album
+        | Field::Genre
+        | Field::Path
+        | Field::Hash
+        | Field::Tag
+        | Field::Valid
+            if comparison.ordered() =>
+        {
+            return Err(QueryError::new(
+                QueryErrorKind::UnsupportedComparison {
+                    field: name.clone(),
+                    operator: operator.to_string(),
+                },
+                span.clone(),
+            ));
+        }
+        Field::Name
+        | Field::Title
+        | Field::Artist
+        | Field::AlbumArtist
+        | Field::Album
+        | Field::Genre
+        | Field::Path
+        | Field::Hash
+        | Field::Tag => FieldValue::Text(value.to_lowercase()),
+        Field::Year | Field::TrackNumber | Field::DiscNumber => value
+            .parse()
+            .map(FieldValue::Number)
+            .map_err(|_| invalid_value("a whole number"))?,
+        Field::Duration => parse_duration(value)
+            .map(FieldValue::Seconds)
+            .ok_or_else(|| invalid_value("a duration such as 4:00, 240 or 4m"))?,
+        Field::Added => parse_age(value)
+            .map(FieldValue::Age)
+            .or_else(|| {
+                NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .ok()
+                    .map(FieldValue::Date)
+            })
+            .ok_or_else(|| invalid_value("an age such as 30d or a date such as 2024-01-31"))?,
+        Field::Valid => match value.to_ascii_lowercase().as_str() {
+            "yes" | "true" => FieldValue::Bool(true),
+            "no" | "false" => FieldValue::Bool(false),
+            _ => return Err(invalid_value("yes or no")),
+        },
+    };
+
+    Ok(Expr::Field {
+        field,
+        comparison,
+        value,
+    })
+}
+
+/// Parses a duration in seconds from either `[h:]m:ss`, a number of seconds,
+/// or a number followed by `s`, `m` or `h`.
+fn parse_duration(value: &str) -> Option<u64> {
+    if value.contains(':') {
+        let parts: Vec<&str> = value.split(':').collect();
+        if parts.len() > 3 {
+            return None;
+        }
+
+        return parts.into_iter().try_fold(0, |secs: u64, part| {
+            secs.checked_mul(60)?.checked_add(part.parse::<u64>().ok()?)
+        });
+    }
+
+    let (number, multiplier) = match value.char_indices().last()? {
+        (index, 's') => (&value[..index], 1),
+        (index, 'm') => (&value[..index], 60),
+        (index, 'h') => (&value[..index], 60 * 60),
+        _ => (value, 1),
+    };
+
+    number.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Parses how long ago something happened, as a number followed by `h`, `d`, `w`, `m` (30 days) or `y` (365 days).
+/// Ages reaching back further than dates can go are rejected.
+fn parse_age(value: &str) -> Option<TimeDelta> {
+    let (index, unit) = value.char_indices().last()?;
+    let number = value[..index].parse::<i64>().ok()?;
+
+    let age = match unit {
+        'h' => TimeDelta::try_hours(number),
+        'd' => TimeDelta::try_days(number),
+        'w' => TimeDelta::try_weeks(number),
+        'm' => TimeDelta::try_days(number.checked_mul(30)?),
+        'y' => TimeDelta::try_days(number.checked_mul(365)?),
+        _ => None,
+    }?;
+
+    Utc::now().checked_sub_signed(age).map(|_| age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::search::search_match_exact_lower;
+
+    fn matching(
+        query: &str,
+        tracks: &[Track],
+        track_tags: HashMap<Uuid, Vec<String>>,
+    ) -> Vec<String> {
+        let query = Query::parse(query).unwrap();
+        let matcher: MatcherFn = Box::new(search_match_exact_lower);
+        let env = QueryEnv {
+            matcher: &matcher,
+            track_tags,
+            now: Utc::now(),
+        };
+
+        tracks
+            .iter()
+            .filter(|track| query.matches(track, &env))
+            .map(|track| track.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_query_fields_and_operators() {
+        let old = Track {
+            name: "One More Time".to_string(),
+            artist: Some("Daft Punk".to_string()),
+            duration_secs: 320.0,
+            path: "/mnt/vinyl/one_more_time.flac".into(),
+            created_at: Utc::now() - TimeDelta::days(60),
+            ..Default::default()
+        };
+        let live = Track {
+            name: "Around the World (Live)".to_string(),
+            artist: Some("Daft Punk".to_string()),
+            duration_secs: 420.0,
+            path: "/mnt/vinyl/around_the_world.flac".into(),
+            ..Default::default()
+        };
+        let other = Track {
+            name: "Windowlicker".to_string(),
+            artist: Some("Aphex Twin".to_string()),
+            duration_secs: 370.0,
+            path: "/music/windowlicker.mp3".into(),
+            ..Default::default()
+        };
+        let track_tags = HashMap::from([(live.id, vec!["Live".to_string()])]);
+        let tracks = [old, live, other];
+
+        assert_eq!(
+            matching(
+                r#"artist:"daft punk" duration>4:00 -tag:live path:/mnt/vinyl"#,
+                &tracks,
+                track_tags.clone()
+            ),
+            ["One More Time"]
+        );
+        assert_eq!(
+            matching("added:<30d", &tracks, track_tags.clone()),
+            ["Around the World (Live)", "Windowlicker"]
+        );
+        assert_eq!(
+            matching(
+                "world OR (window -artist:daft)",
+                &tracks,
+                track_tags.clone()
+            ),
+            ["Around the World (Live)", "Windowlicker"]
+        );
+        assert_eq!(
+            matching("NOT duration<=6m", &tracks, track_tags),
+            ["Around the World (Live)", "Windowlicker"]
+        );
+    }
+
+    #[test]
+    fn test_query_split_full_text() {
+        let split = |query| {
+            let (full_text, rest) = Query::parse(query).unwrap().split_full_text();
+            (full_text, rest.expr)
+        };
+        let filter = |query| Query::parse(query).unwrap().expr;
+
+        assert_eq!(
+            split("daft punk"),
+            (Some(r#"("daft"*) AND ("punk"*)"#.to_string()), None)
+        );
+        assert_eq!(
+            split(r#""one more" OR -live world"#),
+            (
+                Some(r#"("one more") OR (("world"*) NOT ("live"*))"#.to_string()),
+                None
+            )
+        );
+        assert_eq!(
+            split("daft -live year:2001 -valid:no"),
+            (
+                Some(r#"("daft"*) NOT ("live"*)"#.to_string()),
+                filter("year:2001 -valid:no")
+            )
+        );
+        assert_eq!(
+            split(r#"art"ist x" (punk OR year:2001)"#),
+            (
+                Some(r#""artist x""#.to_string()),
+                filter("(punk OR year:2001)")
+            )
+        );
+        assert_eq!(split("-live"), (None, filter("-live")));
+        assert_eq!(split("year:2001"), (None, filter("year:2001")));
+        assert_eq!(split("..."), (None, filter("...")));
+        assert_eq!(split(""), (None, None));
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let error = |query| Query::parse(query).unwrap_err();
+
+        assert_eq!(error(r#"artist:"daft"#).kind, QueryErrorKind::UnclosedQuote);
+        assert_eq!(error("(a OR b").kind, QueryErrorKind::UnclosedParen);
+        assert_eq!(error("a)").kind, QueryErrorKind::UnmatchedParen);
+        assert_eq!(error("a OR").kind, QueryErrorKind::MissingTerm);
+        assert_eq!(
+            error("year:").kind,
+            QueryErrorKind::MissingValue("year".to_string())
+        );
+
+        let unknown = error("foo artist:x bpm:120");
+        assert_eq!(
+            unknown.kind,
+            QueryErrorKind::UnknownField("bpm".to_string())
+        );
+        assert_eq!(&"foo artist:x bpm:120"[unknown.span], "bpm:120");
+
+        for query in [
+            "duration>soon",
+            "duration>9999999999999999h",
+            "duration>999999999999999999:00:00",
+            "added:<1000000000d",
+            "added:<99999999999y",
+        ] {
+            assert!(
+                matches!(error(query).kind, QueryErrorKind::InvalidValue { .. }),
+                "{query}"
+            );
+        }
+        assert!(matches!(
+            error("artist>a").kind,
+            QueryErrorKind::UnsupportedComparison { .. }
+        ));
+
+        // Quoting or a dash on its own is searched for as text
+        assert!(Query::parse(r#""re:mix" - song"#).is_ok());
+        assert!(Query::parse("").unwrap().is_empty());
+    }
+}